@@ -0,0 +1,39 @@
+use nvim_oxi::{
+    self as nvim,
+    api::{self, Buffer},
+    opts::CreateAutocmdOpts,
+    types::AutocmdCallbackArgs,
+};
+
+use crate::Client;
+
+/// Sets the buffer-local autocommands needed by the client on a newly
+/// attached buffer.
+pub(crate) fn attach_buflocal(
+    client: &Client,
+    buf: Buffer,
+) -> nvim::Result<()> {
+    let insert_char_pre =
+        client.create_fn(|client, _: AutocmdCallbackArgs| {
+            super::on_insert_char_pre(client).map(|_| false)
+        });
+
+    let insert_leave = client.create_fn(|client, _: AutocmdCallbackArgs| {
+        super::on_insert_leave(client).map(|_| false)
+    });
+
+    let mut opts = CreateAutocmdOpts::builder();
+    opts.buffer(buf);
+
+    api::create_autocmd(
+        ["InsertCharPre"],
+        &opts.callback(insert_char_pre).build(),
+    )?;
+
+    api::create_autocmd(
+        ["InsertLeave"],
+        &opts.callback(insert_leave).build(),
+    )?;
+
+    Ok(())
+}
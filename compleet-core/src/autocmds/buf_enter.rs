@@ -1,15 +1,8 @@
-use nvim_oxi::{self as nvim, api::Buffer, opts::BufAttachOpts, print};
+use nvim_oxi::api::Buffer;
 
 use crate::Client;
 
 /// Called the first time the user enters a new buffer.
-pub(super) fn on_buf_enter(client: &Client, buf: Buffer) -> nvim::Result<()> {
-    print!("Just entered {buf}");
-
-    // if client.should_attach(&buf) {
-    //     let opts = BufAttachOpts::builder().on_bytes(|args| Ok(true)).build();
-    //     buf.attach(false, &opts)?;
-    // }
-
-    Ok(())
+pub(super) fn on_buf_enter(client: &Client, buf: Buffer) -> crate::Result<()> {
+    client.attach_buffer(buf)
 }
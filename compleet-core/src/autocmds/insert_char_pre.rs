@@ -0,0 +1,37 @@
+use nvim_oxi::{self as nvim, api};
+
+use crate::{snippet, Client};
+
+/// Called before a character typed in insert mode is inserted in an
+/// attached buffer. If it's one of the commit characters of the selected
+/// completion the character is held back, the completion is accepted and
+/// the character is typed again after it.
+pub(super) fn on_insert_char_pre(client: &Client) -> nvim::Result<()> {
+    let typed = api::get_vvar::<String>("char")?;
+
+    let mut chars = typed.chars();
+
+    let char = match (chars.next(), chars.next()) {
+        (Some(char), None) => char,
+        _ => return Ok(()),
+    };
+
+    if !client.is_commit_character(char) {
+        return Ok(());
+    }
+
+    // The buffer can't be modified during `InsertCharPre`, so both the
+    // acceptance and the character are fed as keys. The completions are
+    // closed once the item has been accepted, so the character is inserted
+    // normally when it's typed again.
+    api::set_vvar("char", "")?;
+
+    let key = match char {
+        '<' => "<lt>".to_owned(),
+        other => other.to_string(),
+    };
+
+    // The keys are inserted before any typeahead, e.g. when typing fast or
+    // running a macro, so that the character keeps its place.
+    snippet::feedkeys(&format!("<Plug>(compleet-accept-selected){key}"), "im")
+}
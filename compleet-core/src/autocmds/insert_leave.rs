@@ -2,6 +2,7 @@ use nvim_oxi as nvim;
 
 use crate::Client;
 
+/// Called every time the user exits insert mode in an attached buffer.
 pub(super) fn on_insert_leave(client: &Client) -> nvim::Result<()> {
-    Ok(())
+    client.stop_completing()
 }
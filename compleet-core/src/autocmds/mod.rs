@@ -1,13 +1,21 @@
+mod attach_buflocal;
 mod buf_enter;
 mod buf_new;
 mod cursor_moved_i;
+mod insert_char_pre;
 mod insert_leave;
+mod on_bytes;
 mod setup;
+mod vim_leave_pre;
 mod vim_resized;
 
+pub(crate) use attach_buflocal::attach_buflocal;
 use buf_enter::on_buf_enter;
 use buf_new::on_buf_new;
 use cursor_moved_i::on_cursor_moved_i;
+use insert_char_pre::on_insert_char_pre;
 use insert_leave::on_insert_leave;
+pub(crate) use on_bytes::on_bytes;
 pub(crate) use setup::setup;
+use vim_leave_pre::on_vim_leave_pre;
 use vim_resized::on_vim_resized;
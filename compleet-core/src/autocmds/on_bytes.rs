@@ -0,0 +1,64 @@
+use nvim_oxi::{api, opts::OnBytesArgs, types::Mode};
+
+use crate::{Client, CompletionContext, TriggerKind};
+
+/// Called on every edit in an attached buffer. Returning `true` detaches the
+/// client from the buffer.
+pub(crate) fn on_bytes(
+    client: &Client,
+    (
+        _,
+        buf,
+        changedtick,
+        start_row,
+        start_col,
+        _,
+        rows_deleted,
+        _,
+        bytes_deleted,
+        rows_added,
+        _,
+        bytes_added,
+    ): OnBytesArgs,
+) -> crate::Result<bool> {
+    if !client.is_attached(&buf) {
+        return Ok(true);
+    }
+
+    // Edits caused by inserting a completion are ignored.
+    if client.is_inserting() {
+        return Ok(false);
+    }
+
+    // We only care about insert mode events.
+    if !matches!(api::get_mode()?.mode, Mode::Insert) {
+        return Ok(false);
+    }
+
+    // If we've added or deleted a line we return early. If we've stayed on
+    // the same line but we've deleted characters we only continue if the
+    // `completion.while_deleting` option is set.
+    if rows_added != 0
+        || rows_deleted != 0
+        || (bytes_deleted != 0 && !client.config().complete_while_deleting())
+    {
+        client.stop_completing()?;
+        return Ok(false);
+    }
+
+    let col = start_col + if bytes_deleted != 0 { 0 } else { bytes_added };
+    let ctx = {
+        let config = client.config();
+        CompletionContext::from_buffer(
+            buf,
+            start_row,
+            col,
+            TriggerKind::Automatic,
+            |filetype| config.keyword_pattern(filetype),
+        )?
+    };
+
+    client.fetch_completions(ctx, changedtick)?;
+
+    Ok(false)
+}
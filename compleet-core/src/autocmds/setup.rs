@@ -12,10 +12,19 @@ pub(crate) fn setup(client: &Client) -> nvim::Result<()> {
         super::on_buf_new(client, args.buffer).map(|_| false)
     });
 
+    let vim_leave_pre = client.create_fn(|client, _: AutocmdCallbackArgs| {
+        super::on_vim_leave_pre(client).map(|_| false)
+    });
+
     api::create_autocmd(
         ["BufNew"],
         &CreateAutocmdOpts::builder().callback(buf_new).build(),
     )?;
 
+    api::create_autocmd(
+        ["VimLeavePre"],
+        &CreateAutocmdOpts::builder().callback(vim_leave_pre).build(),
+    )?;
+
     Ok(())
 }
@@ -0,0 +1,6 @@
+use crate::Client;
+
+/// Called right before exiting Neovim.
+pub(super) fn on_vim_leave_pre(client: &Client) -> crate::Result<()> {
+    client.save_frecency()
+}
@@ -0,0 +1,58 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::Notify;
+
+/// A token passed to [`CompletionSource::complete`] that's cancelled when
+/// the completions being computed are no longer needed, e.g. because the
+/// user started typing a new word, left insert mode or the source took too
+/// long to respond.
+///
+/// Once a token has been cancelled the results returned by the source are
+/// discarded, so sources should stop as soon as possible and clean up any
+/// outstanding work (e.g. cancel the requests sent to a language server).
+///
+/// [`CompletionSource::complete`]: crate::CompletionSource::complete
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<Inner>);
+
+#[derive(Debug, Default)]
+struct Inner {
+    is_cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancellationToken {
+    #[inline]
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the token, waking up all the tasks waiting on
+    /// [`cancelled`](Self::cancelled).
+    pub(crate) fn cancel(&self) {
+        if !self.0.is_cancelled.swap(true, Ordering::AcqRel) {
+            self.0.notify.notify_waiters();
+        }
+    }
+
+    /// Returns a future that completes once the token has been cancelled.
+    pub async fn cancelled(&self) {
+        // The `Notified` future receives the notifications sent after it's
+        // created, even if it hasn't been polled yet, so we can't miss a
+        // cancellation happening between the check and the `await`.
+        let notified = self.0.notify.notified();
+
+        if self.is_cancelled() {
+            return;
+        }
+
+        notified.await
+    }
+
+    /// Whether the token has been cancelled.
+    #[inline]
+    pub fn is_cancelled(&self) -> bool {
+        self.0.is_cancelled.load(Ordering::Acquire)
+    }
+}
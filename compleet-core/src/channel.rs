@@ -0,0 +1,403 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use nvim_oxi::{self as nvim, libuv::AsyncHandle, Function};
+use tokio::{
+    runtime::{Builder as RuntimeBuilder, Runtime},
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    task::JoinHandle,
+    time,
+};
+
+use crate::completion_source::{ErasedSource, SourceResult};
+use crate::stats::Outcome;
+use crate::{
+    CancellationToken,
+    CompletionContext,
+    CompletionItem,
+    CompletionList,
+    Config,
+    ResolvedItem,
+};
+
+/// How long a source is given to wind down after its request has been
+/// cancelled before the task polling it is dropped, or aborted if it's still
+/// running.
+const CANCELLATION_GRACE: Duration = Duration::from_millis(500);
+
+/// The message sent by a task once the source it's polling has finished
+/// computing its completions, or once its request has been cancelled.
+pub(crate) struct Message {
+    /// The name of the source that computed the completions.
+    pub(crate) source: &'static str,
+
+    /// The id of the completion session the completions were requested in.
+    pub(crate) session: u64,
+
+    /// The `changedtick` of the buffer when the completions were requested.
+    pub(crate) changedtick: u32,
+
+    /// The context the completions were requested in.
+    pub(crate) ctx: Arc<CompletionContext>,
+
+    /// How the request ended.
+    pub(crate) outcome: Outcome,
+
+    /// The result returned by the source, or `None` if the request was
+    /// cancelled or timed out.
+    pub(crate) completions: Option<SourceResult<CompletionList>>,
+}
+
+/// The message sent by the task resolving a completion item once the
+/// source has responded.
+pub(crate) struct Resolution {
+    /// The name of the source that resolved the item.
+    pub(crate) source: &'static str,
+
+    /// The item that was resolved.
+    pub(crate) item: Arc<CompletionItem>,
+
+    /// The result returned by the source.
+    pub(crate) resolved: SourceResult<Option<ResolvedItem>>,
+}
+
+/// A task computing the completions of a single source, or resolving a
+/// completion item.
+struct Task {
+    handle: JoinHandle<()>,
+
+    /// Cancelled to ask the source to stop computing its completions.
+    cancel: CancellationToken,
+
+    /// Set once the task's request has ended. The task can keep running for
+    /// a while after that to give a cancelled source time to clean up.
+    reported: Arc<AtomicBool>,
+}
+
+impl Task {
+    /// Cancels the task, giving the source some time to clean up before
+    /// the task is aborted in case the source doesn't observe the
+    /// cancellation.
+    fn cancel(self, runtime: &Runtime) {
+        self.cancel.cancel();
+
+        let handle = self.handle;
+
+        runtime.spawn(async move {
+            time::sleep(CANCELLATION_GRACE).await;
+            handle.abort();
+        });
+    }
+}
+
+/// A channel used to communicate with the tokio threadpool where the
+/// completion results are computed.
+pub(crate) struct Channel {
+    receiver: UnboundedReceiver<Message>,
+
+    runtime: Runtime,
+
+    sender: UnboundedSender<Message>,
+
+    /// Used to wake up Neovim's event loop when a task has sent its
+    /// completions.
+    signal: Arc<AsyncHandle>,
+
+    /// The last task spawned for every source, keyed by the source's name.
+    tasks: HashMap<&'static str, Task>,
+
+    resolved_receiver: UnboundedReceiver<Resolution>,
+
+    resolved_sender: UnboundedSender<Resolution>,
+
+    /// The task resolving the currently selected completion item, if any.
+    resolve_task: Option<Task>,
+}
+
+impl Channel {
+    /// Creates a new [`Channel`]. The `on_completions` function will be
+    /// scheduled on Neovim's main thread every time a source has sent its
+    /// completions or has resolved an item.
+    pub(crate) fn new(on_completions: Function<(), ()>) -> nvim::Result<Self> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let (resolved_sender, resolved_receiver) = mpsc::unbounded_channel();
+
+        let signal = AsyncHandle::new(move || {
+            let on_completions = on_completions.clone();
+            nvim::schedule(move |_| on_completions.call(()));
+            Ok::<_, nvim::Error>(())
+        })?;
+
+        let runtime = RuntimeBuilder::new_multi_thread()
+            .enable_all()
+            .build()
+            .expect("couldn't create tokio runtime");
+
+        Ok(Self {
+            receiver,
+            runtime,
+            sender,
+            signal: Arc::new(signal),
+            tasks: HashMap::new(),
+            resolved_receiver,
+            resolved_sender,
+            resolve_task: None,
+        })
+    }
+
+    /// Spawns a new task for every source, each one computing the
+    /// completions for the given context. The tasks still running for the
+    /// same sources are cancelled.
+    ///
+    /// The sources are only queried after the debounce interval set in the
+    /// `config`, and their requests are cancelled if they don't respond
+    /// within their timeout.
+    pub(crate) fn fetch_completions<'a, S>(
+        &mut self,
+        sources: S,
+        ctx: Arc<CompletionContext>,
+        session: u64,
+        changedtick: u32,
+        config: &Config,
+    ) where
+        S: IntoIterator<Item = &'a Arc<dyn ErasedSource>>,
+    {
+        let debounce = config.debounce();
+
+        for source in sources {
+            let source = Arc::clone(source);
+            let ctx = Arc::clone(&ctx);
+            let sender = self.sender.clone();
+            let signal = Arc::clone(&self.signal);
+            let cancel = CancellationToken::new();
+            let reported = Arc::new(AtomicBool::new(false));
+
+            let name = source.name();
+            let timeout = config.source_timeout(name);
+
+            let handle = self.runtime.spawn({
+                let cancel = cancel.clone();
+                let reported = Arc::clone(&reported);
+
+                async move {
+                    let request = Arc::clone(&ctx);
+
+                    let report = move |outcome, completions| {
+                        reported.store(true, Ordering::Release);
+
+                        let msg = Message {
+                            source: name,
+                            session,
+                            changedtick,
+                            ctx: request,
+                            outcome,
+                            completions,
+                        };
+
+                        // The receiver is only dropped when the channel is,
+                        // in which case nobody is interested in the results
+                        // anymore.
+                        if sender.send(msg).is_ok() {
+                            let _ = signal.send();
+                        }
+                    };
+
+                    self::complete(
+                        &*source, &ctx, &cancel, debounce, timeout, report,
+                    )
+                    .await
+                }
+            });
+
+            let task = Task { handle, cancel, reported };
+
+            if let Some(old) = self.tasks.insert(name, task) {
+                old.cancel(&self.runtime);
+            }
+        }
+    }
+
+    /// Cancels the task resolving the last selected completion item, if
+    /// it's still running.
+    pub(crate) fn cancel_resolve(&mut self) {
+        if let Some(task) = self.resolve_task.take() {
+            task.cancel(&self.runtime);
+        }
+    }
+
+    /// Whether the source named `source` is still computing its
+    /// completions, i.e. its last request hasn't completed, failed, timed
+    /// out or been cancelled yet.
+    pub(crate) fn is_pending(&self, source: &str) -> bool {
+        self.tasks
+            .get(source)
+            .map_or(false, |task| !task.reported.load(Ordering::Acquire))
+    }
+
+    /// Returns an iterator over all the messages received since the last
+    /// time this was called.
+    pub(crate) fn recv_all(&mut self) -> impl Iterator<Item = Message> + '_ {
+        std::iter::from_fn(|| self.receiver.try_recv().ok())
+    }
+
+    /// Returns an iterator over all the items resolved since the last time
+    /// this was called.
+    pub(crate) fn recv_resolved(
+        &mut self,
+    ) -> impl Iterator<Item = Resolution> + '_ {
+        std::iter::from_fn(|| self.resolved_receiver.try_recv().ok())
+    }
+
+    /// Spawns a new task asking `source` to resolve `item`, cancelling the
+    /// one resolving the previously selected item.
+    ///
+    /// The source is only queried after the debounce interval of the
+    /// details window set in the `config`, and its request is cancelled if
+    /// it doesn't respond within its timeout.
+    pub(crate) fn resolve(
+        &mut self,
+        source: Arc<dyn ErasedSource>,
+        item: Arc<CompletionItem>,
+        config: &Config,
+    ) {
+        self.cancel_resolve();
+
+        let sender = self.resolved_sender.clone();
+        let signal = Arc::clone(&self.signal);
+        let cancel = CancellationToken::new();
+        let reported = Arc::new(AtomicBool::new(false));
+
+        let name = source.name();
+        let debounce = config.details_debounce();
+        let timeout = config.source_timeout(name);
+
+        let handle = self.runtime.spawn({
+            let cancel = cancel.clone();
+            let reported = Arc::clone(&reported);
+
+            async move {
+                let resolved = match self::resolve(
+                    &*source, &item, &cancel, debounce, timeout,
+                )
+                .await
+                {
+                    Some(resolved) => resolved,
+                    None => return,
+                };
+
+                let msg = Resolution { source: name, item, resolved };
+
+                reported.store(true, Ordering::Release);
+
+                if sender.send(msg).is_ok() {
+                    let _ = signal.send();
+                }
+            }
+        });
+
+        self.resolve_task = Some(Task { handle, cancel, reported });
+    }
+
+    /// Cancels all the tasks that are still computing completions or
+    /// resolving an item.
+    pub(crate) fn stop_tasks(&mut self) {
+        let runtime = &self.runtime;
+        self.tasks.drain().for_each(|(_, task)| task.cancel(runtime));
+        self.cancel_resolve();
+    }
+}
+
+/// Waits for the `debounce` interval, then queries the source for its
+/// completions. How the request ended is passed to `report` together with
+/// the source's result, which is `None` if the request was cancelled or if
+/// the source didn't respond within `timeout`.
+///
+/// Requests cancelled before the end of the debounce interval are never
+/// sent to the source, and they're reported as debounced.
+async fn complete<F>(
+    source: &dyn ErasedSource,
+    ctx: &CompletionContext,
+    cancel: &CancellationToken,
+    debounce: Duration,
+    timeout: Duration,
+    report: F,
+) where
+    F: FnOnce(Outcome, Option<SourceResult<CompletionList>>),
+{
+    if !debounce.is_zero() {
+        tokio::select! {
+            _ = cancel.cancelled() => {
+                return report(Outcome::Debounced, None);
+            },
+            _ = time::sleep(debounce) => {},
+        }
+    }
+
+    let start = Instant::now();
+
+    let completions = source.complete(ctx, cancel);
+    tokio::pin!(completions);
+
+    let outcome = tokio::select! {
+        completions = &mut completions => {
+            let latency = start.elapsed();
+
+            let outcome = match &completions {
+                Ok(list) => {
+                    Outcome::Completed { items: list.items.len(), latency }
+                },
+                Err(_) => Outcome::Failed { latency },
+            };
+
+            return report(outcome, Some(completions));
+        },
+
+        _ = cancel.cancelled() => Outcome::Cancelled,
+
+        _ = time::sleep(timeout) => {
+            cancel.cancel();
+            Outcome::TimedOut
+        },
+    };
+
+    // The outcome is reported right away since the task could be aborted
+    // during the grace period below.
+    report(outcome, None);
+
+    // Keep polling the source for a while so that it can observe the
+    // cancellation and clean up after itself. Its results are discarded.
+    let _ = time::timeout(CANCELLATION_GRACE, completions).await;
+}
+
+/// Waits for the `debounce` interval, then asks the source to resolve
+/// `item`. Returns `None` if the request was cancelled or if the source
+/// didn't respond within `timeout`.
+async fn resolve(
+    source: &dyn ErasedSource,
+    item: &CompletionItem,
+    cancel: &CancellationToken,
+    debounce: Duration,
+    timeout: Duration,
+) -> Option<SourceResult<Option<ResolvedItem>>> {
+    tokio::select! {
+        _ = cancel.cancelled() => return None,
+        _ = time::sleep(debounce) => {},
+    }
+
+    let resolved = source.resolve(item, cancel);
+    tokio::pin!(resolved);
+
+    tokio::select! {
+        resolved = &mut resolved => return Some(resolved),
+        _ = cancel.cancelled() => {},
+        _ = time::sleep(timeout) => cancel.cancel(),
+    }
+
+    // Same as in `complete`, the source is given some time to observe the
+    // cancellation.
+    let _ = time::timeout(CANCELLATION_GRACE, resolved).await;
+
+    None
+}
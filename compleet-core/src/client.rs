@@ -1,9 +1,14 @@
-use std::cell::RefCell;
+use std::cell::{Ref, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 use std::rc::Rc;
 use std::sync::Arc;
 
 use nvim_oxi::{
-    api::Buffer,
+    self as nvim,
+    api::{self, Buffer, Window},
+    opts::BufAttachOpts,
+    types::Mode,
     Dictionary,
     FromObject,
     Function,
@@ -11,8 +16,19 @@ use nvim_oxi::{
     ToObject,
 };
 
-use crate::{messages, setup};
-use crate::{CompletionSource, Config, Error};
+use crate::channel::{Channel, Resolution};
+use crate::completion_source::{ErasedSource, ShouldAttach, SourceResult};
+use crate::frecency::{self, Frecency};
+use crate::lua_source::{self, LuaSource};
+use crate::mappings::IdentifyCompletion;
+use crate::matched_completion::MatchedCompletion;
+use crate::session::Session;
+use crate::snippet::{self, Expansion, Snippet};
+use crate::stats::Stats;
+use crate::ui::Ui;
+use crate::{autocmds, health, messages, ranking, setup};
+use crate::{CompletionContext, CompletionItem, CompletionSource, Config};
+use crate::{Error, TriggerKind};
 
 #[derive(Default)]
 pub struct Client(Rc<RefCell<State>>);
@@ -22,10 +38,160 @@ pub(crate) struct State {
     /// The id of the `Compleet` augroup if currently set, `None` otherwise.
     augroup_id: Option<u32>,
 
+    /// Maps the buffers the client is attached to to the completion sources
+    /// enabled in them.
+    attached_buffers: HashMap<Buffer, AttachedBuffer>,
+
+    /// A channel used to communicate with the threadpool where the
+    /// completion results are computed. Only set after the
+    /// [`setup`](setup::setup) function has been called.
+    channel: Option<Channel>,
+
+    /// The currently available completion items, ranked from best to
+    /// worst.
+    completions: Vec<MatchedCompletion>,
+
+    /// The context of the last edit that caused the completion sources to be
+    /// queried.
+    ctx: Option<Arc<CompletionContext>>,
+
+    /// The current configuration.
+    config: Config,
+
     /// Whether the [`setup`](setup::setup) function has ever been called.
     did_setup: bool,
 
-    sources: Vec<Arc<dyn CompletionSource>>,
+    /// The completions accepted in this and previous sessions.
+    frecency: Frecency,
+
+    /// Whether completion has been stopped in all the buffers with
+    /// `:CompleetStop!`.
+    is_stopped: bool,
+
+    /// Whether the client is currently inserting a completion in the
+    /// buffer. Used to avoid querying the sources for the edits caused by
+    /// the insertion itself.
+    is_inserting: bool,
+
+    /// The current completion session, if any.
+    session: Option<Session>,
+
+    /// The last inserted snippet if its tabstops can still be jumped to.
+    snippet: Option<Snippet>,
+
+    sources: Vec<Arc<dyn ErasedSource>>,
+
+    /// Statistics about the requests made to the sources.
+    stats: Stats,
+
+    /// The names of the sources stopped in all the buffers with
+    /// `:CompleetStop! <sources>`.
+    stopped_sources: HashSet<&'static str>,
+
+    /// The UI used to display the completions. Only set after the
+    /// [`setup`](setup::setup) function has been called.
+    ui: Option<Ui>,
+}
+
+/// A buffer the client is attached to.
+struct AttachedBuffer {
+    /// The completion sources that attached to the buffer.
+    sources: Vec<Arc<dyn ErasedSource>>,
+
+    /// Whether completion has been stopped in the buffer with
+    /// `:CompleetStop`.
+    is_stopped: bool,
+
+    /// The names of the sources stopped in the buffer with `:CompleetStop
+    /// <sources>`.
+    stopped_sources: HashSet<&'static str>,
+}
+
+impl AttachedBuffer {
+    fn new(sources: Vec<Arc<dyn ErasedSource>>) -> Self {
+        Self { sources, is_stopped: false, stopped_sources: HashSet::new() }
+    }
+
+    /// Returns the sources that haven't been stopped either in this buffer
+    /// or in all the buffers.
+    fn running_sources<'a>(
+        &'a self,
+        stopped_globally: &'a HashSet<&'static str>,
+    ) -> impl Iterator<Item = &'a Arc<dyn ErasedSource>> + 'a {
+        self.sources.iter().filter(move |source| {
+            let name = source.name();
+            !self.stopped_sources.contains(name)
+                && !stopped_globally.contains(name)
+        })
+    }
+}
+
+impl State {
+    /// Filters the results cached in the current session against the
+    /// current prefix, ranks them and displays them in the UI.
+    fn update_completions(&mut self) -> nvim::Result<()> {
+        let (ctx, session) = match (&self.ctx, &self.session) {
+            (Some(ctx), Some(session)) => (ctx, session),
+            _ => return Ok(()),
+        };
+
+        let selected = self
+            .ui
+            .as_ref()
+            .and_then(Ui::selected_index)
+            .map(|idx| Arc::clone(&self.completions[idx].item));
+
+        self.completions =
+            session.matches(ctx, &self.frecency, frecency::now());
+
+        let config = &self.config;
+
+        ranking::rank(&mut self.completions, config.comparators(), |source| {
+            config.source_priority(source)
+        });
+
+        // Results can stream in after the user has already selected an item,
+        // which stays selected as long as it's still being shown.
+        let selected = selected
+            .and_then(|item| ranking::position(&self.completions, &item));
+
+        match self.ui.as_mut() {
+            Some(ui) => ui.update(&self.completions, ctx.prefix(), selected),
+            None => Ok(()),
+        }
+    }
+
+    /// Replaces the cached `old` item with its resolved version, updating
+    /// the details window if it's the selected one.
+    fn replace_resolved(
+        &mut self,
+        old: &Arc<CompletionItem>,
+        new: CompletionItem,
+    ) -> nvim::Result<()> {
+        let new = Arc::new(new);
+
+        if let Some(session) = self.session.as_mut() {
+            session.replace(old, &new);
+        }
+
+        for completion in &mut self.completions {
+            if Arc::ptr_eq(&completion.item, old) {
+                completion.item = Arc::clone(&new);
+            }
+        }
+
+        let ui = match self.ui.as_mut() {
+            Some(ui) => ui,
+            None => return Ok(()),
+        };
+
+        match ui.selected_index().map(|idx| &self.completions[idx]) {
+            Some(selected) if Arc::ptr_eq(&selected.item, &new) => {
+                ui.show_details(new.details())
+            },
+            _ => Ok(()),
+        }
+    }
 }
 
 impl From<&Rc<RefCell<State>>> for Client {
@@ -40,9 +206,70 @@ impl Client {
         self.0.borrow().did_setup
     }
 
+    /// Attaches the client to `buf` if at least one of the registered
+    /// completion sources is enabled for it, does nothing otherwise.
+    pub(crate) fn attach_buffer(&self, buf: Buffer) -> crate::Result<()> {
+        if self.is_attached(&buf) {
+            return Ok(());
+        }
+
+        let sources = self.sources_for(&buf)?;
+
+        if sources.is_empty() {
+            return Ok(());
+        }
+
+        let on_bytes = self.create_fn(autocmds::on_bytes);
+        let opts = BufAttachOpts::builder().on_bytes(on_bytes).build();
+        buf.attach(false, &opts)?;
+
+        autocmds::attach_buflocal(self, buf)?;
+
+        self.0
+            .borrow_mut()
+            .attached_buffers
+            .insert(buf, AttachedBuffer::new(sources));
+
+        Ok(())
+    }
+
+    /// Returns the buffers the client is currently attached to.
+    pub(crate) fn attached_buffers(&self) -> Vec<Buffer> {
+        self.0.borrow().attached_buffers.keys().cloned().collect()
+    }
+
     /// Returns a [`Dictionary`] representing the public API of the plugin.
     pub fn build_api(&self) -> Dictionary {
-        Dictionary::from_iter([("setup", Object::from(self.setup()))])
+        let check_health = self.create_fn(health::check);
+        let register_source = self.create_fn(lua_source::register_source);
+        let snippet_jumpable = self.create_fn(|client, forward| {
+            Ok::<_, Error>(client.snippet_jumpable(forward))
+        });
+
+        Dictionary::from_iter([
+            ("check_health", Object::from(check_health)),
+            ("register_source", Object::from(register_source)),
+            ("setup", Object::from(self.setup())),
+            ("snippet_jumpable", Object::from(snippet_jumpable)),
+        ])
+    }
+
+    /// Returns the completion identified by `which` together with the
+    /// context it was computed for, or `None` if there's no such completion.
+    pub(crate) fn completion(
+        &self,
+        which: IdentifyCompletion,
+    ) -> Option<(MatchedCompletion, Arc<CompletionContext>)> {
+        let state = &*self.0.borrow();
+        let ctx = state.ctx.as_ref()?;
+        let selected = state.ui.as_ref().and_then(Ui::selected_index);
+        let index = which.index(selected, state.completions.len())?;
+        Some((state.completions[index].clone(), Arc::clone(ctx)))
+    }
+
+    /// Returns the current configuration.
+    pub(crate) fn config(&self) -> Ref<'_, Config> {
+        Ref::map(self.0.borrow(), |state| &state.config)
     }
 
     pub(crate) fn create_fn<F, A, R, E>(&self, fun: F) -> Function<A, R>
@@ -74,31 +301,752 @@ impl Client {
         self.0.borrow_mut().did_setup = true;
     }
 
+    /// Queries the completion sources attached to the buffer of `ctx`.
+    ///
+    /// If `ctx` is still editing the word of the current completion session
+    /// only the sources whose last results were incomplete are queried
+    /// again, and the cached results are filtered against the new prefix.
+    ///
+    /// If there's no keyword before the cursor only the sources with a
+    /// trigger character right before the cursor are queried, and the
+    /// current completions are cleared if there are none.
+    pub(crate) fn fetch_completions(
+        &self,
+        mut ctx: CompletionContext,
+        changedtick: u32,
+    ) -> crate::Result<()> {
+        // The options of a snippet's choice are filtered against all the
+        // text typed since the start of the tabstop.
+        {
+            let state = &mut *self.0.borrow_mut();
+
+            if let Some(session) = state.session.as_ref().filter(|session| {
+                session.is_snippet_choice() && session.contains(&ctx)
+            }) {
+                ctx.set_prefix_start(session.prefix_start());
+                state.ctx = Some(Arc::new(ctx));
+                return Ok(state.update_completions()?);
+            }
+        }
+
+        let mut sources = self.running_sources(ctx.buffer());
+
+        if sources.is_empty() {
+            return Ok(());
+        }
+
+        if ctx.prefix().is_empty()
+            && ctx.trigger_kind() == TriggerKind::Automatic
+        {
+            let mut trigger = None;
+            let mut triggered = Vec::new();
+
+            for source in sources {
+                let name = source.name();
+
+                let triggers = source
+                    .trigger_characters(ctx.buffer())
+                    .map_err(|err| Error::SourceError { name, err })?;
+
+                if let Some(char) = ctx.triggered_by(&triggers) {
+                    trigger = Some(char);
+                    triggered.push(source);
+                }
+            }
+
+            match trigger {
+                Some(char) => {
+                    ctx.set_trigger_kind(TriggerKind::TriggerCharacter(char))
+                },
+                None => return Ok(self.stop_completing()?),
+            }
+
+            sources = triggered;
+        }
+
+        let is_new_session = {
+            let state = &mut *self.0.borrow_mut();
+
+            match &state.session {
+                Some(session) if session.contains(&ctx) => false,
+
+                _ => {
+                    let channel =
+                        state.channel.as_mut().expect("already setup");
+                    channel.stop_tasks();
+                    state.session = Some(Session::new(&ctx));
+                    true
+                },
+            }
+        };
+
+        let mut should_recompute = Vec::new();
+
+        for source in &sources {
+            let name = source.name();
+
+            let is_stale = {
+                let state = &*self.0.borrow();
+                let channel = state.channel.as_ref().expect("already setup");
+                state.session.as_ref().map_or(false, |session| {
+                    session.is_incomplete(name)
+                        || (!session.has_results(name)
+                            && !channel.is_pending(name))
+                })
+            };
+
+            // The state isn't borrowed while the source runs, since it could
+            // call back into the client.
+            let recompute = is_stale
+                || (!is_new_session
+                    && source
+                        .on_edit(&ctx)
+                        .map_err(|err| Error::SourceError { name, err })?);
+
+            if recompute {
+                should_recompute.push(source);
+            }
+        }
+
+        let state = &mut *self.0.borrow_mut();
+
+        let channel = state.channel.as_mut().expect("already setup");
+
+        // A source could have stopped completing from one of its callbacks.
+        let session = match &state.session {
+            Some(session) => session,
+            None => return Ok(()),
+        };
+
+        let ctx = Arc::new(ctx);
+
+        channel.fetch_completions(
+            should_recompute,
+            Arc::clone(&ctx),
+            session.id(),
+            changedtick,
+            &state.config,
+        );
+
+        state.ctx = Some(ctx);
+        state.update_completions()?;
+
+        Ok(())
+    }
+
+    /// Returns the completions accepted in this and previous sessions.
+    pub(crate) fn frecency(&self) -> Ref<'_, Frecency> {
+        Ref::map(self.0.borrow(), |state| &state.frecency)
+    }
+
+    /// Whether the client is currently attached to `buf`.
+    #[inline]
+    pub(crate) fn is_attached(&self, buf: &Buffer) -> bool {
+        self.0.borrow().attached_buffers.contains_key(buf)
+    }
+
+    /// Whether the source named `source` is currently providing completions
+    /// in `buf`, i.e. it's attached to it and it hasn't been stopped.
+    pub(crate) fn is_running(&self, buf: &Buffer, source: &str) -> bool {
+        self.running_sources(buf).iter().any(|s| s.name() == source)
+    }
+
+    /// Whether `char` is one of the commit characters of the selected
+    /// completion, i.e. whether typing it should accept the completion.
+    /// Snippets are never accepted this way, since the character would end
+    /// up in their first placeholder.
+    pub(crate) fn is_commit_character(&self, char: char) -> bool {
+        let state = &*self.0.borrow();
+
+        state.ui.as_ref().and_then(Ui::selected_index).map_or(false, |idx| {
+            let item = &state.completions[idx].item;
+            !item.is_snippet && item.commit_characters.contains(&char)
+        })
+    }
+
+    /// Whether the client is currently inserting a completion in the buffer.
+    #[inline]
+    pub(crate) fn is_inserting(&self) -> bool {
+        self.0.borrow().is_inserting
+    }
+
+    /// Jumps to the next tabstop of the current snippet if `forward` is set,
+    /// or to the previous one otherwise. The snippet ends when its final
+    /// tabstop is reached.
+    pub(crate) fn jump_snippet(&self, forward: bool) -> crate::Result<()> {
+        if !self.snippet_jumpable(forward) {
+            return Ok(());
+        }
+
+        let mut snippet =
+            self.0.borrow_mut().snippet.take().expect("snippet is jumpable");
+
+        self.stop_completing()?;
+        self.while_inserting(|| snippet.jump(forward))?;
+
+        let has_choices = !snippet.choices().is_empty();
+
+        // The snippet is kept around until its choices have been shown.
+        if snippet.is_finished() && !has_choices {
+            snippet.end()?;
+        } else {
+            self.0.borrow_mut().snippet = Some(snippet);
+        }
+
+        if !has_choices {
+            return Ok(());
+        }
+
+        // If the jump had to leave insert mode the cursor is only moved
+        // after this function returns, so the choices are shown by a
+        // mapping that runs after that.
+        if matches!(api::get_mode()?.mode, Mode::Insert) {
+            self.show_snippet_choices()
+        } else {
+            Ok(snippet::feedkeys("<Plug>(compleet-snippet-choices)", "m")?)
+        }
+    }
+
     #[inline]
     /// Creates a new [`Client`].
     pub fn new() -> Self {
         Self::default()
     }
 
+    /// Called on the main thread every time a completion source has sent its
+    /// results or has resolved an item.
+    pub(crate) fn on_completions(&self) -> crate::Result<()> {
+        let state = &mut *self.0.borrow_mut();
+
+        let channel = match state.channel.as_mut() {
+            Some(channel) => channel,
+            None => return Ok(()),
+        };
+
+        let mut has_new = false;
+
+        for msg in channel.recv_all() {
+            state.stats.record_request(
+                msg.source,
+                msg.ctx.filetype(),
+                msg.outcome,
+            );
+
+            // Discard the results requested in previous sessions.
+            let session = match state.session.as_mut() {
+                Some(session) if session.id() == msg.session => session,
+                _ => continue,
+            };
+
+            match msg.completions {
+                // The request was cancelled or timed out.
+                None => {},
+
+                Some(Ok(list)) => {
+                    has_new |= session.insert(
+                        msg.source,
+                        list,
+                        msg.changedtick,
+                        msg.ctx,
+                    )
+                },
+
+                Some(Err(err)) => messages::echowarn!(
+                    "{}",
+                    Error::SourceError { name: msg.source, err }
+                ),
+            }
+        }
+
+        let resolutions = channel.recv_resolved().collect::<Vec<_>>();
+
+        if has_new {
+            state.update_completions()?;
+        }
+
+        for Resolution { source, item, resolved } in resolutions {
+            // Failed items are still marked as resolved so that they're not
+            // resolved again every time they're selected.
+            let resolved = resolved.unwrap_or_else(|err| {
+                messages::echowarn!(
+                    "{}",
+                    Error::SourceError { name: source, err }
+                );
+                None
+            });
+
+            state.replace_resolved(&item, item.resolved(resolved))?;
+        }
+
+        Ok(())
+    }
+
+    /// Records that `completion` has been accepted in the context `ctx`,
+    /// also learning from it if the user enabled frecency.
+    pub(crate) fn record_accepted(
+        &self,
+        completion: &MatchedCompletion,
+        ctx: &CompletionContext,
+    ) {
+        // The options of a snippet's choices aren't learned.
+        if completion.source == snippet::CHOICES_SOURCE {
+            return;
+        }
+
+        let state = &mut *self.0.borrow_mut();
+
+        state.stats.record_accepted(completion.source, ctx.filetype());
+
+        if state.config.frecency_enabled() {
+            state.frecency.record(
+                ctx.filetype(),
+                completion.source,
+                ctx.prefix(),
+                completion.item.text(),
+                frecency::now(),
+            );
+        }
+    }
+
+    /// Returns an error if a source named `name` has already been
+    /// registered.
+    pub(crate) fn check_source_name(&self, name: &str) -> crate::Result<()> {
+        if self.0.borrow().sources.iter().any(|s| s.name() == name) {
+            return Err(Error::DuplicateSource(name.to_owned()));
+        }
+        Ok(())
+    }
+
+    /// Registers a new completion source. Its configuration will be read
+    /// from the `sources.<name>` table passed to `setup`.
+    ///
+    /// Sources are identified by their name, so a source with the same name
+    /// as an already registered one is rejected with an error message.
     pub fn register_source<S>(&self, source: S)
     where
         S: CompletionSource,
     {
+        let name = CompletionSource::name(&source);
+
+        if let Err(err) = self.check_source_name(name) {
+            messages::echoerr!("{err}");
+            return;
+        }
+
         let sources = &mut self.0.borrow_mut().sources;
         sources.push(Arc::new(source));
     }
 
+    /// Registers a completion source defined in Lua, whose name has already
+    /// been checked with [`check_source_name`](Self::check_source_name). If
+    /// `setup` has already been called the source is set up right away, and
+    /// it will be attached to all the buffers entered from now on.
+    pub(crate) fn register_lua_source(
+        &self,
+        mut source: LuaSource,
+    ) -> crate::Result<()> {
+        if self.already_setup() {
+            ErasedSource::setup(&mut source, None)?;
+        }
+
+        self.0.borrow_mut().sources.push(Arc::new(source));
+
+        Ok(())
+    }
+
+    /// Returns the sources attached to `buf` that haven't been stopped.
+    fn running_sources(&self, buf: &Buffer) -> Vec<Arc<dyn ErasedSource>> {
+        let state = &*self.0.borrow();
+
+        match state.attached_buffers.get(buf) {
+            Some(attached) if !state.is_stopped && !attached.is_stopped => {
+                attached
+                    .running_sources(&state.stopped_sources)
+                    .cloned()
+                    .collect()
+            },
+            _ => Vec::new(),
+        }
+    }
+
+    /// Saves the completions accepted so far to disk.
+    pub(crate) fn save_frecency(&self) -> crate::Result<()> {
+        self.0.borrow_mut().frecency.save()
+    }
+
+    /// Selects the completion identified by `which`, or removes the current
+    /// selection if there's no such completion. Does nothing if the
+    /// completion menu is closed.
+    ///
+    /// The details of the selected completion are shown right away, and
+    /// its source is asked to resolve it if it hasn't already.
+    pub(crate) fn select_completion(
+        &self,
+        which: IdentifyCompletion,
+    ) -> nvim::Result<()> {
+        let state = &mut *self.0.borrow_mut();
+
+        let ui = match state.ui.as_mut() {
+            Some(ui) if ui.is_menu_open() => ui,
+            _ => return Ok(()),
+        };
+
+        let index = which.index(ui.selected_index(), state.completions.len());
+
+        ui.select(index)?;
+
+        let selected = index.map(|idx| &state.completions[idx]);
+
+        ui.show_details(selected.and_then(|c| c.item.details()))?;
+
+        let channel = state.channel.as_mut().expect("already setup");
+
+        let source = selected.filter(|c| !c.item.is_resolved).and_then(|c| {
+            let source =
+                state.sources.iter().find(|source| source.name() == c.source);
+            Some((Arc::clone(source?), Arc::clone(&c.item)))
+        });
+
+        match source {
+            Some((source, item)) => {
+                channel.resolve(source, item, &state.config)
+            },
+            None => channel.cancel_resolve(),
+        }
+
+        Ok(())
+    }
+
     pub(crate) fn setup(&self) -> Function<Object, ()> {
         self.create_fn(setup::setup)
     }
 
+    /// Loads the completions accepted in previous sessions from
+    /// `stdpath("data")`, if the user enabled frecency.
+    pub(crate) fn setup_frecency(&self) -> nvim::Result<()> {
+        if !self.config().frecency_enabled() {
+            return Ok(());
+        }
+
+        let data = api::call_function::<_, String>("stdpath", ("data",))?;
+        let path = PathBuf::from(data).join("compleet").join("frecency");
+
+        // Not being able to read the learned data shouldn't prevent the
+        // plugin from working, so we only warn the user about it.
+        match Frecency::load(path) {
+            Ok(frecency) => self.0.borrow_mut().frecency = frecency,
+            Err(err) => messages::echowarn!("{err}"),
+        }
+
+        Ok(())
+    }
+
+    /// Calls the `setup` method of every registered source with its
+    /// configuration and creates the channel used to compute the completion
+    /// results.
+    pub(crate) fn setup_sources(
+        &self,
+        mut configs: HashMap<String, Object>,
+    ) -> crate::Result<()> {
+        {
+            let mut state = self.0.borrow_mut();
+            let sources = &mut state.sources;
+
+            // Make sure every configured source has actually been
+            // registered.
+            if let Some(name) = configs
+                .keys()
+                .find(|&name| !sources.iter().any(|s| s.name() == name))
+            {
+                return Err(Error::UnknownSource {
+                    name: name.clone(),
+                    registered: sources.iter().map(|s| s.name()).collect(),
+                });
+            }
+
+            for source in sources.iter_mut() {
+                let config = configs.remove(source.name());
+
+                // Sources are only shared with the attached buffers, and no
+                // buffer can be attached before `setup` is called.
+                Arc::get_mut(source)
+                    .expect("sources aren't shared before setup")
+                    .setup(config)?;
+            }
+        }
+
+        let on_completions =
+            self.create_fn(|client, ()| client.on_completions());
+
+        self.0.borrow_mut().channel = Some(Channel::new(on_completions)?);
+
+        Ok(())
+    }
+
+    /// Creates the UI used to display the completions.
+    pub(crate) fn setup_ui(&self) -> nvim::Result<()> {
+        let ui = Ui::new(&self.config())?;
+        self.0.borrow_mut().ui = Some(ui);
+        Ok(())
+    }
+
     pub(crate) fn set_config(&self, config: Config) {
-        // todo!()
+        self.0.borrow_mut().config = config;
+    }
+
+    /// Stops the completion sources named in `sources`, or completion as a
+    /// whole if `sources` is empty, either in `buf` or in all the buffers if
+    /// `buf` is `None`. If `stopped` is `false` the sources are started
+    /// again instead.
+    ///
+    /// Starting something in all the buffers also starts it in the buffers
+    /// where it had been stopped individually, while a source stopped in
+    /// all the buffers can't be started in a single one.
+    pub(crate) fn set_stopped(
+        &self,
+        buf: Option<&Buffer>,
+        sources: &[String],
+        stopped: bool,
+    ) -> crate::Result<()> {
+        let names = sources
+            .iter()
+            .map(|name| self.source_name(name))
+            .collect::<crate::Result<Vec<_>>>()?;
+
+        {
+            let state = &mut *self.0.borrow_mut();
+
+            let update = |set: &mut HashSet<&'static str>| {
+                for &name in &names {
+                    if stopped {
+                        set.insert(name);
+                    } else {
+                        set.remove(name);
+                    }
+                }
+            };
+
+            match buf {
+                Some(buf) => {
+                    let attached = match state.attached_buffers.get_mut(buf) {
+                        Some(attached) => attached,
+                        None => return Ok(()),
+                    };
+
+                    if names.is_empty() {
+                        attached.is_stopped = stopped;
+                    } else {
+                        update(&mut attached.stopped_sources);
+                    }
+                },
+
+                None => {
+                    if names.is_empty() {
+                        state.is_stopped = stopped;
+                    } else {
+                        update(&mut state.stopped_sources);
+                    }
+
+                    if !stopped {
+                        for attached in state.attached_buffers.values_mut() {
+                            if names.is_empty() {
+                                attached.is_stopped = false;
+                            } else {
+                                update(&mut attached.stopped_sources);
+                            }
+                        }
+                    }
+                },
+            }
+        }
+
+        // The current completions could come from a source that's just been
+        // stopped.
+        if stopped {
+            self.stop_completing()?;
+        }
+
+        Ok(())
+    }
+
+    /// Asks every registered source whether it should attach to `buf`,
+    /// returning their answers together with their names.
+    pub(crate) fn should_attach(
+        &self,
+        buf: &Buffer,
+    ) -> Vec<(&'static str, SourceResult<ShouldAttach>)> {
+        self.sources()
+            .iter()
+            .map(|source| (source.name(), source.should_attach(buf)))
+            .collect()
+    }
+
+    /// Shows the options of the current tabstop of the snippet in the
+    /// completion menu, if it's a choice.
+    pub(crate) fn show_snippet_choices(&self) -> crate::Result<()> {
+        let choices = match &self.0.borrow().snippet {
+            Some(snippet) if !snippet.choices().is_empty() => {
+                snippet.choices().to_vec()
+            },
+            _ => return Ok(()),
+        };
+
+        let (row, col) = Window::current().get_cursor()?;
+
+        let mut ctx = {
+            let config = self.config();
+            CompletionContext::from_buffer(
+                Buffer::current(),
+                row - 1,
+                col,
+                TriggerKind::Manual,
+                |filetype| config.keyword_pattern(filetype),
+            )?
+        };
+
+        ctx.set_prefix_start(col);
+
+        let state = &mut *self.0.borrow_mut();
+
+        if let Some(channel) = state.channel.as_mut() {
+            channel.stop_tasks();
+        }
+
+        state.session = Some(Session::snippet_choices(&ctx, &choices));
+        state.ctx = Some(Arc::new(ctx));
+        state.update_completions()?;
+
+        Ok(())
+    }
+
+    /// Whether the current snippet has a tabstop to jump to in the current
+    /// buffer, in the given direction.
+    pub(crate) fn snippet_jumpable(&self, forward: bool) -> bool {
+        self.0.borrow().snippet.as_ref().map_or(false, |snippet| {
+            snippet.buffer() == &Buffer::current() && snippet.jumpable(forward)
+        })
+    }
+
+    /// Returns the name of the registered source named `name`, or an
+    /// [`UnknownSource`](Error::UnknownSource) error if there's no such
+    /// source.
+    fn source_name(&self, name: &str) -> crate::Result<&'static str> {
+        let names = self.source_names();
+
+        names.iter().find(|&&registered| registered == name).copied().ok_or(
+            Error::UnknownSource { name: name.to_owned(), registered: names },
+        )
+    }
+
+    /// Returns the names of all the registered completion sources.
+    pub(crate) fn source_names(&self) -> Vec<&'static str> {
+        self.0.borrow().sources.iter().map(|source| source.name()).collect()
+    }
+
+    /// Returns all the registered completion sources. They're cloned out of
+    /// the state so that they can call back into the client while they're
+    /// running.
+    fn sources(&self) -> Vec<Arc<dyn ErasedSource>> {
+        self.0.borrow().sources.clone()
+    }
+
+    /// Returns all the registered completion sources that should be attached
+    /// to `buf`.
+    fn sources_for(
+        &self,
+        buf: &Buffer,
+    ) -> crate::Result<Vec<Arc<dyn ErasedSource>>> {
+        let mut sources = Vec::new();
+
+        for source in self.sources() {
+            match source.should_attach(buf) {
+                Ok(true) => sources.push(source),
+                Ok(false) => {},
+                Err(err) => {
+                    return Err(Error::SourceError {
+                        name: source.name(),
+                        err,
+                    })
+                },
+            }
+        }
+
+        Ok(sources)
+    }
+
+    /// Starts tracking the tabstops of a snippet whose `expansion` has just
+    /// been inserted in `buf`, and jumps to its first tabstop. Any previous
+    /// snippet is ended, so snippets can't be nested.
+    ///
+    /// See [`Snippet::new`] for the meaning of `position`.
+    pub(crate) fn start_snippet<F>(
+        &self,
+        buf: Buffer,
+        expansion: Expansion,
+        position: F,
+    ) -> crate::Result<()>
+    where
+        F: Fn(usize) -> (usize, usize),
+    {
+        let old = self.0.borrow_mut().snippet.take();
+
+        if let Some(old) = old {
+            old.end()?;
+        }
+
+        let snippet = Snippet::new(buf, expansion, position)?;
+        self.0.borrow_mut().snippet = Some(snippet);
+        self.jump_snippet(true)
+    }
+
+    /// Returns the statistics about the requests made to the sources.
+    pub(crate) fn stats(&self) -> Ref<'_, Stats> {
+        Ref::map(self.0.borrow(), |state| &state.stats)
+    }
+
+    /// Stops all the pending completion requests and clears the currently
+    /// available completions.
+    pub(crate) fn stop_completing(&self) -> nvim::Result<()> {
+        let state = &mut *self.0.borrow_mut();
+
+        if let Some(channel) = state.channel.as_mut() {
+            channel.stop_tasks();
+        }
+
+        state.completions.clear();
+        state.ctx = None;
+        state.session = None;
+
+        if let Some(ui) = state.ui.as_mut() {
+            ui.close()?;
+        }
+
+        Ok(())
+    }
+
+    /// Calls `f`, ignoring the edits it makes to attached buffers unless the
+    /// user asked to be shown new completions right after inserting one.
+    pub(crate) fn while_inserting<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce() -> R,
+    {
+        if self.config().complete_after_inserting() {
+            return f();
+        }
+
+        self.0.borrow_mut().is_inserting = true;
+        let res = f();
+        self.0.borrow_mut().is_inserting = false;
+        res
+    }
+
+    /// Removes all the learned completions, both from memory and from disk.
+    pub(crate) fn wipe_frecency(&self) -> crate::Result<()> {
+        self.0.borrow_mut().frecency.wipe()
     }
 
-    /// Queries all the registered completion sources, returning whether any of
-    /// them are enabled for the given `buf`.
-    pub(crate) fn should_attach(&self, buf: &Buffer) -> bool {
-        todo!()
+    /// Removes all the statistics about the requests made to the sources.
+    pub(crate) fn wipe_stats(&self) {
+        self.0.borrow_mut().stats.reset()
     }
 }
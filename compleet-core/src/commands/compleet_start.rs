@@ -1,9 +1,15 @@
+use nvim_oxi::api::Buffer;
+
 use crate::Client;
 
+/// Executed on `:CompleetStart`. Starts the completion sources named in
+/// `sources`, or completion as a whole if no sources are given, in the
+/// current buffer. With a bang they're started in all the buffers.
 pub(super) fn compleet_start(
     client: &Client,
     bang: bool,
     sources: Vec<String>,
 ) -> crate::Result<()> {
-    Ok(())
+    let buf = (!bang).then(Buffer::current);
+    client.set_stopped(buf.as_ref(), &sources, false)
 }
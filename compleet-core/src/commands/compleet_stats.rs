@@ -1,7 +1,94 @@
-use nvim_oxi as nvim;
+use nvim_oxi::api::{self, Window};
 
-use crate::Client;
+use crate::{frecency, messages, Client};
+
+/// The maximum number of learned completions listed by `:CompleetStats`.
+const MAX_LISTED: usize = 10;
+
+/// Executed on `:CompleetStats`. Opens a scratch buffer in a new window
+/// showing statistics about the requests made to every source and the
+/// completions learned so far. With a bang, resets the statistics and
+/// forgets all the learned completions instead.
+pub(super) fn compleet_stats(
+    client: &Client,
+    bang: bool,
+) -> crate::Result<()> {
+    if bang {
+        client.wipe_stats();
+        client.wipe_frecency()?;
+        messages::echoinfo!(
+            "reset the statistics and forgot all the learned completions"
+        );
+        return Ok(());
+    }
+
+    let mut lines = vec![String::from("# Requests"), String::new()];
+
+    let stats = client.stats();
+
+    if stats.is_empty() {
+        lines.push(String::from("No completions have been requested yet."));
+    } else {
+        lines.extend(stats.render());
+    }
+
+    drop(stats);
+
+    lines.extend([String::new(), String::from("# Learned completions")]);
+    lines.push(String::new());
+    lines.extend(self::learned_completions(client));
+
+    let mut buf = api::create_buf(false, true)?;
+    buf.set_lines(0, 1, false, lines)?;
+    buf.set_option("bufhidden", "wipe")?;
+    buf.set_option("modifiable", false)?;
+    buf.set_option("filetype", "markdown")?;
+
+    api::command("botright split")?;
+    Window::current().set_buf(&buf)?;
 
-pub(super) fn compleet_stats(client: &Client) -> nvim::Result<()> {
     Ok(())
 }
+
+/// Describes the completions learned from the ones accepted so far.
+fn learned_completions(client: &Client) -> Vec<String> {
+    if !client.config().frecency_enabled() {
+        return vec![String::from(
+            "Learning from accepted completions is disabled.",
+        )];
+    }
+
+    let frecency = client.frecency();
+    let now = frecency::now();
+
+    let mut lines = vec![match frecency.path() {
+        Some(path) => format!(
+            "{} learned completions, saved in {}.",
+            frecency.len(),
+            path.display()
+        ),
+        None => format!("{} learned completions.", frecency.len()),
+    }];
+
+    if frecency.len() > 0 {
+        lines.push(String::new());
+    }
+
+    for (filetype, text, entry) in
+        frecency.entries(now).into_iter().take(MAX_LISTED)
+    {
+        // Multi-line completions have to fit in a single line of the buffer.
+        let text = text.replace('\n', "\\n");
+
+        lines.push(format!(
+            "- {text} ({}, from {}): accepted {} times, last after typing \
+             \"{}\"",
+            if filetype.is_empty() { "no filetype" } else { filetype },
+            entry.source,
+            entry.count,
+            entry.prefix,
+        ));
+    }
+
+    lines
+}
@@ -2,14 +2,15 @@ use nvim_oxi::{
     self as nvim,
     api,
     opts::CreateCommandOpts,
-    types::{CommandArgs, CommandNArgs},
+    types::{CommandArgs, CommandComplete, CommandNArgs},
 };
 
 use crate::Client;
 
 pub(crate) fn setup(client: &Client) -> nvim::Result<()> {
-    let stats =
-        client.create_fn(|client, _args| super::compleet_stats(client));
+    let stats = client.create_fn(|client, args: CommandArgs| {
+        super::compleet_stats(client, args.bang)
+    });
 
     let start = client.create_fn(|client, args: CommandArgs| {
         super::compleet_start(client, args.bang, args.fargs)
@@ -19,12 +20,29 @@ pub(crate) fn setup(client: &Client) -> nvim::Result<()> {
         super::compleet_stop(client, args.bang, args.fargs)
     });
 
+    // Completes the names of the registered sources.
+    let complete_sources = client.create_fn(
+        |client, (arg_lead, _, _): (String, String, usize)| {
+            Ok::<_, nvim::Error>(
+                client
+                    .source_names()
+                    .into_iter()
+                    .filter(|name| name.starts_with(&arg_lead))
+                    .map(ToOwned::to_owned)
+                    .collect::<Vec<_>>(),
+            )
+        },
+    );
+
+    let stats_opts = CreateCommandOpts::builder().bang(true).build();
+
     let opts = CreateCommandOpts::builder()
         .bang(true)
         .nargs(CommandNArgs::Any)
+        .complete(CommandComplete::CustomList(complete_sources))
         .build();
 
-    api::create_user_command("CompleetStats", stats, None)?;
+    api::create_user_command("CompleetStats", stats, Some(&stats_opts))?;
     api::create_user_command("CompleetStart", start, Some(&opts))?;
     api::create_user_command("CompleetStop", stop, Some(&opts))?;
 
@@ -0,0 +1,347 @@
+use nvim_oxi::api::Buffer;
+
+use crate::iskeyword::IsKeyword;
+
+/// How the completion was triggered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerKind {
+    /// Completion was triggered automatically by typing a keyword character.
+    Automatic,
+
+    /// Completion was explicitly requested by the user, e.g. via the
+    /// `<Plug>(compleet-show-completions)` mapping.
+    Manual,
+
+    /// Completion was triggered by typing one of the trigger characters of
+    /// a source.
+    TriggerCharacter(char),
+}
+
+/// Describes the state of the buffer at the moment completions were
+/// requested. It only holds owned data so that it can be sent to the
+/// threadpool where the completion sources are queried.
+#[derive(Debug, Clone)]
+pub struct CompletionContext {
+    /// The buffer the completions were requested in.
+    buf: Buffer,
+
+    /// The filetype of the buffer.
+    filetype: String,
+
+    /// The text of the line the cursor is currently on.
+    line: String,
+
+    /// The row the cursor is currently on (0-indexed).
+    row: usize,
+
+    /// The number of bytes between the start of the line and the cursor.
+    col: usize,
+
+    /// The number of UTF-16 code units between the start of the line and the
+    /// cursor.
+    col_utf16: usize,
+
+    /// The number of UTF-32 code units (i.e. `char`s) between the start of
+    /// the line and the cursor.
+    col_utf32: usize,
+
+    /// The byte offset of the start of the keyword before the cursor.
+    prefix_start: usize,
+
+    /// The byte offset of the end of the keyword the cursor is in.
+    keyword_end: usize,
+
+    /// How the completion was triggered.
+    trigger: TriggerKind,
+}
+
+impl CompletionContext {
+    /// Creates a new context for the `row`-th line of `buf` with the cursor
+    /// at byte `col`, reading the line and the needed buffer options from
+    /// Neovim.
+    ///
+    /// The `keyword_pattern` callback is given the buffer's filetype and can
+    /// return extra keyword characters to add to the buffer's `'iskeyword'`.
+    pub(crate) fn from_buffer<'a, F>(
+        buf: Buffer,
+        row: usize,
+        col: usize,
+        trigger: TriggerKind,
+        keyword_pattern: F,
+    ) -> crate::Result<Self>
+    where
+        F: FnOnce(&str) -> Option<&'a str>,
+    {
+        let line = buf
+            .get_lines(row, row + 1, false)?
+            .next()
+            .map(|line| line.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let filetype = buf.get_option::<String>("filetype")?;
+
+        let mut iskeyword = buf.get_option::<String>("iskeyword")?;
+
+        if let Some(pattern) = keyword_pattern(&filetype) {
+            iskeyword.push(',');
+            iskeyword.push_str(pattern);
+        }
+
+        let iskeyword = IsKeyword::parse(&iskeyword);
+
+        Ok(Self::new(buf, filetype, row, col, line, &iskeyword, trigger))
+    }
+
+    pub(crate) fn new(
+        buf: Buffer,
+        filetype: String,
+        row: usize,
+        col: usize,
+        line: String,
+        iskeyword: &IsKeyword,
+        trigger: TriggerKind,
+    ) -> Self {
+        // Clamp the column to the closest character boundary to avoid
+        // panicking if we've been given an invalid position.
+        let col = (0..=col.min(line.len()))
+            .rev()
+            .find(|&col| line.is_char_boundary(col))
+            .unwrap_or_default();
+
+        let (before, after) = line.split_at(col);
+
+        Self {
+            col_utf16: before.encode_utf16().count(),
+            col_utf32: before.chars().count(),
+            prefix_start: keyword_start(before, iskeyword),
+            keyword_end: col + keyword_len(after, iskeyword),
+            buf,
+            filetype,
+            line,
+            row,
+            col,
+            trigger,
+        }
+    }
+
+    /// The text of the line after the cursor.
+    #[inline]
+    pub fn after_cursor(&self) -> &str {
+        &self.line[self.col..]
+    }
+
+    /// The text of the line before the cursor.
+    #[inline]
+    pub fn before_cursor(&self) -> &str {
+        &self.line[..self.col]
+    }
+
+    /// The buffer the completions were requested in.
+    #[inline]
+    pub fn buffer(&self) -> &Buffer {
+        &self.buf
+    }
+
+    /// The number of bytes between the start of the line and the cursor.
+    #[inline]
+    pub fn col(&self) -> usize {
+        self.col
+    }
+
+    /// The number of UTF-16 code units between the start of the line and the
+    /// cursor. This is how the LSP specification measures columns by default.
+    #[inline]
+    pub fn col_utf16(&self) -> usize {
+        self.col_utf16
+    }
+
+    /// The number of UTF-32 code units (i.e. `char`s) between the start of
+    /// the line and the cursor.
+    #[inline]
+    pub fn col_utf32(&self) -> usize {
+        self.col_utf32
+    }
+
+    /// The filetype of the buffer.
+    #[inline]
+    pub fn filetype(&self) -> &str {
+        &self.filetype
+    }
+
+    /// The byte offset of the end of the keyword the cursor is in, i.e. of
+    /// the first non-keyword character after the cursor.
+    #[inline]
+    pub fn keyword_end(&self) -> usize {
+        self.keyword_end
+    }
+
+    /// The text of the line the cursor is currently on.
+    #[inline]
+    pub fn line(&self) -> &str {
+        &self.line
+    }
+
+    /// The keyword right before the cursor, where which characters are part
+    /// of a keyword is decided by the buffer's `'iskeyword'` option and by
+    /// the `completion.keyword_patterns` configured for its filetype.
+    #[inline]
+    pub fn prefix(&self) -> &str {
+        &self.line[self.prefix_start..self.col]
+    }
+
+    /// The number of bytes between the start of the line and the start of
+    /// the [`prefix`](CompletionContext::prefix).
+    #[inline]
+    pub fn prefix_start(&self) -> usize {
+        self.prefix_start
+    }
+
+    /// The row the cursor is currently on (0-indexed).
+    #[inline]
+    pub fn row(&self) -> usize {
+        self.row
+    }
+
+    /// How the completion was triggered.
+    #[inline]
+    pub fn trigger_kind(&self) -> TriggerKind {
+        self.trigger
+    }
+
+    /// Makes the prefix start at byte `start` instead of at the start of the
+    /// keyword before the cursor.
+    #[inline]
+    pub(crate) fn set_prefix_start(&mut self, start: usize) {
+        self.prefix_start = start.min(self.col);
+    }
+
+    #[inline]
+    pub(crate) fn set_trigger_kind(&mut self, trigger: TriggerKind) {
+        self.trigger = trigger;
+    }
+
+    /// Returns the last character of the trigger in `triggers` the text
+    /// before the cursor ends with, if any.
+    pub(crate) fn triggered_by(&self, triggers: &[String]) -> Option<char> {
+        trigger_character(self.before_cursor(), triggers)
+    }
+}
+
+/// Returns the byte offset of the start of the keyword at the end of `text`.
+fn keyword_start(text: &str, iskeyword: &IsKeyword) -> usize {
+    text.char_indices()
+        .rev()
+        .take_while(|&(_, char)| iskeyword.contains(char))
+        .last()
+        .map(|(idx, _)| idx)
+        .unwrap_or(text.len())
+}
+
+/// Returns the length in bytes of the keyword at the start of `text`.
+fn keyword_len(text: &str, iskeyword: &IsKeyword) -> usize {
+    text.char_indices()
+        .find(|&(_, char)| !iskeyword.contains(char))
+        .map_or(text.len(), |(idx, _)| idx)
+}
+
+/// Returns the last character of the first trigger `text` ends with.
+fn trigger_character<S: AsRef<str>>(
+    text: &str,
+    triggers: &[S],
+) -> Option<char> {
+    triggers
+        .iter()
+        .map(AsRef::as_ref)
+        .find(|&trigger| !trigger.is_empty() && text.ends_with(trigger))
+        .and_then(|trigger| trigger.chars().last())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prefix(line: &str, col: usize) -> &str {
+        &line[keyword_start(&line[..col], &IsKeyword::default())..col]
+    }
+
+    // NOTE: the `|` in the following comments indicates the cursor position.
+
+    #[test]
+    // `|`
+    fn empty_line() {
+        assert_eq!("", prefix("", 0))
+    }
+
+    #[test]
+    // `|foo`
+    fn cursor_at_beginning_of_line() {
+        assert_eq!("", prefix("foo", 0))
+    }
+
+    #[test]
+    // ` ⇥|foo`
+    fn only_whitespace_before_cursor() {
+        assert_eq!("", prefix(" \tfoo", 2))
+    }
+
+    #[test]
+    // `foo |bar`
+    fn cursor_before_word() {
+        assert_eq!("", prefix("foo bar", 4))
+    }
+
+    #[test]
+    // `foo|`
+    fn cursor_end_of_word() {
+        assert_eq!("foo", prefix("foo", 3))
+    }
+
+    #[test]
+    // `foo|bar`
+    fn cursor_inside_word() {
+        assert_eq!("foo", prefix("foobar", 3))
+    }
+
+    #[test]
+    // `foo.ba|`
+    fn stops_at_non_keyword_chars() {
+        assert_eq!("ba", prefix("foo.ba", 6))
+    }
+
+    #[test]
+    // `fö|ö` (every `ö` is 2 bytes long)
+    fn cursor_inside_word_multibyte_chars() {
+        assert_eq!("fö", prefix("föö", 3))
+    }
+
+    #[test]
+    // `a-b|` with `-` added to the keyword characters
+    fn extra_keyword_chars() {
+        let iskeyword = IsKeyword::parse("@,48-57,_,192-255,-");
+        assert_eq!(0, keyword_start("a-b", &iskeyword));
+    }
+
+    #[test]
+    // `fo|o.bar`, `foo|.bar`, `f|öö`
+    fn keyword_after_cursor() {
+        let iskeyword = IsKeyword::default();
+        assert_eq!(1, keyword_len("o.bar", &iskeyword));
+        assert_eq!(0, keyword_len(".bar", &iskeyword));
+        assert_eq!(4, keyword_len("öö", &iskeyword));
+    }
+
+    #[test]
+    // `foo::|`
+    fn multi_char_trigger() {
+        assert_eq!(Some(':'), trigger_character("foo::", &[".", "::"]));
+        assert_eq!(None, trigger_character("foo:", &[".", "::"]));
+    }
+
+    #[test]
+    // `foo.|`
+    fn single_char_trigger() {
+        assert_eq!(Some('.'), trigger_character("foo.", &[".", "::"]));
+        assert_eq!(None, trigger_character("foo. ", &[".", "::"]));
+        assert_eq!(None, trigger_character("foo.", &[""]));
+    }
+}
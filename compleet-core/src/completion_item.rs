@@ -0,0 +1,695 @@
+use std::any::Any;
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::Range;
+use std::sync::Arc;
+
+use crate::{snippet, PositionEncoding, SourceResult, TextEdit, TextRange};
+
+/// A range used to highlight all the bytes of a completion's label in the
+/// `bytes` range with the `group` highlight group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HighlightRange {
+    pub bytes: Range<usize>,
+    pub group: &'static str,
+}
+
+/// A callback that's executed on Neovim's main thread **after** a completion
+/// has been accepted and inserted into the buffer.
+pub type PostInsertCallback =
+    Arc<dyn Fn(&CompletionItem) -> SourceResult<()> + Send + Sync + 'static>;
+
+/// Additional information about a completion item that's displayed in a
+/// separate floating window when the item is selected.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Details {
+    /// The lines of text displayed in the details window.
+    pub text: Vec<String>,
+
+    /// The filetype used to highlight the contents of the details window.
+    pub ft: String,
+}
+
+/// The informations about a [`CompletionItem`] filled in by
+/// [`CompletionSource::resolve`]. The fields that are left empty keep the
+/// values the item was built with.
+///
+/// [`CompletionSource::resolve`]: crate::CompletionSource::resolve
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResolvedItem {
+    /// The details displayed in the details window.
+    pub details: Option<Details>,
+
+    /// The edits to other parts of the buffer made when the item is
+    /// accepted.
+    pub additional_edits: Vec<TextEdit>,
+}
+
+/// The kind of a completion item, mirroring the `CompletionItemKind` of the
+/// LSP specification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u8)]
+pub enum CompletionItemKind {
+    Text = 1,
+    Method,
+    Function,
+    Constructor,
+    Field,
+    Variable,
+    Class,
+    Interface,
+    Module,
+    Property,
+    Unit,
+    Value,
+    Enum,
+    Keyword,
+    Snippet,
+    Color,
+    File,
+    Reference,
+    Folder,
+    EnumMember,
+    Constant,
+    Struct,
+    Event,
+    Operator,
+    TypeParameter,
+}
+
+impl CompletionItemKind {
+    /// The name of the kind, as it's displayed in the completion menu.
+    pub fn as_str(&self) -> &'static str {
+        use CompletionItemKind::*;
+
+        match self {
+            Text => "Text",
+            Method => "Method",
+            Function => "Function",
+            Constructor => "Constructor",
+            Field => "Field",
+            Variable => "Variable",
+            Class => "Class",
+            Interface => "Interface",
+            Module => "Module",
+            Property => "Property",
+            Unit => "Unit",
+            Value => "Value",
+            Enum => "Enum",
+            Keyword => "Keyword",
+            Snippet => "Snippet",
+            Color => "Color",
+            File => "File",
+            Reference => "Reference",
+            Folder => "Folder",
+            EnumMember => "EnumMember",
+            Constant => "Constant",
+            Struct => "Struct",
+            Event => "Event",
+            Operator => "Operator",
+            TypeParameter => "TypeParameter",
+        }
+    }
+}
+
+/// A single completion item returned by a [`CompletionSource`].
+///
+/// [`CompletionSource`]: crate::CompletionSource
+#[derive(Clone)]
+pub struct CompletionItem {
+    /// The text that will be inserted into the buffer if the completion is
+    /// accepted.
+    pub(crate) text: String,
+
+    /// Where to place the cursor after the item has been inserted, as a byte
+    /// offset in `text`. If not set the cursor is placed at the end of the
+    /// inserted text.
+    pub(crate) cursor: Option<usize>,
+
+    /// Whether `text` is an LSP snippet that has to be expanded before being
+    /// inserted.
+    pub(crate) is_snippet: bool,
+
+    /// The characters that accept the item when typed while it's selected,
+    /// before being inserted themselves.
+    pub(crate) commit_characters: Vec<char>,
+
+    /// The range replaced by `text` when the item is inserted. If not set
+    /// the keyword before the cursor is replaced.
+    pub(crate) insert_range: Option<TextRange>,
+
+    /// The range replaced by `text` when the item is accepted in replace
+    /// mode. If not set the whole keyword the cursor is in is replaced.
+    pub(crate) replace_range: Option<TextRange>,
+
+    /// Edits to other parts of the buffer made when the item is accepted,
+    /// like adding an import. They can't overlap with each other or with
+    /// the range replaced by `text`.
+    pub(crate) additional_edits: Vec<TextEdit>,
+
+    /// How the columns of the item's ranges are measured.
+    pub(crate) position_encoding: PositionEncoding,
+
+    /// An icon representing the type of completion.
+    pub(crate) icon: Option<char>,
+
+    /// The highlight group of the `icon`, if set.
+    pub(crate) icon_hl_group: Option<&'static str>,
+
+    /// The text that's shown in the completion menu. It defaults to the first
+    /// line of the `text` field if it's not set. Should **not** contain any
+    /// newlines.
+    pub(crate) label: String,
+
+    /// The highlight ranges of the `label`, relative to its start.
+    pub(crate) label_hl_ranges: Vec<HighlightRange>,
+
+    /// A short text shown right after the label without any spacing, like
+    /// the signature of a function.
+    pub(crate) label_detail: Option<String>,
+
+    /// Whether the item is deprecated, in which case its label is struck
+    /// through.
+    pub(crate) is_deprecated: bool,
+
+    /// Additional informations about the item, also shown in the completion
+    /// menu and ragged left (meaning the last character of the string will
+    /// touch the right edge of the completion menu). Should only be used for
+    /// short infos, a few words at most.
+    pub(crate) infos: Option<String>,
+
+    /// The highlight group of the `infos`, if set.
+    pub(crate) infos_hl_group: Option<&'static str>,
+
+    /// Additional information about a completion item that's displayed in a
+    /// separate floating window when the item is selected. Can be used for
+    /// longer metadata like documentation.
+    pub(crate) details: Option<Details>,
+
+    /// A callback that's executed **after** a completion has been accepted
+    /// and inserted into the buffer.
+    pub(crate) post_insert_callback: Option<PostInsertCallback>,
+
+    /// The kind of the completion, if known.
+    pub(crate) kind: Option<CompletionItemKind>,
+
+    /// A string used to sort the completion among the other ones returned by
+    /// the same source. Falls back to the `label` if not set.
+    pub(crate) sort_text: Option<String>,
+
+    /// Arbitrary data attached to the item by its source, usually to be able
+    /// to resolve it later.
+    pub(crate) data: Option<Arc<dyn Any + Send + Sync>>,
+
+    /// Whether the item has already been passed to its source's `resolve`,
+    /// in which case it's not resolved again.
+    pub(crate) is_resolved: bool,
+}
+
+impl fmt::Debug for CompletionItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompletionItem")
+            .field("text", &self.text)
+            .field("cursor", &self.cursor)
+            .field("is_snippet", &self.is_snippet)
+            .field("commit_characters", &self.commit_characters)
+            .field("insert_range", &self.insert_range)
+            .field("replace_range", &self.replace_range)
+            .field("additional_edits", &self.additional_edits)
+            .field("position_encoding", &self.position_encoding)
+            .field("icon", &self.icon)
+            .field("icon_hl_group", &self.icon_hl_group)
+            .field("label", &self.label)
+            .field("label_hl_ranges", &self.label_hl_ranges)
+            .field("label_detail", &self.label_detail)
+            .field("is_deprecated", &self.is_deprecated)
+            .field("infos", &self.infos)
+            .field("infos_hl_group", &self.infos_hl_group)
+            .field("details", &self.details)
+            .field(
+                "post_insert_callback",
+                &self.post_insert_callback.as_ref().map(|_| "{closure}"),
+            )
+            .field("kind", &self.kind)
+            .field("sort_text", &self.sort_text)
+            .field("data", &self.data.as_ref().map(|_| "{data}"))
+            .field("is_resolved", &self.is_resolved)
+            .finish()
+    }
+}
+
+impl CompletionItem {
+    /// Returns a new [`CompletionItemBuilder`] for an item inserting `text`.
+    #[inline]
+    pub fn builder<S: Into<String>>(text: S) -> CompletionItemBuilder {
+        CompletionItemBuilder::new(text)
+    }
+
+    /// The data attached to the item by its source, if there's any and it's
+    /// of type `T`.
+    #[inline]
+    pub fn data<T: Any>(&self) -> Option<&T> {
+        self.data.as_deref()?.downcast_ref()
+    }
+
+    /// The additional details displayed when the item is selected, if any.
+    #[inline]
+    pub fn details(&self) -> Option<&Details> {
+        self.details.as_ref()
+    }
+
+    /// The icon of the completion, if any.
+    #[inline]
+    pub fn icon(&self) -> Option<char> {
+        self.icon
+    }
+
+    /// The highlight group of the icon, if set.
+    #[inline]
+    pub fn icon_hl_group(&self) -> Option<&'static str> {
+        self.icon_hl_group
+    }
+
+    /// The short informations displayed on the right of the label, if any.
+    #[inline]
+    pub fn infos(&self) -> Option<&str> {
+        self.infos.as_deref()
+    }
+
+    /// The highlight group of the infos, if set.
+    #[inline]
+    pub fn infos_hl_group(&self) -> Option<&'static str> {
+        self.infos_hl_group
+    }
+
+    /// Whether the completion is deprecated.
+    #[inline]
+    pub fn is_deprecated(&self) -> bool {
+        self.is_deprecated
+    }
+
+    /// The kind of the completion, if known.
+    #[inline]
+    pub fn kind(&self) -> Option<CompletionItemKind> {
+        self.kind
+    }
+
+    /// The text displayed in the completion menu.
+    #[inline]
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// The text displayed right after the label, if any.
+    #[inline]
+    pub fn label_detail(&self) -> Option<&str> {
+        self.label_detail.as_deref()
+    }
+
+    /// Returns the highlight ranges of the completion's label, relative to
+    /// its start.
+    #[inline]
+    pub fn label_hl_ranges(&self) -> &[HighlightRange] {
+        &self.label_hl_ranges
+    }
+
+    /// Returns a copy of the item marked as resolved, with the non-empty
+    /// fields of `resolved` replacing its own.
+    pub(crate) fn resolved(&self, resolved: Option<ResolvedItem>) -> Self {
+        let mut item = self.clone();
+        item.is_resolved = true;
+
+        if let Some(resolved) = resolved {
+            if resolved.details.is_some() {
+                item.details = resolved.details;
+            }
+            if !resolved.additional_edits.is_empty() {
+                item.additional_edits = resolved.additional_edits;
+            }
+        }
+
+        item
+    }
+
+    /// The string used to sort the completion among the other ones returned
+    /// by the same source.
+    #[inline]
+    pub fn sort_text(&self) -> &str {
+        self.sort_text.as_deref().unwrap_or(&self.label)
+    }
+
+    /// The text inserted in the buffer when the completion is accepted.
+    #[inline]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+#[derive(Default)]
+pub struct CompletionItemBuilder {
+    text: Option<String>,
+    cursor: Option<usize>,
+    is_snippet: bool,
+    commit_characters: Vec<char>,
+    insert_range: Option<TextRange>,
+    replace_range: Option<TextRange>,
+    additional_edits: Vec<TextEdit>,
+    position_encoding: PositionEncoding,
+    icon: Option<char>,
+    icon_hl_group: Option<&'static str>,
+    label: Option<String>,
+    label_hl_ranges: Vec<(Range<usize>, &'static str)>,
+    label_detail: Option<String>,
+    is_deprecated: bool,
+    infos: Option<String>,
+    infos_hl_group: Option<&'static str>,
+    details_text: Option<Vec<String>>,
+    details_ft: Option<String>,
+    post_insert_callback: Option<PostInsertCallback>,
+    kind: Option<CompletionItemKind>,
+    sort_text: Option<String>,
+    data: Option<Arc<dyn Any + Send + Sync>>,
+}
+
+impl CompletionItemBuilder {
+    #[inline]
+    pub fn new<S: Into<String>>(text: S) -> Self {
+        Self { text: Some(text.into()), ..Default::default() }
+    }
+
+    /// Sets where to place the cursor after the item has been inserted, as
+    /// a byte offset in its text. Offsets that are out of bounds or not on
+    /// a character boundary are ignored.
+    pub fn cursor(&mut self, offset: usize) -> &mut Self {
+        self.cursor = Some(offset);
+        self
+    }
+
+    /// Adds an edit to another part of the buffer that's applied together
+    /// with the item when it's accepted.
+    pub fn additional_edit(&mut self, edit: TextEdit) -> &mut Self {
+        self.additional_edits.push(edit);
+        self
+    }
+
+    /// Sets the characters that accept the item when typed while it's
+    /// selected. The character is inserted after the item.
+    pub fn commit_characters<I>(&mut self, chars: I) -> &mut Self
+    where
+        I: IntoIterator<Item = char>,
+    {
+        self.commit_characters = chars.into_iter().collect();
+        self
+    }
+
+    /// Attaches arbitrary data to the item, which can be retrieved with
+    /// [`CompletionItem::data`] when the item is resolved.
+    pub fn data<T: Any + Send + Sync>(&mut self, data: T) -> &mut Self {
+        self.data = Some(Arc::new(data));
+        self
+    }
+
+    /// Sets the lines of text displayed in the details window.
+    pub fn details_text<S: Into<String>>(&mut self, text: S) -> &mut Self {
+        self.details_text =
+            Some(text.into().lines().map(ToOwned::to_owned).collect());
+        self
+    }
+
+    /// Sets the filetype used to highlight the details window.
+    pub fn details_ft<S: Into<String>>(&mut self, ft: S) -> &mut Self {
+        self.details_ft = Some(ft.into());
+        self
+    }
+
+    /// Highlights the `bytes` range of the label with the `group` highlight
+    /// group. The range is relative to the start of the label, not to the
+    /// one of the formatted string.
+    pub fn highlight_label(
+        &mut self,
+        bytes: Range<usize>,
+        group: &'static str,
+    ) -> &mut Self {
+        self.label_hl_ranges.push((bytes, group));
+        self
+    }
+
+    pub fn icon(&mut self, icon: char) -> &mut Self {
+        self.icon = Some(icon);
+        self
+    }
+
+    /// Sets the highlight group of the icon. Does nothing if the icon is not
+    /// set.
+    pub fn icon_hl_group(&mut self, group: &'static str) -> &mut Self {
+        self.icon_hl_group = Some(group);
+        self
+    }
+
+    pub fn infos<S: Into<String>>(&mut self, infos: S) -> &mut Self {
+        self.infos = Some(infos.into());
+        self
+    }
+
+    /// Sets the highlight group of the infos. Does nothing if the infos are
+    /// not set. Defaults to `CompleetMenuInfos`.
+    pub fn infos_hl_group(&mut self, group: &'static str) -> &mut Self {
+        self.infos_hl_group = Some(group);
+        self
+    }
+
+    /// Sets the range replaced by the item's text when it's inserted. It has
+    /// to be on a single line and contain the cursor position. If no
+    /// replace range is set it's also used in replace mode.
+    pub fn insert_range(&mut self, range: TextRange) -> &mut Self {
+        self.insert_range = Some(range);
+        self
+    }
+
+    /// Marks the item as deprecated, which strikes through its label in the
+    /// completion menu.
+    pub fn is_deprecated(&mut self, is_deprecated: bool) -> &mut Self {
+        self.is_deprecated = is_deprecated;
+        self
+    }
+
+    /// Marks the text of the item as an LSP snippet. Its tabstops can be
+    /// jumped to after it's been inserted, and the cursor offset is ignored.
+    pub fn is_snippet(&mut self, is_snippet: bool) -> &mut Self {
+        self.is_snippet = is_snippet;
+        self
+    }
+
+    pub fn kind(&mut self, kind: CompletionItemKind) -> &mut Self {
+        self.kind = Some(kind);
+        self
+    }
+
+    /// Sets the label displayed in the completion menu. Any newline and
+    /// everything after it is discarded.
+    pub fn label<S: Into<String>>(&mut self, label: S) -> &mut Self {
+        let label = label.into();
+        self.label = Some(match label.split_once('\n') {
+            Some((first, _)) => first.to_owned(),
+            None => label,
+        });
+        self
+    }
+
+    /// Sets the text displayed right after the label, like the signature of
+    /// a function. Any newline and everything after it is discarded.
+    pub fn label_detail<S: Into<String>>(&mut self, detail: S) -> &mut Self {
+        let detail = detail.into();
+        self.label_detail = Some(match detail.split_once('\n') {
+            Some((first, _)) => first.to_owned(),
+            None => detail,
+        });
+        self
+    }
+
+    /// Sets how the columns of the item's ranges are measured. Defaults to
+    /// [`PositionEncoding::Utf8`].
+    pub fn position_encoding(
+        &mut self,
+        encoding: PositionEncoding,
+    ) -> &mut Self {
+        self.position_encoding = encoding;
+        self
+    }
+
+    /// Sets a callback that's executed after the item has been accepted and
+    /// inserted into the buffer. If it fails the error is echoed as a
+    /// warning.
+    pub fn post_insert_callback<F>(&mut self, callback: F) -> &mut Self
+    where
+        F: Fn(&CompletionItem) -> SourceResult<()> + Send + Sync + 'static,
+    {
+        self.post_insert_callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// Sets the range replaced by the item's text when it's accepted in
+    /// replace mode. It has to be on a single line and contain the cursor
+    /// position. If no insert range is set it's also used when inserting.
+    pub fn replace_range(&mut self, range: TextRange) -> &mut Self {
+        self.replace_range = Some(range);
+        self
+    }
+
+    /// Sets the string used to sort the completion among the other ones
+    /// returned by the same source.
+    pub fn sort_text<S: Into<String>>(&mut self, sort_text: S) -> &mut Self {
+        self.sort_text = Some(sort_text.into());
+        self
+    }
+
+    /// Builds the [`CompletionItem`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the builder has already been used to build another item.
+    pub fn build(&mut self) -> CompletionItem {
+        let text = self.text.take().expect("builder already used");
+
+        // TODO: come up with a better logic for this. For example, if the text
+        // is:
+        // ```txt
+        // foo {
+        //   bar
+        // }
+        // ```
+        // then the `label` would be set to `foo {`. A better label would be
+        // `foo {..}`. Look into how treesitter sets the visible line in folded
+        // text.
+        let label = self.label.take().unwrap_or_else(|| {
+            let text = if self.is_snippet {
+                Cow::Owned(snippet::plain_text(&text))
+            } else {
+                Cow::Borrowed(&*text)
+            };
+            text.lines().next().map(ToOwned::to_owned).unwrap_or_default()
+        });
+
+        let label_hl_ranges = self
+            .label_hl_ranges
+            .drain(..)
+            .map(|(bytes, group)| HighlightRange { bytes, group })
+            .collect();
+
+        let details = self.details_text.take().map(|text| Details {
+            text,
+            ft: self.details_ft.take().unwrap_or_default(),
+        });
+
+        let cursor =
+            self.cursor.take().filter(|&offset| text.is_char_boundary(offset));
+
+        let (insert_range, replace_range) =
+            (self.insert_range.take(), self.replace_range.take());
+
+        CompletionItem {
+            text,
+            cursor,
+            is_snippet: self.is_snippet,
+            commit_characters: std::mem::take(&mut self.commit_characters),
+            insert_range: insert_range.or(replace_range),
+            replace_range: replace_range.or(insert_range),
+            additional_edits: std::mem::take(&mut self.additional_edits),
+            position_encoding: self.position_encoding,
+            icon: self.icon.take(),
+            icon_hl_group: self.icon_hl_group.take(),
+            label,
+            label_hl_ranges,
+            label_detail: self.label_detail.take(),
+            is_deprecated: self.is_deprecated,
+            infos: self.infos.take(),
+            infos_hl_group: self.infos_hl_group.take(),
+            details,
+            post_insert_callback: self.post_insert_callback.take(),
+            kind: self.kind.take(),
+            sort_text: self.sort_text.take(),
+            data: self.data.take(),
+            is_resolved: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn label_defaults_to_first_line() {
+        let item = CompletionItem::builder("foo {\n  bar\n}").build();
+        assert_eq!("foo {", item.label());
+        assert_eq!("foo {\n  bar\n}", item.text());
+    }
+
+    #[test]
+    fn label_is_truncated_at_newline() {
+        let item = CompletionItem::builder("foo").label("bar\nbaz").build();
+        assert_eq!("bar", item.label());
+    }
+
+    #[test]
+    fn label_detail_is_truncated_at_newline() {
+        let item =
+            CompletionItem::builder("foo").label_detail("(a,\nb)").build();
+        assert_eq!(Some("(a,"), item.label_detail());
+    }
+
+    #[test]
+    fn label_hl_ranges() {
+        let item = CompletionItem::builder("foobar")
+            .highlight_label(0..3, "Foo")
+            .highlight_label(3..6, "Bar")
+            .build();
+
+        assert_eq!(
+            &[
+                HighlightRange { bytes: 0..3, group: "Foo" },
+                HighlightRange { bytes: 3..6, group: "Bar" },
+            ],
+            item.label_hl_ranges()
+        );
+    }
+
+    #[test]
+    fn details() {
+        let item = CompletionItem::builder("foo")
+            .details_text("foo\nbar")
+            .details_ft("rust")
+            .build();
+
+        let details = item.details().unwrap();
+        assert_eq!(vec!["foo", "bar"], details.text);
+        assert_eq!("rust", details.ft);
+    }
+
+    #[test]
+    fn resolved_keeps_missing_fields() {
+        let item = CompletionItem::builder("foo")
+            .details_text("foo")
+            .data(42)
+            .build();
+
+        let edit = TextEdit {
+            range: TextRange {
+                start: crate::Position { row: 0, col: 0 },
+                end: crate::Position { row: 0, col: 0 },
+            },
+            new_text: "use foo;\n".into(),
+        };
+
+        let resolved = item.resolved(Some(ResolvedItem {
+            details: None,
+            additional_edits: vec![edit.clone()],
+        }));
+
+        assert!(resolved.is_resolved);
+        assert_eq!(Some(&42), resolved.data::<i32>());
+        assert_eq!(vec!["foo"], resolved.details().unwrap().text);
+        assert_eq!(vec![edit], resolved.additional_edits);
+        assert!(item.resolved(None).is_resolved);
+    }
+}
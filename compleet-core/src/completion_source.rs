@@ -1,4 +1,220 @@
 use async_trait::async_trait;
+use nvim_oxi::{api::Buffer, object, Object};
+use serde::de::DeserializeOwned;
 
+use crate::{
+    CancellationToken,
+    CompletionContext,
+    CompletionItem,
+    Error,
+    ResolvedItem,
+};
+
+/// Any error returned by the methods of a [`CompletionSource`].
+pub type SourceError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+/// Alias for a `Result` with error type [`SourceError`].
+pub type SourceResult<T> = std::result::Result<T, SourceError>;
+
+pub type ShouldAttach = bool;
+pub type ShouldRecompute = bool;
+
+/// The completions returned by a [`CompletionSource`].
+#[derive(Debug, Default)]
+pub struct CompletionList {
+    /// The completion items, in no particular order.
+    pub items: Vec<CompletionItem>,
+
+    /// Whether `items` is only a subset of all the possible completions for
+    /// the context it was computed for. Incomplete lists are recomputed on
+    /// every edit, while complete ones are only filtered locally until a
+    /// new word is started.
+    pub is_incomplete: bool,
+}
+
+impl From<Vec<CompletionItem>> for CompletionList {
+    #[inline]
+    fn from(items: Vec<CompletionItem>) -> Self {
+        Self { items, is_incomplete: false }
+    }
+}
+
+/// The trait every completion source has to implement.
+///
+/// All the methods not marked as `async` are executed on Neovim's main thread
+/// and are therefore free to call Neovim's API. [`complete`] and [`resolve`]
+/// are executed on a separate threadpool and can only rely on the
+/// informations contained in the arguments they receive.
+///
+/// [`complete`]: CompletionSource::complete
+/// [`resolve`]: CompletionSource::resolve
 #[async_trait]
-pub trait CompletionSource: Send + Sync + 'static {}
+pub trait CompletionSource: Send + Sync + 'static {
+    /// The configuration of the source, deserialized from the
+    /// `sources.<name>` table passed to `setup`. If the user didn't
+    /// configure the source its `Default` implementation is used.
+    type Config: DeserializeOwned + Default;
+
+    /// The name of the source. It's used to identify the source in the
+    /// configuration and in the `:CompleetStart` and `:CompleetStop`
+    /// commands, so it should be unique among all the registered sources.
+    fn name(&self) -> &'static str;
+
+    /// Called once with the source's configuration when the user calls the
+    /// `setup` function.
+    fn setup(&mut self, _config: Self::Config) -> SourceResult<()> {
+        Ok(())
+    }
+
+    /// Called the first time the user enters a new buffer. Return `true` if
+    /// the source should attach to the buffer.
+    fn should_attach(&self, buf: &Buffer) -> SourceResult<ShouldAttach>;
+
+    /// Returns the strings that start a new completion when typed right
+    /// before the cursor in `buf`, even if there's no keyword to complete
+    /// (e.g. `.` or `::` to complete struct fields and module items).
+    ///
+    /// Called on edits where no keyword is being typed, so it should be
+    /// cheap.
+    fn trigger_characters(&self, _buf: &Buffer) -> SourceResult<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    /// Called on every insert mode edit in a buffer the source is attached
+    /// to, except for the ones that start completing a new word. Return
+    /// `true` if the source should recompute its completions even if the
+    /// last ones it returned were complete.
+    fn on_edit(
+        &self,
+        _ctx: &CompletionContext,
+    ) -> SourceResult<ShouldRecompute> {
+        Ok(false)
+    }
+
+    /// Returns the completions for the given context.
+    ///
+    /// The source is queried when the user starts typing a new word. As
+    /// more characters are typed the returned items are filtered against
+    /// the new prefix, and the source is only queried again if the list was
+    /// marked as [incomplete](CompletionList::is_incomplete).
+    ///
+    /// The `cancel` token is cancelled when the completions are no longer
+    /// needed or when the source takes longer than its configured timeout,
+    /// in which case the returned list is discarded.
+    async fn complete(
+        &self,
+        ctx: &CompletionContext,
+        cancel: &CancellationToken,
+    ) -> SourceResult<CompletionList>;
+
+    /// Called when one of the items returned by [`complete`] is selected,
+    /// to fill in the informations that were too expensive to compute for
+    /// every item (e.g. its documentation). Return `None` if there's
+    /// nothing to add.
+    ///
+    /// Every item is resolved at most once. The `cancel` token is cancelled
+    /// if the selection moves to another item before the source responds.
+    ///
+    /// [`complete`]: CompletionSource::complete
+    async fn resolve(
+        &self,
+        _item: &CompletionItem,
+        _cancel: &CancellationToken,
+    ) -> SourceResult<Option<ResolvedItem>> {
+        Ok(None)
+    }
+}
+
+/// An object-safe version of [`CompletionSource`] where the type of the
+/// source's configuration has been erased, used to store sources of
+/// different types in the same collection.
+#[async_trait]
+pub(crate) trait ErasedSource: Send + Sync + 'static {
+    fn name(&self) -> &'static str;
+
+    /// Deserializes the source's configuration from `config` (or uses the
+    /// default one if `None`) and calls the source's `setup` with it.
+    fn setup(&mut self, config: Option<Object>) -> crate::Result<()>;
+
+    fn should_attach(&self, buf: &Buffer) -> SourceResult<ShouldAttach>;
+
+    fn trigger_characters(&self, buf: &Buffer) -> SourceResult<Vec<String>>;
+
+    fn on_edit(
+        &self,
+        ctx: &CompletionContext,
+    ) -> SourceResult<ShouldRecompute>;
+
+    async fn complete(
+        &self,
+        ctx: &CompletionContext,
+        cancel: &CancellationToken,
+    ) -> SourceResult<CompletionList>;
+
+    async fn resolve(
+        &self,
+        item: &CompletionItem,
+        cancel: &CancellationToken,
+    ) -> SourceResult<Option<ResolvedItem>>;
+}
+
+#[async_trait]
+impl<S: CompletionSource> ErasedSource for S {
+    #[inline]
+    fn name(&self) -> &'static str {
+        CompletionSource::name(self)
+    }
+
+    fn setup(&mut self, config: Option<Object>) -> crate::Result<()> {
+        let name = CompletionSource::name(self);
+
+        let config = match config {
+            Some(config) => {
+                let deserializer = object::Deserializer::new(config);
+                serde_path_to_error::deserialize(deserializer)
+                    .map_err(|err| Error::from(err).in_source_config(name))?
+            },
+
+            None => S::Config::default(),
+        };
+
+        CompletionSource::setup(self, config)
+            .map_err(|err| Error::SourceError { name, err })
+    }
+
+    #[inline]
+    fn should_attach(&self, buf: &Buffer) -> SourceResult<ShouldAttach> {
+        CompletionSource::should_attach(self, buf)
+    }
+
+    #[inline]
+    fn trigger_characters(&self, buf: &Buffer) -> SourceResult<Vec<String>> {
+        CompletionSource::trigger_characters(self, buf)
+    }
+
+    #[inline]
+    fn on_edit(
+        &self,
+        ctx: &CompletionContext,
+    ) -> SourceResult<ShouldRecompute> {
+        CompletionSource::on_edit(self, ctx)
+    }
+
+    #[inline]
+    async fn complete(
+        &self,
+        ctx: &CompletionContext,
+        cancel: &CancellationToken,
+    ) -> SourceResult<CompletionList> {
+        CompletionSource::complete(self, ctx, cancel).await
+    }
+
+    #[inline]
+    async fn resolve(
+        &self,
+        item: &CompletionItem,
+        cancel: &CancellationToken,
+    ) -> SourceResult<Option<ResolvedItem>> {
+        CompletionSource::resolve(self, item, cancel).await
+    }
+}
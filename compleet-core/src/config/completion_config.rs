@@ -1,6 +1,8 @@
+use std::collections::HashMap;
+
 use serde::Deserialize;
 
-#[derive(Deserialize)]
+#[derive(Debug, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub(super) struct CompletionConfig {
     /// Whether to show completion results right after a completion has been
@@ -8,13 +10,119 @@ pub(super) struct CompletionConfig {
     /// only be shown after typing more characters.
     pub(super) after_inserting: bool,
 
+    /// How many milliseconds to wait after the last edit before querying
+    /// the sources. Requests made obsolete by a new edit during this
+    /// interval are never sent.
+    #[serde(default)]
+    pub(super) debounce: u64,
+
+    /// Whether to remember the accepted completions and show the ones
+    /// accepted more frequently and recently first. The learned data is
+    /// persisted across sessions.
+    #[serde(default = "yes")]
+    pub(super) frecency: bool,
+
+    /// Maps filetypes to the extra characters that can be part of the
+    /// keyword being completed, e.g. `-` in CSS or `$` in PHP. The format is
+    /// the same as the one of `'iskeyword'`, and the characters are added to
+    /// the ones already in the buffer's `'iskeyword'`.
+    #[serde(default)]
+    pub(super) keyword_patterns: HashMap<String, String>,
+
+    /// Maps source names to the number of milliseconds after which their
+    /// requests time out, overriding `timeout`.
+    #[serde(default)]
+    pub(super) source_timeouts: HashMap<String, u64>,
+
+    /// How many milliseconds to wait for a source's completions before
+    /// giving up on them. The menu is still shown with the completions of
+    /// the other sources.
+    #[serde(default = "default_timeout")]
+    pub(super) timeout: u64,
+
     /// Whether to show completion results while deleting. If set to `false`
     /// completions will only be shown automatically when inserting characters.
     pub(super) while_deleting: bool,
+
+    /// How to sort the completions coming from all the sources.
+    #[serde(default)]
+    pub(super) sorting: SortingConfig,
 }
 
 impl Default for CompletionConfig {
     fn default() -> Self {
-        Self { after_inserting: false, while_deleting: false }
+        Self {
+            after_inserting: false,
+            debounce: 0,
+            frecency: true,
+            keyword_patterns: HashMap::new(),
+            source_timeouts: HashMap::new(),
+            timeout: default_timeout(),
+            while_deleting: false,
+            sorting: SortingConfig::default(),
+        }
     }
 }
+
+fn default_timeout() -> u64 {
+    1000
+}
+
+fn yes() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(super) struct SortingConfig {
+    /// The comparators used to sort the completions. Two completions are
+    /// compared with the first comparator, and the next one is only used to
+    /// break ties.
+    #[serde(default = "default_comparators")]
+    pub(super) comparators: Vec<Comparator>,
+
+    /// Maps source names to their priority. Sources not listed have a
+    /// priority of 0, and completions from sources with a higher priority
+    /// are shown first.
+    #[serde(default)]
+    pub(super) priorities: HashMap<String, i32>,
+}
+
+impl Default for SortingConfig {
+    fn default() -> Self {
+        Self { comparators: default_comparators(), priorities: HashMap::new() }
+    }
+}
+
+/// The criteria that can be used to sort completions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Comparator {
+    /// Completions whose label starts with the prefix come first.
+    Exact,
+
+    /// Completions that have been accepted more frequently and recently
+    /// come first.
+    Frecency,
+
+    /// Completions with a better fuzzy matching score come first.
+    Score,
+
+    /// Completions from sources with a higher priority come first.
+    Priority,
+
+    /// Completions are sorted by their `sort_text`, which falls back to
+    /// their label if not set.
+    SortText,
+
+    /// Completions are sorted by their kind.
+    Kind,
+
+    /// Completions with a shorter label come first.
+    Length,
+}
+
+fn default_comparators() -> Vec<Comparator> {
+    use Comparator::*;
+    vec![Exact, Frecency, Score, Priority, SortText, Kind, Length]
+}
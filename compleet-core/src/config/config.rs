@@ -1,6 +1,9 @@
+use std::time::Duration;
+
+use nvim_oxi::types::WindowBorder;
 use serde::Deserialize;
 
-#[derive(Default, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub(crate) struct Config {
     #[serde(default)]
@@ -11,6 +14,98 @@ pub(crate) struct Config {
 }
 
 impl Config {
+    /// The comparators used to sort the completions.
+    pub fn comparators(&self) -> &[super::Comparator] {
+        &self.completion.sorting.comparators
+    }
+
+    /// Whether to show completion results right after a completion has been
+    /// accepted.
+    pub const fn complete_after_inserting(&self) -> bool {
+        self.completion.after_inserting
+    }
+
+    /// Whether to show completion results while deleting characters.
+    pub const fn complete_while_deleting(&self) -> bool {
+        self.completion.while_deleting
+    }
+
+    /// How long to wait after the last edit before querying the sources.
+    pub const fn debounce(&self) -> Duration {
+        Duration::from_millis(self.completion.debounce)
+    }
+
+    /// The border of the details window, or `None` if it's disabled.
+    pub fn details_border(&self) -> Option<&WindowBorder> {
+        let border = &self.ui.details.border;
+        border.enable.then_some(&border.style)
+    }
+
+    /// How long an item has to stay selected before it's resolved.
+    pub const fn details_debounce(&self) -> Duration {
+        Duration::from_millis(self.ui.details.debounce)
+    }
+
+    /// Whether to learn from the accepted completions.
+    pub const fn frecency_enabled(&self) -> bool {
+        self.completion.frecency
+    }
+
+    /// The extra keyword characters configured for `filetype`, in the
+    /// format of `'iskeyword'`.
+    pub fn keyword_pattern(&self, filetype: &str) -> Option<&str> {
+        self.completion.keyword_patterns.get(filetype).map(String::as_str)
+    }
+
+    /// Where to anchor the completion menu.
+    pub const fn menu_anchor(&self) -> super::MenuAnchor {
+        self.ui.menu.anchor
+    }
+
+    /// The border of the completion menu, or `None` if it's disabled.
+    pub fn menu_border(&self) -> Option<&WindowBorder> {
+        let border = &self.ui.menu.border;
+        border.enable.then_some(&border.style)
+    }
+
+    /// The columns of the completion menu, from left to right.
+    pub fn menu_columns(&self) -> &[super::MenuColumn] {
+        &self.ui.menu.columns
+    }
+
+    /// Whether to show the counter of the completion menu.
+    pub const fn menu_counter(&self) -> bool {
+        self.ui.menu.counter
+    }
+
+    /// The maximum height of the completion menu, if set.
+    pub const fn menu_max_height(&self) -> Option<u32> {
+        self.ui.menu.max_height
+    }
+
+    /// Whether to show the scrollbar of the completion menu.
+    pub const fn menu_scrollbar(&self) -> bool {
+        self.ui.menu.scrollbar
+    }
+
+    /// The priority of the source named `name`.
+    pub fn source_priority(&self, name: &str) -> i32 {
+        self.completion.sorting.priorities.get(name).copied().unwrap_or(0)
+    }
+
+    /// How long to wait for the completions of the source named `name`
+    /// before giving up on them.
+    pub fn source_timeout(&self, name: &str) -> Duration {
+        let timeout = self
+            .completion
+            .source_timeouts
+            .get(name)
+            .copied()
+            .unwrap_or(self.completion.timeout);
+
+        Duration::from_millis(timeout)
+    }
+
     /// Whether completion hints are enabled.
     pub const fn hints_enabled(&self) -> bool {
         self.ui.hint.enable
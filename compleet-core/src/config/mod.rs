@@ -2,6 +2,8 @@ mod completion_config;
 mod config;
 mod ui_config;
 
+pub(crate) use completion_config::Comparator;
 use completion_config::CompletionConfig;
 pub(crate) use config::Config;
 use ui_config::UiConfig;
+pub(crate) use ui_config::{ColumnAlign, ColumnKind, MenuAnchor, MenuColumn};
@@ -18,13 +18,21 @@ pub(super) struct UiConfig {
 #[serde(deny_unknown_fields)]
 pub(super) struct DetailsConfig {
     #[serde(default = "default_details_border")]
-    border: Border,
+    pub(super) border: Border,
+
+    /// How many milliseconds an item has to stay selected before its source
+    /// is asked for the details it didn't send together with the item.
+    #[serde(default = "default_details_debounce")]
+    pub(super) debounce: u64,
 }
 
 impl Default for DetailsConfig {
     #[inline]
     fn default() -> Self {
-        Self { border: default_details_border() }
+        Self {
+            border: default_details_border(),
+            debounce: default_details_debounce(),
+        }
     }
 }
 
@@ -39,7 +47,7 @@ pub(super) struct HintConfig {
 pub(super) struct MenuConfig {
     /// Where to anchor the completion menu.
     #[serde(default)]
-    anchor: MenuAnchor,
+    pub(super) anchor: MenuAnchor,
 
     /// Whether to automatically display the completion menu when completion
     /// results are available. If `false` the menu won't be shown until asked
@@ -48,10 +56,27 @@ pub(super) struct MenuConfig {
     autoshow: bool,
 
     #[serde(default = "default_menu_border")]
-    border: Border,
+    pub(super) border: Border,
+
+    /// The columns shown for every completion, from left to right. Columns
+    /// that are empty for all the completions are hidden.
+    #[serde(default = "default_menu_columns")]
+    pub(super) columns: Vec<MenuColumn>,
+
+    /// Whether to show the index of the selected completion and the total
+    /// number of completions, e.g. `3/147`. The counter is shown in the top
+    /// edge of the border, or in the bottom one on Neovim 0.10+, so it's
+    /// hidden if neither is present.
+    #[serde(default)]
+    pub(super) counter: bool,
 
-    #[serde(default, deserialize_with = "deser_max_height")]
-    max_height: Option<u32>,
+    #[serde(default, deserialize_with = "deser_positive")]
+    pub(super) max_height: Option<u32>,
+
+    /// Whether to show a scrollbar on the right edge of the menu when it's
+    /// not tall enough to show all the completions.
+    #[serde(default = "yes")]
+    pub(super) scrollbar: bool,
 }
 
 impl Default for MenuConfig {
@@ -61,25 +86,132 @@ impl Default for MenuConfig {
             anchor: MenuAnchor::default(),
             autoshow: yes(),
             border: default_menu_border(),
+            columns: default_menu_columns(),
+            counter: false,
             max_height: None,
+            scrollbar: yes(),
         }
     }
 }
 
 #[derive(Debug, Deserialize)]
 pub(super) struct Border {
-    enable: bool,
-    style: WindowBorder,
+    pub(super) enable: bool,
+    pub(super) style: WindowBorder,
 }
 
-#[derive(Debug, Default, Deserialize)]
+/// A column of the completion menu. Can be configured with just the name of
+/// its kind, e.g. `"label"`, or with a table also setting its other fields.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(from = "ColumnConfig")]
+pub(crate) struct MenuColumn {
+    /// What the column shows.
+    pub(crate) kind: ColumnKind,
+
+    /// How the contents of the column are aligned.
+    pub(crate) align: ColumnAlign,
+
+    /// The maximum width of the column. Longer contents are truncated and
+    /// end with an ellipsis.
+    pub(crate) max_width: Option<u32>,
+
+    /// The highlight group used for the contents of the column, instead of
+    /// the one chosen by the item or its source.
+    pub(crate) hl_group: Option<String>,
+}
+
+/// What a column of the completion menu shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ColumnKind {
+    /// The icon of the item.
+    KindIcon,
+
+    /// The name of the item's kind.
+    KindText,
+
+    /// The label of the item.
+    Label,
+
+    /// The text shown right after the label, like the signature of a
+    /// function. If the column directly follows the `label` one the two
+    /// are joined without any spacing.
+    LabelDetail,
+
+    /// The short infos about the item.
+    Infos,
+
+    /// The name of the source that returned the item.
+    Source,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
 #[serde(rename_all = "lowercase")]
-pub(super) enum MenuAnchor {
-    /// TODO: docs.
+pub(crate) enum ColumnAlign {
+    Left,
+    Right,
+}
+
+impl From<ColumnKind> for MenuColumn {
+    fn from(kind: ColumnKind) -> Self {
+        Self {
+            kind,
+            // The infos are ragged left, i.e. they touch the right edge of
+            // the menu.
+            align: match kind {
+                ColumnKind::Infos => ColumnAlign::Right,
+                _ => ColumnAlign::Left,
+            },
+            max_width: None,
+            hl_group: None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ColumnConfig {
+    Kind(ColumnKind),
+    Table(ColumnTable),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ColumnTable {
+    name: ColumnKind,
+    align: Option<ColumnAlign>,
+    #[serde(default, deserialize_with = "deser_positive")]
+    max_width: Option<u32>,
+    hl_group: Option<String>,
+}
+
+impl From<ColumnConfig> for MenuColumn {
+    fn from(config: ColumnConfig) -> Self {
+        match config {
+            ColumnConfig::Kind(kind) => kind.into(),
+
+            ColumnConfig::Table(table) => {
+                let default = MenuColumn::from(table.name);
+                Self {
+                    align: table.align.unwrap_or(default.align),
+                    max_width: table.max_width,
+                    hl_group: table.hl_group,
+                    ..default
+                }
+            },
+        }
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum MenuAnchor {
+    /// The left edge of the menu is aligned with the cursor.
     #[default]
     Cursor,
 
-    /// TODO: docs.
+    /// The labels of the completions are aligned with the start of the
+    /// prefix being completed.
     Match,
 }
 
@@ -95,6 +227,10 @@ fn default_details_border() -> Border {
     }
 }
 
+fn default_details_debounce() -> u64 {
+    100
+}
+
 fn default_menu_border() -> Border {
     Border {
         enable: true,
@@ -102,11 +238,16 @@ fn default_menu_border() -> Border {
     }
 }
 
+fn default_menu_columns() -> Vec<MenuColumn> {
+    use ColumnKind::*;
+    [KindIcon, Label, LabelDetail, Infos].into_iter().map(Into::into).collect()
+}
+
 fn yes() -> bool {
     true
 }
 
-fn deser_max_height<'de, D>(deserializer: D) -> Result<Option<u32>, D::Error>
+fn deser_positive<'de, D>(deserializer: D) -> Result<Option<u32>, D::Error>
 where
     D: de::Deserializer<'de>,
 {
@@ -1,5 +1,10 @@
+use std::io;
+use std::path::PathBuf;
+
 use nvim_oxi as nvim;
 
+use crate::completion_source::SourceError;
+
 /// Alias for a `Result` with error type [`nvim_compleet::Error`](Error).
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -8,16 +13,52 @@ pub enum Error {
     #[error("can't setup more than once per session")]
     AlreadySetup,
 
+    #[error("error parsing `{option}` of Lua source: {why}")]
+    BadLuaSource { option: String, why: String },
+
     #[error("error parsing `{option}`: {why}")]
-    BadPreferences { option: serde_path_to_error::Path, why: String },
+    BadPreferences { option: String, why: String },
+
+    #[error("a source named \"{0}\" is already registered")]
+    DuplicateSource(String),
+
+    #[error("couldn't access the frecency data at {}: {err}", .path.display())]
+    FrecencyError { path: PathBuf, err: io::Error },
 
     #[error(transparent)]
     NvimError(#[from] nvim_oxi::Error),
+
+    #[error("source \"{name}\" failed: {err}")]
+    SourceError { name: &'static str, err: SourceError },
+
+    #[error(
+        "unknown source \"{name}\", the registered sources are: {}",
+        .registered.join(", ")
+    )]
+    UnknownSource { name: String, registered: Vec<&'static str> },
+}
+
+impl Error {
+    /// Prefixes the option of a [`BadPreferences`](Error::BadPreferences)
+    /// error with `sources.<name>`, leaving other errors untouched.
+    pub(crate) fn in_source_config(self, name: &str) -> Self {
+        match self {
+            Self::BadPreferences { option, why } => {
+                let option = match option.as_str() {
+                    "." => format!("sources.{name}"),
+                    _ => format!("sources.{name}.{option}"),
+                };
+                Self::BadPreferences { option, why }
+            },
+
+            other => other,
+        }
+    }
 }
 
 impl From<serde_path_to_error::Error<nvim::Error>> for Error {
     fn from(err: serde_path_to_error::Error<nvim::Error>) -> Self {
-        let option = err.path().to_owned();
+        let option = err.path().to_string();
 
         match err.into_inner() {
             nvim::Error::DeserializeError(why) => {
@@ -0,0 +1,499 @@
+//! A store of the completions accepted by the user, used to boost the ones
+//! that have been accepted frequently and/or recently.
+//!
+//! Every time a completion is accepted its score is incremented by one, and
+//! scores decay exponentially with a half-life of [`HALF_LIFE`] seconds.
+//! Entries whose score drops below [`MIN_SCORE`] are forgotten, and at most
+//! [`MAX_ENTRIES`] entries are kept.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{fs, io};
+
+use crate::Error;
+
+/// The number of seconds after which the score of an entry is halved.
+const HALF_LIFE: f64 = 7.0 * 24.0 * 60.0 * 60.0;
+
+/// Entries with a score lower than this are removed from the store.
+const MIN_SCORE: f64 = 0.125;
+
+/// The maximum number of entries kept in the store. When the limit is
+/// exceeded the entries with the lowest score are removed.
+const MAX_ENTRIES: usize = 4096;
+
+/// The first line of the file the store is saved to. It has to be updated
+/// every time the format changes.
+const HEADER: &str = "# compleet frecency v1";
+
+/// Maps the filetype of the buffer a completion was accepted in to the
+/// entries of all the completions accepted in buffers of that filetype,
+/// keyed by their text.
+type Entries = HashMap<String, HashMap<String, Entry>>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Entry {
+    /// The name of the source that returned the completion the last time it
+    /// was accepted.
+    pub(crate) source: String,
+
+    /// The prefix that was typed the last time the completion was accepted.
+    pub(crate) prefix: String,
+
+    /// How many times the completion has been accepted.
+    pub(crate) count: u32,
+
+    /// The score of the entry at the time of `last_used`.
+    score: f64,
+
+    /// When the completion was last accepted, in seconds since the Unix
+    /// epoch.
+    pub(crate) last_used: u64,
+}
+
+impl Entry {
+    /// The score of the entry at time `now`.
+    pub(crate) fn score(&self, now: u64) -> f64 {
+        let elapsed = now.saturating_sub(self.last_used) as f64;
+        self.score * 0.5_f64.powf(elapsed / HALF_LIFE)
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Frecency {
+    entries: Entries,
+
+    /// The file the store is loaded from and saved to, or `None` if the
+    /// store only lives in memory.
+    path: Option<PathBuf>,
+}
+
+impl Frecency {
+    /// Returns the boost of the completion with the given text accepted in a
+    /// buffer with the given filetype. Completions that have never been
+    /// accepted have a boost of zero.
+    ///
+    /// The boost grows logarithmically with the score, so that completions
+    /// accepted a similar number of times are considered equivalent and can
+    /// be sorted using other criteria.
+    pub(crate) fn boost(&self, filetype: &str, text: &str, now: u64) -> u32 {
+        let score = self
+            .entries
+            .get(filetype)
+            .and_then(|entries| entries.get(text))
+            .map(|entry| entry.score(now));
+
+        match score {
+            Some(score) if score >= MIN_SCORE => {
+                (score / MIN_SCORE).log2() as u32 + 1
+            },
+            _ => 0,
+        }
+    }
+
+    /// Returns the entries in the store sorted by descending score at time
+    /// `now`, together with the filetype and the text of their completion.
+    pub(crate) fn entries(&self, now: u64) -> Vec<(&str, &str, &Entry)> {
+        let mut entries = self
+            .entries
+            .iter()
+            .flat_map(|(ft, entries)| {
+                entries.iter().map(move |(text, entry)| {
+                    (ft.as_str(), text.as_str(), entry)
+                })
+            })
+            .collect::<Vec<_>>();
+
+        entries.sort_by(|(_, _, a), (_, _, b)| {
+            b.score(now).total_cmp(&a.score(now))
+        });
+
+        entries
+    }
+
+    /// Loads the store from the file at `path`, which is also where the
+    /// store will be saved to. A missing file results in an empty store.
+    pub(crate) fn load(path: PathBuf) -> crate::Result<Self> {
+        let entries = match fs::read_to_string(&path) {
+            Ok(contents) => self::parse(&contents),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                Entries::new()
+            },
+            Err(err) => return Err(Error::FrecencyError { path, err }),
+        };
+
+        Ok(Self { entries, path: Some(path) })
+    }
+
+    /// The number of entries in the store.
+    pub(crate) fn len(&self) -> usize {
+        self.entries.values().map(HashMap::len).sum()
+    }
+
+    /// Merges the entries of `other` into the store. If both stores contain
+    /// the same completion the most recently used entry wins.
+    fn merge(&mut self, other: Entries) {
+        for (filetype, other) in other {
+            let entries = self.entries.entry(filetype).or_default();
+
+            for (text, entry) in other {
+                match entries.get(&text) {
+                    Some(old) if old.last_used >= entry.last_used => {},
+                    _ => {
+                        entries.insert(text, entry);
+                    },
+                }
+            }
+        }
+    }
+
+    /// Removes the entries that have decayed below [`MIN_SCORE`] and, if
+    /// there are still more than [`MAX_ENTRIES`], the ones with the lowest
+    /// score.
+    fn prune(&mut self, now: u64) {
+        self.retain(|entry| entry.score(now) >= MIN_SCORE);
+
+        if self.len() <= MAX_ENTRIES {
+            return;
+        }
+
+        let mut scores = self
+            .entries
+            .values()
+            .flat_map(HashMap::values)
+            .map(|entry| entry.score(now))
+            .collect::<Vec<_>>();
+
+        scores.sort_by(|a, b| b.total_cmp(a));
+        let threshold = scores[MAX_ENTRIES - 1];
+
+        self.retain(|entry| entry.score(now) >= threshold);
+    }
+
+    /// Records that a completion has been accepted at time `now`.
+    pub(crate) fn record(
+        &mut self,
+        filetype: &str,
+        source: &str,
+        prefix: &str,
+        text: &str,
+        now: u64,
+    ) {
+        let entry = self
+            .entries
+            .entry(filetype.to_owned())
+            .or_default()
+            .entry(text.to_owned())
+            .or_insert_with(|| Entry {
+                source: String::new(),
+                prefix: String::new(),
+                count: 0,
+                score: 0.0,
+                last_used: now,
+            });
+
+        entry.score = entry.score(now) + 1.0;
+        entry.last_used = now;
+        entry.count += 1;
+        source.clone_into(&mut entry.source);
+        prefix.clone_into(&mut entry.prefix);
+
+        if self.len() > MAX_ENTRIES {
+            self.prune(now);
+        }
+    }
+
+    /// Only keeps the entries for which `f` returns `true`.
+    fn retain<F: Fn(&Entry) -> bool>(&mut self, f: F) {
+        for entries in self.entries.values_mut() {
+            entries.retain(|_, entry| f(entry));
+        }
+        self.entries.retain(|_, entries| !entries.is_empty());
+    }
+
+    /// Saves the store to the file it was loaded from, merging it with the
+    /// entries that have been saved in the meantime by other Neovim
+    /// instances. Does nothing if the store wasn't loaded from a file.
+    pub(crate) fn save(&mut self) -> crate::Result<()> {
+        let path = match &self.path {
+            Some(path) => path.clone(),
+            None => return Ok(()),
+        };
+
+        let to_err = |err| Error::FrecencyError { path: path.clone(), err };
+
+        match fs::read_to_string(&path) {
+            Ok(contents) => self.merge(self::parse(&contents)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {},
+            Err(err) => return Err(to_err(err)),
+        }
+
+        self.prune(self::now());
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(to_err)?;
+        }
+
+        fs::write(&path, self::serialize(&self.entries)).map_err(to_err)
+    }
+
+    /// Removes all the entries from the store and deletes the file it's
+    /// saved to.
+    pub(crate) fn wipe(&mut self) -> crate::Result<()> {
+        self.entries.clear();
+
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        match fs::remove_file(path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                Err(Error::FrecencyError { path: path.clone(), err })
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// The file the store is saved to, if any.
+    #[inline]
+    pub(crate) fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+}
+
+/// The current time in seconds since the Unix epoch.
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+/// Serializes the entries into a header line followed by one line per
+/// entry, each containing the tab-separated fields
+/// `last_used count score filetype source prefix text`.
+fn serialize(entries: &Entries) -> String {
+    let mut out = String::new();
+    out.push_str(HEADER);
+    out.push('\n');
+
+    let entries = entries.iter().flat_map(|(filetype, entries)| {
+        entries.iter().map(move |(text, entry)| (filetype, text, entry))
+    });
+
+    for (filetype, text, entry) in entries {
+        let _ = writeln!(
+            out,
+            "{}\t{}\t{:.3}\t{}\t{}\t{}\t{}",
+            entry.last_used,
+            entry.count,
+            entry.score,
+            escape(filetype),
+            escape(&entry.source),
+            escape(&entry.prefix),
+            escape(text),
+        );
+    }
+
+    out
+}
+
+/// Parses the contents of a file written by [`serialize`]. Malformed lines
+/// are skipped, and files with a different header are ignored entirely.
+fn parse(contents: &str) -> Entries {
+    let mut entries = Entries::new();
+    let mut lines = contents.lines();
+
+    if lines.next() != Some(HEADER) {
+        return entries;
+    }
+
+    let parsed = lines.filter_map(|line| {
+        let mut fields = line.split('\t');
+
+        let last_used = fields.next()?.parse().ok()?;
+        let count = fields.next()?.parse().ok()?;
+        let score = fields.next()?.parse().ok()?;
+        let filetype = unescape(fields.next()?);
+        let source = unescape(fields.next()?);
+        let prefix = unescape(fields.next()?);
+        let text = unescape(fields.next()?);
+
+        if fields.next().is_some() {
+            return None;
+        }
+
+        let entry = Entry { source, prefix, count, score, last_used };
+
+        Some((filetype, text, entry))
+    });
+
+    for (filetype, text, entry) in parsed {
+        entries.entry(filetype).or_default().insert(text, entry);
+    }
+
+    entries
+}
+
+/// Escapes backslashes, tabs and newlines so that every field fits in a
+/// single tab-separated column.
+fn escape(field: &str) -> String {
+    let mut escaped = String::with_capacity(field.len());
+
+    for char in field.chars() {
+        match char {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            _ => escaped.push(char),
+        }
+    }
+
+    escaped
+}
+
+fn unescape(field: &str) -> String {
+    let mut unescaped = String::with_capacity(field.len());
+    let mut chars = field.chars();
+
+    while let Some(char) = chars.next() {
+        if char != '\\' {
+            unescaped.push(char);
+            continue;
+        }
+
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 24 * 60 * 60;
+
+    #[test]
+    fn never_accepted() {
+        let frecency = Frecency::default();
+        assert_eq!(0, frecency.boost("rust", "foo", 0));
+    }
+
+    #[test]
+    fn boost_grows_with_use() {
+        let mut frecency = Frecency::default();
+
+        frecency.record("rust", "lsp", "f", "foo", 0);
+        let once = frecency.boost("rust", "foo", 0);
+
+        for _ in 0..10 {
+            frecency.record("rust", "lsp", "f", "foo", 0);
+        }
+        let many = frecency.boost("rust", "foo", 0);
+
+        assert!(once > 0);
+        assert!(many > once);
+    }
+
+    #[test]
+    fn boost_is_per_filetype() {
+        let mut frecency = Frecency::default();
+        frecency.record("rust", "lsp", "f", "foo", 0);
+        assert_eq!(0, frecency.boost("lua", "foo", 0));
+    }
+
+    #[test]
+    fn decay() {
+        let mut frecency = Frecency::default();
+        frecency.record("rust", "lsp", "f", "foo", 0);
+
+        let fresh = frecency.boost("rust", "foo", 0);
+        let week_old = frecency.boost("rust", "foo", 7 * DAY);
+        let year_old = frecency.boost("rust", "foo", 365 * DAY);
+
+        assert!(fresh > week_old);
+        assert_eq!(0, year_old);
+    }
+
+    #[test]
+    fn prune_decayed() {
+        let mut frecency = Frecency::default();
+        frecency.record("rust", "lsp", "f", "foo", 0);
+        frecency.record("rust", "lsp", "b", "bar", 365 * DAY);
+        frecency.prune(365 * DAY);
+
+        assert_eq!(1, frecency.len());
+        assert_eq!("bar", frecency.entries(365 * DAY)[0].1);
+    }
+
+    #[test]
+    fn bounded_size() {
+        let mut frecency = Frecency::default();
+
+        for i in 0..MAX_ENTRIES + 10 {
+            frecency.record("rust", "lsp", "", &i.to_string(), i as u64);
+        }
+
+        assert!(frecency.len() <= MAX_ENTRIES);
+
+        // The most recent entry has the highest score, so it must be kept.
+        let last = (MAX_ENTRIES + 9).to_string();
+        assert!(frecency.boost("rust", &last, (MAX_ENTRIES + 9) as u64) > 0);
+    }
+
+    #[test]
+    fn roundtrip() {
+        let mut frecency = Frecency::default();
+        frecency.record("rust", "lsp", "f", "foo", 10);
+        frecency.record("rust", "lsp", "f", "foo", 20);
+        frecency.record("", "buffer", "a\\b", "a\tb\nc\\", 30);
+
+        let parsed = parse(&serialize(&frecency.entries));
+
+        assert_eq!(frecency.entries.len(), parsed.len());
+
+        for (ft, text, entry) in frecency.entries(30) {
+            let other = &parsed[ft][text];
+            assert_eq!(entry.source, other.source);
+            assert_eq!(entry.prefix, other.prefix);
+            assert_eq!(entry.count, other.count);
+            assert_eq!(entry.last_used, other.last_used);
+            assert!((entry.score - other.score).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn parse_skips_malformed_lines() {
+        let contents = format!(
+            "{HEADER}\n10\t1\t1.000\trust\tlsp\tf\tfoo\nnot an entry\n"
+        );
+        assert_eq!(1, parse(&contents)["rust"].len());
+        assert!(parse("10\t1\t1.000\trust\tlsp\tf\tfoo\n").is_empty());
+    }
+
+    #[test]
+    fn merge_keeps_most_recent() {
+        let mut frecency = Frecency::default();
+        frecency.record("rust", "lsp", "f", "foo", 10);
+
+        let mut other = Frecency::default();
+        other.record("rust", "buffer", "fo", "foo", 20);
+        other.record("rust", "buffer", "b", "bar", 20);
+
+        frecency.merge(other.entries);
+
+        assert_eq!(2, frecency.len());
+        let entry = &frecency.entries["rust"]["foo"];
+        assert_eq!("buffer", entry.source);
+    }
+}
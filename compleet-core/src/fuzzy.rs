@@ -0,0 +1,271 @@
+//! A fuzzy matcher based on the algorithm used by [fzy], i.e. a modified
+//! version of the Smith-Waterman algorithm which prefers matches that are
+//! consecutive, or that happen right after a word boundary, a path separator
+//! or in a camelCase transition.
+//!
+//! [fzy]: https://github.com/jhawthorn/fzy
+
+pub(crate) type Score = f64;
+
+pub(crate) const SCORE_MIN: Score = Score::NEG_INFINITY;
+pub(crate) const SCORE_MAX: Score = Score::INFINITY;
+
+const SCORE_GAP_LEADING: Score = -0.005;
+const SCORE_GAP_TRAILING: Score = -0.005;
+const SCORE_GAP_INNER: Score = -0.01;
+const SCORE_MATCH_CONSECUTIVE: Score = 1.0;
+const SCORE_MATCH_SLASH: Score = 0.9;
+const SCORE_MATCH_WORD: Score = 0.8;
+const SCORE_MATCH_CAPITAL: Score = 0.7;
+const SCORE_MATCH_DOT: Score = 0.6;
+
+/// Haystacks longer than this (in characters) are matched but not scored,
+/// to avoid allocating huge matrices.
+const MAX_HAYSTACK_LEN: usize = 1024;
+
+/// The result of successfully matching a needle against a haystack.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct FuzzyMatch {
+    /// How good the match is. Higher is better.
+    pub(crate) score: Score,
+
+    /// The byte offsets in the haystack of every matched character.
+    pub(crate) matched_bytes: Vec<usize>,
+}
+
+/// Fuzzy matches `needle` against `haystack`, returning `None` if not all the
+/// characters of the needle appear in the haystack in the same order.
+///
+/// The match is case insensitive unless the needle contains at least one
+/// uppercase character.
+pub(crate) fn fuzzy_match(needle: &str, haystack: &str) -> Option<FuzzyMatch> {
+    if needle.is_empty() {
+        return Some(FuzzyMatch::default());
+    }
+
+    let case_sensitive = needle.chars().any(char::is_uppercase);
+
+    let eq = |a: char, b: char| {
+        if case_sensitive {
+            a == b
+        } else {
+            a.to_lowercase().eq(b.to_lowercase())
+        }
+    };
+
+    let needle = needle.chars().collect::<Vec<_>>();
+    let haystack = haystack.char_indices().collect::<Vec<_>>();
+
+    // Start with a quick check to see if the needle is a subsequence of the
+    // haystack, recording the greedy positions along the way.
+    let mut greedy = Vec::with_capacity(needle.len());
+    let mut hay = haystack.iter().enumerate();
+    for &n in &needle {
+        let (pos, _) = hay.find(|(_, &(_, h))| eq(n, h))?;
+        greedy.push(pos);
+    }
+
+    let (score, positions) = if needle.len() == haystack.len() {
+        // The needle is a subsequence of the haystack and they have the same
+        // length, so they must be equal.
+        (SCORE_MAX, greedy)
+    } else if haystack.len() > MAX_HAYSTACK_LEN {
+        (SCORE_MIN, greedy)
+    } else {
+        score_positions(&needle, &haystack, eq)
+    };
+
+    Some(FuzzyMatch {
+        score,
+        matched_bytes: positions.into_iter().map(|i| haystack[i].0).collect(),
+    })
+}
+
+/// Returns the best score of the needle in the haystack together with the
+/// indices of the matched characters.
+fn score_positions<F>(
+    needle: &[char],
+    haystack: &[(usize, char)],
+    eq: F,
+) -> (Score, Vec<usize>)
+where
+    F: Fn(char, char) -> bool,
+{
+    let (n, m) = (needle.len(), haystack.len());
+
+    let bonus = compute_bonus(haystack);
+
+    // `d[i][j]` is the best score of the first `i + 1` needle characters
+    // that *ends* with a match at haystack position `j`, while `mm[i][j]` is
+    // the best score of the first `i + 1` needle characters matched anywhere
+    // in the first `j + 1` haystack characters.
+    let mut d = vec![vec![SCORE_MIN; m]; n];
+    let mut mm = vec![vec![SCORE_MIN; m]; n];
+
+    for i in 0..n {
+        let mut prev_score = SCORE_MIN;
+
+        let gap_score =
+            if i == n - 1 { SCORE_GAP_TRAILING } else { SCORE_GAP_INNER };
+
+        for j in 0..m {
+            if eq(needle[i], haystack[j].1) {
+                let score = if i == 0 {
+                    (j as Score * SCORE_GAP_LEADING) + bonus[j]
+                } else if j > 0 {
+                    (mm[i - 1][j - 1] + bonus[j])
+                        .max(d[i - 1][j - 1] + SCORE_MATCH_CONSECUTIVE)
+                } else {
+                    SCORE_MIN
+                };
+
+                d[i][j] = score;
+                prev_score = score.max(prev_score + gap_score);
+            } else {
+                prev_score += gap_score;
+            }
+
+            mm[i][j] = prev_score;
+        }
+    }
+
+    // Backtrack to find the positions of the optimal match.
+    let mut positions = vec![0; n];
+    let mut match_required = false;
+    let mut j = m;
+
+    for i in (0..n).rev() {
+        while j > 0 {
+            j -= 1;
+
+            if d[i][j] != SCORE_MIN && (match_required || d[i][j] == mm[i][j])
+            {
+                // If this score was determined using
+                // `SCORE_MATCH_CONSECUTIVE`, the previous character must
+                // also be a match.
+                match_required = i > 0
+                    && j > 0
+                    && mm[i][j] == d[i - 1][j - 1] + SCORE_MATCH_CONSECUTIVE;
+
+                positions[i] = j;
+                break;
+            }
+        }
+    }
+
+    (mm[n - 1][m - 1], positions)
+}
+
+/// Computes the bonus of matching each character of the haystack based on
+/// the character that precedes it.
+fn compute_bonus(haystack: &[(usize, char)]) -> Vec<Score> {
+    // The start of the haystack is treated like it comes right after a path
+    // separator.
+    let mut last = '/';
+
+    haystack
+        .iter()
+        .map(|&(_, char)| {
+            let bonus = if char.is_lowercase() || char.is_numeric() {
+                bonus_after(last)
+            } else if char.is_uppercase() {
+                if last.is_lowercase() {
+                    SCORE_MATCH_CAPITAL
+                } else {
+                    bonus_after(last)
+                }
+            } else {
+                0.0
+            };
+
+            last = char;
+            bonus
+        })
+        .collect()
+}
+
+fn bonus_after(char: char) -> Score {
+    match char {
+        '/' | '\\' => SCORE_MATCH_SLASH,
+        '-' | '_' | ' ' => SCORE_MATCH_WORD,
+        '.' => SCORE_MATCH_DOT,
+        _ => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(needle: &str, haystack: &str) -> Score {
+        fuzzy_match(needle, haystack).unwrap().score
+    }
+
+    fn positions(needle: &str, haystack: &str) -> Vec<usize> {
+        fuzzy_match(needle, haystack).unwrap().matched_bytes
+    }
+
+    #[test]
+    fn no_match() {
+        assert_eq!(None, fuzzy_match("abc", "acb"));
+        assert_eq!(None, fuzzy_match("abc", ""));
+    }
+
+    #[test]
+    fn empty_needle() {
+        assert_eq!(Some(FuzzyMatch::default()), fuzzy_match("", "foo"));
+    }
+
+    #[test]
+    fn exact_match() {
+        assert_eq!(SCORE_MAX, score("foo", "foo"));
+        assert_eq!(vec![0, 1, 2], positions("foo", "foo"));
+    }
+
+    #[test]
+    fn smart_case() {
+        assert!(fuzzy_match("foo", "FooBar").is_some());
+        assert!(fuzzy_match("Foo", "FooBar").is_some());
+        assert!(fuzzy_match("Foo", "fooBar").is_none());
+    }
+
+    #[test]
+    fn prefers_consecutive() {
+        assert!(score("foo", "foobar") > score("foo", "fxoxobar"));
+    }
+
+    #[test]
+    fn prefers_start_of_word() {
+        assert!(score("ab", "a_b") > score("ab", "axb"));
+        assert_eq!(vec![0, 4], positions("fb", "foo_bar"));
+        assert_eq!(vec![0, 4], positions("fb", "foo.bar"));
+    }
+
+    #[test]
+    fn prefers_camel_case() {
+        assert!(score("fb", "fooBar") > score("fb", "foobar"));
+        assert_eq!(vec![0, 3], positions("fb", "fooBar"));
+    }
+
+    #[test]
+    fn prefers_after_slash() {
+        assert!(score("b", "foo/bar") > score("b", "foo_bar"));
+        assert_eq!(vec![4, 5], positions("ba", "foo/bar/baz"));
+    }
+
+    #[test]
+    fn prefers_shorter() {
+        assert!(score("abc", "abcd") > score("abc", "abcde"));
+    }
+
+    #[test]
+    fn prefers_leading() {
+        assert!(score("b", "bxxx") > score("b", "xxbx"));
+    }
+
+    #[test]
+    fn positions_are_bytes() {
+        assert_eq!(vec![0, 3], positions("éa", "éxa"));
+        assert_eq!(vec![2, 6], positions("ΑΒ", "αΑxxΒ"));
+    }
+}
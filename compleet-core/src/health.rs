@@ -0,0 +1,316 @@
+//! The report shown by `:checkhealth compleet`. It's built here and rendered
+//! by the `compleet.health` Lua module using the functions in `vim.health`.
+
+use nvim_oxi::{
+    self as nvim,
+    api::{self, Buffer},
+    object,
+    Array,
+    Dictionary,
+    FromObject,
+    Object,
+};
+use serde::Deserialize;
+
+use crate::{hlgroups, Client};
+
+/// Returns whether a treesitter parser is available for the filetype `_A`.
+const HAS_PARSER: &str = r#"
+(function(ft)
+  local get_lang = vim.treesitter.language.get_lang
+  local lang = get_lang and get_lang(ft) or ft
+  return (pcall(vim.treesitter.get_string_parser, "", lang))
+end)(_A)
+"#;
+
+/// Returns the LSP clients attached to the buffer `_A`.
+const LSP_CLIENTS: &str = r#"
+(function(bufnr)
+  local clients = {}
+  for _, client in pairs(vim.lsp.get_active_clients({ bufnr = bufnr })) do
+    table.insert(clients, {
+      id = client.id,
+      name = client.name,
+      offset_encoding = client.offset_encoding,
+      completion_provider =
+        client.server_capabilities.completionProvider ~= nil,
+    })
+  end
+  return clients
+end)(_A)
+"#;
+
+/// The severity of a health check entry, each one reported with the
+/// function of the same name in `vim.health`.
+#[derive(Clone, Copy)]
+enum Level {
+    Ok,
+    Info,
+    Warn,
+    Error,
+}
+
+struct Entry {
+    level: Level,
+    msg: String,
+    advice: Option<String>,
+}
+
+/// A section of the report, reported with `vim.health.start`.
+struct Section {
+    name: &'static str,
+    entries: Vec<Entry>,
+}
+
+impl Section {
+    fn new(name: &'static str) -> Self {
+        Self { name, entries: Vec::new() }
+    }
+
+    fn push<M: Into<String>>(
+        &mut self,
+        level: Level,
+        msg: M,
+        advice: Option<String>,
+    ) {
+        self.entries.push(Entry { level, msg: msg.into(), advice });
+    }
+
+    fn ok<M: Into<String>>(&mut self, msg: M) {
+        self.push(Level::Ok, msg, None)
+    }
+
+    fn info<M: Into<String>>(&mut self, msg: M) {
+        self.push(Level::Info, msg, None)
+    }
+}
+
+impl From<Section> for Object {
+    fn from(section: Section) -> Self {
+        let entries = section
+            .entries
+            .into_iter()
+            .map(|Entry { level, msg, advice }| {
+                let level = match level {
+                    Level::Ok => "ok",
+                    Level::Info => "info",
+                    Level::Warn => "warn",
+                    Level::Error => "error",
+                };
+
+                Object::from(Dictionary::from_iter([
+                    ("level", Object::from(level)),
+                    ("msg", Object::from(msg)),
+                    ("advice", advice.map_or_else(Object::nil, Object::from)),
+                ]))
+            })
+            .collect::<Array>();
+
+        Dictionary::from_iter([
+            ("name", Object::from(section.name)),
+            ("entries", Object::from(entries)),
+        ])
+        .into()
+    }
+}
+
+/// Called by `:checkhealth compleet`. Returns a list of sections, each one
+/// containing a list of entries.
+pub(crate) fn check(client: &Client, (): ()) -> nvim::Result<Array> {
+    let buf = Buffer::current();
+
+    let sections = [
+        self::setup(client),
+        self::sources(client, &buf),
+        self::config(client),
+        self::highlight_groups()?,
+        self::treesitter(client, &buf)?,
+        self::lsp_clients(&buf)?,
+    ];
+
+    Ok(sections.into_iter().map(Object::from).collect())
+}
+
+fn setup(client: &Client) -> Section {
+    let mut section = Section::new("Setup");
+
+    if client.already_setup() {
+        section.ok("`setup` has been called");
+    } else {
+        section.push(
+            Level::Error,
+            "`setup` hasn't been called",
+            Some("add `require(\"compleet\").setup()` to your config".into()),
+        );
+    }
+
+    section
+}
+
+fn sources(client: &Client, buf: &Buffer) -> Section {
+    let mut section = Section::new("Sources");
+
+    let sources = client.should_attach(buf);
+
+    if sources.is_empty() {
+        section.push(Level::Warn, "no sources have been registered", None);
+    }
+
+    for (name, should_attach) in sources {
+        match should_attach {
+            Ok(true) if client.is_running(buf, name) => {
+                section.ok(format!("{name}: running in the current buffer"))
+            },
+
+            Ok(true) if client.is_attached(buf) => section.push(
+                Level::Warn,
+                format!("{name}: stopped in the current buffer"),
+                Some(format!("start it with `:CompleetStart[!] {name}`")),
+            ),
+
+            Ok(true) => section.push(
+                Level::Warn,
+                format!(
+                    "{name}: can attach to the current buffer, but the \
+                     client isn't attached to it"
+                ),
+                Some("make sure `setup` has been called".into()),
+            ),
+
+            Ok(false) => section.info(format!(
+                "{name}: not attached to the current buffer, check the \
+                 `sources.{name}` options"
+            )),
+
+            Err(err) => section.push(
+                Level::Error,
+                format!("{name}: couldn't check whether to attach: {err}"),
+                None,
+            ),
+        }
+    }
+
+    section
+}
+
+fn config(client: &Client) -> Section {
+    let mut section = Section::new("Configuration");
+
+    if client.already_setup() {
+        section.info(format!("{:#?}", &*client.config()));
+    } else {
+        section.info("the default configuration will be used");
+    }
+
+    section
+}
+
+fn highlight_groups() -> nvim::Result<Section> {
+    let mut section = Section::new("Highlight groups");
+
+    for &group in hlgroups::ALL {
+        if api::call_function::<_, i64>("hlexists", (group,))? == 0 {
+            section.push(
+                Level::Warn,
+                format!("{group} isn't defined"),
+                Some("it's defined when `setup` is called".into()),
+            );
+            continue;
+        }
+
+        let id = api::call_function::<_, i64>("hlID", (group,))?;
+        let linked = api::call_function::<_, i64>("synIDtrans", (id,))?;
+        let linked =
+            api::call_function::<_, String>("synIDattr", (linked, "name"))?;
+
+        if linked == group {
+            section.ok(format!("{group} is defined"));
+        } else {
+            section.ok(format!("{group} links to {linked}"));
+        }
+    }
+
+    Ok(section)
+}
+
+fn treesitter(client: &Client, buf: &Buffer) -> nvim::Result<Section> {
+    let mut section = Section::new("Treesitter");
+
+    let mut filetypes = client
+        .attached_buffers()
+        .iter()
+        .chain(Some(buf))
+        .map(|buf| buf.get_option::<String>("filetype"))
+        .collect::<nvim::Result<Vec<_>>>()?;
+
+    filetypes.retain(|ft| !ft.is_empty());
+    filetypes.sort();
+    filetypes.dedup();
+
+    if filetypes.is_empty() {
+        section.info("no buffers with a filetype are open");
+    }
+
+    for ft in filetypes {
+        if self::luaeval::<bool>(HAS_PARSER, ft.as_str())? {
+            section.ok(format!("{ft}: highlighter available"));
+        } else {
+            section.push(
+                Level::Info,
+                format!("{ft}: no parser found, highlighter not available"),
+                None,
+            );
+        }
+    }
+
+    Ok(section)
+}
+
+#[derive(Deserialize)]
+struct LspClient {
+    id: u32,
+    name: String,
+    offset_encoding: String,
+    completion_provider: bool,
+}
+
+fn lsp_clients(buf: &Buffer) -> nvim::Result<Section> {
+    let mut section = Section::new("LSP clients");
+
+    let clients = Vec::<LspClient>::deserialize(object::Deserializer::new(
+        self::luaeval::<Object>(LSP_CLIENTS, buf.clone())?,
+    ))?;
+
+    if clients.is_empty() {
+        section.info("no LSP clients attached to the current buffer");
+    }
+
+    for LspClient { id, name, offset_encoding, completion_provider } in clients
+    {
+        if completion_provider {
+            section.ok(format!(
+                "{name} (id {id}): attached, offset encoding is \
+                 {offset_encoding}"
+            ));
+        } else {
+            section.push(
+                Level::Warn,
+                format!(
+                    "{name} (id {id}): attached, but can't provide \
+                     completions"
+                ),
+                None,
+            );
+        }
+    }
+
+    Ok(section)
+}
+
+/// Evaluates the Lua expression `expr` with `_A` set to `arg`.
+fn luaeval<R: FromObject>(
+    expr: &str,
+    arg: impl Into<Object>,
+) -> nvim::Result<R> {
+    api::call_function("luaeval", (expr, arg.into()))
+}
@@ -15,13 +15,62 @@ pub(crate) fn setup() -> nvim::Result<()> {
     api::set_hl(0, HINT, Some(&opts.link("Comment").build()))?;
     api::set_hl(0, MENU, Some(&opts.link("NormalFloat").build()))?;
     api::set_hl(0, MENU_BORDER, Some(&opts.link("FloatBorder").build()))?;
-    api::set_hl(0, MENU_MATCHING, Some(&opts.bold(true).build()))?;
+    api::set_hl(0, MENU_COUNTER, Some(&opts.link(MENU_BORDER).build()))?;
+    api::set_hl(0, MENU_INFOS, Some(&opts.link("Comment").build()))?;
+    api::set_hl(0, MENU_LABEL_DETAIL, Some(&opts.link("Comment").build()))?;
+    api::set_hl(
+        0,
+        MENU_SCROLLBAR_GUTTER,
+        Some(&opts.link("PmenuSbar").build()),
+    )?;
+    api::set_hl(
+        0,
+        MENU_SCROLLBAR_THUMB,
+        Some(&opts.link("PmenuThumb").build()),
+    )?;
     api::set_hl(0, MENU_SELECTED, Some(&opts.link("PmenuSel").build()))?;
 
+    // The builder keeps the last link, so the groups that only set some
+    // attributes need a new one.
+    let attrs = || {
+        let mut opts = SetHighlightOpts::builder();
+        opts.default(true);
+        opts
+    };
+
+    api::set_hl(
+        0,
+        MENU_DEPRECATED,
+        Some(&attrs().strikethrough(true).build()),
+    )?;
+    api::set_hl(0, MENU_MATCHING, Some(&attrs().bold(true).build()))?;
+
     Ok(())
 }
 
-pub(crate) use consts::*;
+pub use consts::*;
+
+/// All the highlight groups defined in [`setup`].
+pub(crate) const ALL: &[&str] = &[
+    BAD_OPTION_PATH,
+    ERROR_MSG_TAG,
+    INFO_MSG_TAG,
+    MSG_DQUOTED,
+    WARNING_MSG_TAG,
+    DETAILS,
+    DETAILS_BORDER,
+    HINT,
+    MENU,
+    MENU_BORDER,
+    MENU_COUNTER,
+    MENU_DEPRECATED,
+    MENU_INFOS,
+    MENU_LABEL_DETAIL,
+    MENU_MATCHING,
+    MENU_SCROLLBAR_GUTTER,
+    MENU_SCROLLBAR_THUMB,
+    MENU_SELECTED,
+];
 
 mod consts {
     pub use messages::*;
@@ -61,10 +110,30 @@ mod consts {
         /// Highlights the border of the completion menu.
         pub const MENU_BORDER: &str = "CompleetMenuBorder";
 
+        /// Highlights the counter of the completion menu.
+        pub const MENU_COUNTER: &str = "CompleetMenuCounter";
+
+        /// Highlights the labels of deprecated completion items.
+        pub const MENU_DEPRECATED: &str = "CompleetMenuDeprecated";
+
+        /// Highlights the infos of the completion items, unless their source
+        /// sets a different highlight group.
+        pub const MENU_INFOS: &str = "CompleetMenuInfos";
+
+        /// Highlights the text displayed right after the label of a
+        /// completion item.
+        pub const MENU_LABEL_DETAIL: &str = "CompleetMenuLabelDetail";
+
         /// Highlights the characters where a completion item matches the
         /// current completion prefix.
         pub const MENU_MATCHING: &str = "CompleetMenuMatchingChars";
 
+        /// Highlights the track of the completion menu's scrollbar.
+        pub const MENU_SCROLLBAR_GUTTER: &str = "CompleetMenuScrollbarGutter";
+
+        /// Highlights the thumb of the completion menu's scrollbar.
+        pub const MENU_SCROLLBAR_THUMB: &str = "CompleetMenuScrollbarThumb";
+
         /// Highlights the currently selected completion item.
         pub const MENU_SELECTED: &str = "CompleetMenuSelected";
     }
@@ -0,0 +1,128 @@
+use nvim_oxi::{self as nvim, api::Buffer};
+
+/// Used to reindent the lines of a multi-line completion text when inserting
+/// it into a buffer.
+///
+/// The continuation lines of a completion are indented relative to the line
+/// the completion is inserted on, and every leading tab in them stands for
+/// one level of indentation, just like in LSP snippets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Indent {
+    /// The indentation of the line the completion is inserted on.
+    base: String,
+
+    /// The string inserted for every level of indentation.
+    unit: String,
+}
+
+impl Indent {
+    /// Creates a new [`Indent`] for inserting text on `line`, reading the
+    /// indentation options of `buf`.
+    pub(crate) fn from_buffer(buf: &Buffer, line: &str) -> nvim::Result<Self> {
+        let expandtab = buf.get_option::<bool>("expandtab")?;
+
+        let unit = if expandtab {
+            let shiftwidth = match buf.get_option::<i64>("shiftwidth")? {
+                // A `'shiftwidth'` of zero means the `'tabstop'` is used.
+                0 => buf.get_option::<i64>("tabstop")?,
+                sw => sw,
+            };
+            " ".repeat(shiftwidth.max(0) as usize)
+        } else {
+            String::from("\t")
+        };
+
+        Ok(Self::new(line, unit))
+    }
+
+    pub(crate) fn new(line: &str, unit: String) -> Self {
+        let base = line
+            .char_indices()
+            .find(|&(_, char)| !matches!(char, ' ' | '\t'))
+            .map_or(line, |(idx, _)| &line[..idx])
+            .to_owned();
+
+        Self { base, unit }
+    }
+
+    /// Splits `text` into lines, reindenting all of them except the first
+    /// one. Empty lines are left empty.
+    pub(crate) fn apply(&self, text: &str) -> Vec<String> {
+        let mut lines = text
+            .split('\n')
+            .map(|line| line.strip_suffix('\r').unwrap_or(line));
+
+        let first = lines.next().unwrap_or_default().to_owned();
+
+        std::iter::once(first)
+            .chain(lines.map(|line| {
+                if line.is_empty() {
+                    return String::new();
+                }
+
+                let body = line.trim_start_matches('\t');
+                let levels = line.len() - body.len();
+
+                format!("{}{}{body}", self.base, self.unit.repeat(levels))
+            }))
+            .collect()
+    }
+}
+
+/// Returns the position right after the `lines` inserted at `(row, col)`,
+/// where columns are byte offsets.
+pub(crate) fn end_position(
+    lines: &[String],
+    row: usize,
+    col: usize,
+) -> (usize, usize) {
+    match lines {
+        [] => (row, col),
+        [line] => (row, col + line.len()),
+        [.., last] => (row + lines.len() - 1, last.len()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_line() {
+        let indent = Indent::new("    foo", "  ".into());
+        assert_eq!(vec!["foo"], indent.apply("foo"));
+    }
+
+    #[test]
+    fn continuation_lines() {
+        let indent = Indent::new("    fn", "    ".into());
+        assert_eq!(
+            vec!["fn foo() {", "        bar", "    }"],
+            indent.apply("fn foo() {\n\tbar\n}"),
+        );
+    }
+
+    #[test]
+    fn tabs() {
+        let indent = Indent::new("\tif", "\t".into());
+        assert_eq!(
+            vec!["if x {", "\t\t\ty", "\t}"],
+            indent.apply("if x {\n\t\ty\n}"),
+        );
+    }
+
+    #[test]
+    fn empty_lines() {
+        let indent = Indent::new("  a", "  ".into());
+        assert_eq!(vec!["a", "", "  b"], indent.apply("a\n\nb"));
+        assert_eq!(vec!["a", "  b"], indent.apply("a\r\nb"));
+    }
+
+    #[test]
+    fn end_positions() {
+        let lines = |text: &str| Indent::new("  ", "  ".into()).apply(text);
+        assert_eq!((3, 10), end_position(&lines("foo"), 3, 7));
+        assert_eq!((4, 3), end_position(&lines("foo\n}"), 3, 7));
+        assert_eq!((5, 0), end_position(&lines("foo\n\n"), 3, 7));
+    }
+}
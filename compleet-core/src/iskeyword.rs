@@ -0,0 +1,131 @@
+/// A parsed `'iskeyword'` option, used to decide which characters can be part
+/// of a keyword.
+///
+/// Like in Vim, the option only affects characters below 256. All the other
+/// characters are considered part of a keyword if they're alphanumeric.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct IsKeyword {
+    table: [bool; 256],
+}
+
+impl Default for IsKeyword {
+    #[inline]
+    fn default() -> Self {
+        Self::parse("@,48-57,_,192-255")
+    }
+}
+
+impl IsKeyword {
+    /// Whether `char` can be part of a keyword.
+    #[inline]
+    pub(crate) fn contains(&self, char: char) -> bool {
+        match u8::try_from(char) {
+            Ok(byte) => self.table[byte as usize],
+            Err(_) => char.is_alphanumeric(),
+        }
+    }
+
+    /// Parses the value of the `'iskeyword'` option (see `:h isfname` for
+    /// the format). Invalid parts are silently ignored, just like Vim does.
+    pub(crate) fn parse(option: &str) -> Self {
+        let mut table = [false; 256];
+
+        for part in option.split(',').filter(|part| !part.is_empty()) {
+            let (part, include) = match part.strip_prefix('^') {
+                Some(rest) if !rest.is_empty() => (rest, false),
+                _ => (part, true),
+            };
+
+            // A lone `@` stands for all the alphabetic characters, while `@`
+            // itself has to be written as `@-@`.
+            if part == "@" {
+                (0..=255u8)
+                    .filter(|&byte| (byte as char).is_alphabetic())
+                    .for_each(|byte| table[byte as usize] = include);
+                continue;
+            }
+
+            let (start, end) = match part.split_once('-') {
+                Some((start, end)) if !start.is_empty() && !end.is_empty() => {
+                    match (parse_char(start), parse_char(end)) {
+                        (Some(start), Some(end)) => (start, end),
+                        _ => continue,
+                    }
+                },
+
+                _ => match parse_char(part) {
+                    Some(char) => (char, char),
+                    None => continue,
+                },
+            };
+
+            (start..=end).for_each(|byte| table[byte as usize] = include);
+        }
+
+        Self { table }
+    }
+}
+
+/// Parses either a decimal number or a single character below 256.
+fn parse_char(str: &str) -> Option<u8> {
+    if str.bytes().all(|byte| byte.is_ascii_digit()) {
+        return str.parse().ok();
+    }
+
+    let mut chars = str.chars();
+
+    match (chars.next(), chars.next()) {
+        (Some(char), None) => u8::try_from(char).ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_option() {
+        let iskeyword = IsKeyword::default();
+        assert!(iskeyword.contains('a'));
+        assert!(iskeyword.contains('Z'));
+        assert!(iskeyword.contains('0'));
+        assert!(iskeyword.contains('_'));
+        assert!(iskeyword.contains('é'));
+        assert!(!iskeyword.contains('-'));
+        assert!(!iskeyword.contains(' '));
+        assert!(!iskeyword.contains('.'));
+    }
+
+    #[test]
+    fn multibyte_chars() {
+        let iskeyword = IsKeyword::parse("");
+        assert!(iskeyword.contains('λ'));
+        assert!(iskeyword.contains('漢'));
+        assert!(!iskeyword.contains('→'));
+    }
+
+    #[test]
+    fn char_ranges() {
+        let iskeyword = IsKeyword::parse("a-c,-,@-@");
+        assert!(iskeyword.contains('b'));
+        assert!(iskeyword.contains('-'));
+        assert!(iskeyword.contains('@'));
+        assert!(!iskeyword.contains('d'));
+    }
+
+    #[test]
+    fn exclusions() {
+        let iskeyword = IsKeyword::parse("@,^a-c,^");
+        assert!(iskeyword.contains('d'));
+        assert!(iskeyword.contains('^'));
+        assert!(!iskeyword.contains('a'));
+    }
+
+    #[test]
+    fn invalid_parts_are_ignored() {
+        let iskeyword = IsKeyword::parse("foo,300,_");
+        assert!(iskeyword.contains('_'));
+        assert!(!iskeyword.contains('f'));
+    }
+}
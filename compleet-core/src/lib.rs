@@ -1,16 +1,56 @@
 mod autocmds;
+mod cancellation;
+mod channel;
 mod client;
 mod commands;
+mod completion_context;
+mod completion_item;
 mod completion_source;
 mod config;
 mod error;
+mod frecency;
+mod fuzzy;
+mod health;
 mod hlgroups;
+mod indent;
+mod iskeyword;
+mod lua_source;
 mod mappings;
-mod messages;
+mod matched_completion;
+#[doc(hidden)]
+pub mod messages;
+mod ranking;
+mod session;
 mod setup;
+mod snippet;
+mod stats;
+mod text_edit;
+mod ui;
 
+// Re-exported so that sources don't have to depend on `async-trait`
+// directly.
+pub use async_trait::async_trait;
+pub use cancellation::CancellationToken;
 pub use client::Client;
 use client::State;
-pub use completion_source::CompletionSource;
+pub use completion_context::{CompletionContext, TriggerKind};
+pub use completion_item::{
+    CompletionItem,
+    CompletionItemBuilder,
+    CompletionItemKind,
+    Details,
+    HighlightRange,
+    PostInsertCallback,
+    ResolvedItem,
+};
+pub use completion_source::{
+    CompletionList,
+    CompletionSource,
+    ShouldAttach,
+    ShouldRecompute,
+    SourceError,
+    SourceResult,
+};
 use config::Config;
 pub use error::{Error, Result};
+pub use text_edit::{Position, PositionEncoding, TextEdit, TextRange};
@@ -0,0 +1,329 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use nvim_oxi::{
+    self as nvim,
+    api::Buffer,
+    libuv::AsyncHandle,
+    object,
+    Dictionary,
+    Function,
+    Object,
+};
+use serde::Deserialize;
+use tokio::sync::{
+    mpsc::{self, UnboundedSender},
+    oneshot,
+};
+
+use crate::{
+    async_trait,
+    CancellationToken,
+    Client,
+    CompletionContext,
+    CompletionItem,
+    CompletionList,
+    CompletionSource,
+    Error,
+    ShouldAttach,
+    ShouldRecompute,
+    SourceResult,
+    TriggerKind,
+};
+
+/// The callback passed to the `complete` function of a Lua source, which
+/// the source calls with a list of completion items and an optional boolean
+/// indicating whether the list is incomplete.
+type LuaCallback = Function<(Object, Option<bool>), ()>;
+
+type Sender = oneshot::Sender<SourceResult<CompletionList>>;
+
+/// The table passed to `register_source` from Lua.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LuaSourceSpec {
+    name: String,
+
+    /// `function(ctx, callback)`, where `callback` has to be called exactly
+    /// once with a list of completion items.
+    complete: Function<(Object, LuaCallback), ()>,
+
+    /// `function(bufnr) -> boolean`. If not set the source attaches to every
+    /// buffer.
+    should_attach: Option<Function<Buffer, bool>>,
+
+    /// The strings that start a new completion when typed, even if there's
+    /// no keyword before the cursor.
+    #[serde(default)]
+    trigger_characters: Vec<String>,
+
+    /// `function(ctx) -> boolean`. If not set the source only recomputes its
+    /// completions when starting a new word or if the last ones were
+    /// incomplete.
+    on_edit: Option<Function<Object, bool>>,
+}
+
+/// A completion item returned by a Lua source.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LuaCompletionItem {
+    text: String,
+    cursor: Option<usize>,
+    is_snippet: Option<bool>,
+    label: Option<String>,
+    label_detail: Option<String>,
+    is_deprecated: Option<bool>,
+    icon: Option<char>,
+    infos: Option<String>,
+    details: Option<String>,
+    details_ft: Option<String>,
+}
+
+impl From<LuaCompletionItem> for CompletionItem {
+    fn from(item: LuaCompletionItem) -> Self {
+        let mut builder = CompletionItem::builder(item.text);
+
+        if let Some(cursor) = item.cursor {
+            builder.cursor(cursor);
+        }
+
+        if let Some(is_snippet) = item.is_snippet {
+            builder.is_snippet(is_snippet);
+        }
+
+        if let Some(label) = item.label {
+            builder.label(label);
+        }
+
+        if let Some(detail) = item.label_detail {
+            builder.label_detail(detail);
+        }
+
+        if let Some(is_deprecated) = item.is_deprecated {
+            builder.is_deprecated(is_deprecated);
+        }
+
+        if let Some(icon) = item.icon {
+            builder.icon(icon);
+        }
+
+        if let Some(infos) = item.infos {
+            builder.infos(infos);
+        }
+
+        if let Some(details) = item.details {
+            builder.details_text(details);
+        }
+
+        if let Some(ft) = item.details_ft {
+            builder.details_ft(ft);
+        }
+
+        builder.build()
+    }
+}
+
+/// A request sent from the threadpool to the main thread to call the
+/// `complete` function of a Lua source.
+struct Request {
+    ctx: CompletionContext,
+    sender: Sender,
+}
+
+/// A completion source defined in Lua.
+///
+/// Since Lua functions can only be called on Neovim's main thread, the
+/// [`complete`](CompletionSource::complete) method sends the context to the
+/// main thread and waits for the Lua source to call its callback.
+pub(crate) struct LuaSource {
+    name: &'static str,
+    should_attach: Option<Function<Buffer, bool>>,
+    trigger_characters: Vec<String>,
+    on_edit: Option<Function<Object, bool>>,
+    requests: UnboundedSender<Request>,
+    signal: AsyncHandle,
+}
+
+impl LuaSource {
+    fn new(spec: LuaSourceSpec) -> nvim::Result<Self> {
+        let LuaSourceSpec {
+            name,
+            complete,
+            should_attach,
+            trigger_characters,
+            on_edit,
+        } = spec;
+
+        let (requests, mut receiver) = mpsc::unbounded_channel::<Request>();
+
+        let signal = AsyncHandle::new(move || {
+            while let Ok(Request { ctx, sender }) = receiver.try_recv() {
+                let complete = complete.clone();
+                nvim::schedule(move |_| call_complete(&complete, ctx, sender));
+            }
+            Ok::<_, nvim::Error>(())
+        })?;
+
+        Ok(Self {
+            // Sources are never unregistered, so leaking the name once per
+            // registered source is fine.
+            name: Box::leak(name.into_boxed_str()),
+            should_attach,
+            trigger_characters,
+            on_edit,
+            requests,
+            signal,
+        })
+    }
+}
+
+#[async_trait]
+impl CompletionSource for LuaSource {
+    /// Lua sources are configured directly in Lua when they're registered.
+    type Config = ();
+
+    #[inline]
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn should_attach(&self, buf: &Buffer) -> SourceResult<ShouldAttach> {
+        match &self.should_attach {
+            Some(should_attach) => Ok(should_attach.call(buf.clone())?),
+            None => Ok(true),
+        }
+    }
+
+    #[inline]
+    fn trigger_characters(&self, _: &Buffer) -> SourceResult<Vec<String>> {
+        Ok(self.trigger_characters.clone())
+    }
+
+    fn on_edit(
+        &self,
+        ctx: &CompletionContext,
+    ) -> SourceResult<ShouldRecompute> {
+        match &self.on_edit {
+            Some(on_edit) => Ok(on_edit.call(ctx_to_object(ctx))?),
+            None => Ok(false),
+        }
+    }
+
+    async fn complete(
+        &self,
+        ctx: &CompletionContext,
+        cancel: &CancellationToken,
+    ) -> SourceResult<CompletionList> {
+        let (sender, receiver) = oneshot::channel();
+
+        // The receiving end lives as long as `self.signal`, so this can't
+        // fail.
+        let _ = self.requests.send(Request { ctx: ctx.clone(), sender });
+        let _ = self.signal.send();
+
+        tokio::select! {
+            completions = receiver => match completions {
+                Ok(completions) => completions,
+
+                // The callback was dropped without ever being called.
+                Err(_) => Ok(CompletionList::default()),
+            },
+
+            // Lua sources can't be notified of the cancellation, so we just
+            // stop waiting for their callback to be called.
+            _ = cancel.cancelled() => Ok(CompletionList::default()),
+        }
+    }
+}
+
+/// Calls the `complete` function of a Lua source on the main thread,
+/// sending the items it returns through `sender`.
+fn call_complete(
+    complete: &Function<(Object, LuaCallback), ()>,
+    ctx: CompletionContext,
+    sender: Sender,
+) -> nvim::Result<()> {
+    let sender = Rc::new(Cell::new(Some(sender)));
+
+    let callback = {
+        let sender = Rc::clone(&sender);
+
+        Function::from_fn(
+            move |(items, incomplete): (Object, Option<bool>)| {
+                if let Some(sender) = sender.take() {
+                    let items = Vec::<LuaCompletionItem>::deserialize(
+                        object::Deserializer::new(items),
+                    )
+                    .map(|items| CompletionList {
+                        items: items.into_iter().map(Into::into).collect(),
+                        is_incomplete: incomplete.unwrap_or(false),
+                    })
+                    .map_err(Into::into);
+
+                    // The receiver is dropped if the completion request
+                    // has been cancelled in the meantime.
+                    let _ = sender.send(items);
+                }
+                Ok::<_, nvim::Error>(())
+            },
+        )
+    };
+
+    if let Err(err) = complete.call((ctx_to_object(&ctx), callback)) {
+        if let Some(sender) = sender.take() {
+            let _ = sender.send(Err(err.into()));
+        }
+    }
+
+    Ok(())
+}
+
+/// Converts a [`CompletionContext`] into the Lua table passed to the
+/// functions of a Lua source.
+fn ctx_to_object(ctx: &CompletionContext) -> Object {
+    let (trigger_kind, trigger_character) = match ctx.trigger_kind() {
+        TriggerKind::Automatic => ("automatic", Object::nil()),
+        TriggerKind::Manual => ("manual", Object::nil()),
+        TriggerKind::TriggerCharacter(char) => {
+            ("trigger_character", Object::from(char.to_string()))
+        },
+    };
+
+    Dictionary::from_iter([
+        ("bufnr", Object::from(ctx.buffer().clone())),
+        ("filetype", Object::from(ctx.filetype())),
+        ("line", Object::from(ctx.line())),
+        ("row", Object::from(ctx.row() as i64)),
+        ("col", Object::from(ctx.col() as i64)),
+        ("col_utf16", Object::from(ctx.col_utf16() as i64)),
+        ("col_utf32", Object::from(ctx.col_utf32() as i64)),
+        ("prefix", Object::from(ctx.prefix())),
+        ("prefix_start", Object::from(ctx.prefix_start() as i64)),
+        ("after_cursor", Object::from(ctx.after_cursor())),
+        ("trigger_kind", Object::from(trigger_kind)),
+        ("trigger_character", trigger_character),
+    ])
+    .into()
+}
+
+/// Called from Lua to register a new completion source.
+pub(crate) fn register_source(
+    client: &Client,
+    spec: Object,
+) -> crate::Result<()> {
+    let deserializer = object::Deserializer::new(spec);
+
+    let spec =
+        serde_path_to_error::deserialize::<_, LuaSourceSpec>(deserializer)
+            .map_err(|err| match Error::from(err) {
+                Error::BadPreferences { option, why } => {
+                    Error::BadLuaSource { option, why }
+                },
+                other => other,
+            })?;
+
+    // Building the source leaks its name, so duplicates are rejected first.
+    client.check_source_name(&spec.name)?;
+
+    client.register_lua_source(LuaSource::new(spec)?)
+}
@@ -1,9 +1,325 @@
+use nvim_oxi::{
+    self as nvim,
+    api::{self, Buffer, Window},
+    opts::{GetExtmarkByIdOpts, SetExtmarkOpts},
+};
+
 use super::IdentifyCompletion;
-use crate::Client;
+use crate::indent::{self, Indent};
+use crate::{messages, snippet, Client, Error, PositionEncoding, TextRange};
+
+/// A range in a buffer as `(start_row, start_col, end_row, end_col)`, where
+/// columns are byte offsets.
+type ByteRange = (usize, usize, usize, usize);
 
+/// What an accepted completion replaces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum AcceptBehavior {
+    /// Only the text before the cursor is replaced, together with the part
+    /// of the text after the cursor the completion overlaps with.
+    Insert,
+
+    /// The whole keyword the cursor is in is replaced.
+    Replace,
+}
+
+/// Executed on `<Plug>(compleet-accept-{first,selected})` and on their
+/// `-replace` variants.
 pub(super) fn accept_completion(
     client: &Client,
     which: IdentifyCompletion,
+    behavior: AcceptBehavior,
 ) -> crate::Result<()> {
+    let (completion, ctx) = match client.completion(which) {
+        Some(completion) => completion,
+        None => return Ok(()),
+    };
+
+    client.stop_completing()?;
+    client.record_accepted(&completion, &ctx);
+
+    let item = &*completion.item;
+    let text = item.text();
+
+    // Snippets are expanded before being inserted, and their text is never
+    // trimmed since it's not what ends up in the buffer.
+    let expansion =
+        item.is_snippet.then(|| snippet::expand(text, &ctx)).transpose()?;
+
+    let text = expansion.as_ref().map_or(text, |expansion| &*expansion.text);
+
+    // The ranges of the item refer to the line as it was when the item was
+    // requested.
+    let request = completion.request.as_deref().unwrap_or(&*ctx);
+
+    // Only ranges on the line of the cursor are supported, the others fall
+    // back to replacing the prefix.
+    let range = match behavior {
+        AcceptBehavior::Insert => item.insert_range,
+        AcceptBehavior::Replace => item.replace_range,
+    }
+    .filter(|range| {
+        range.start.row == request.row() && range.end.row == request.row()
+    });
+
+    let (start_col, end_col, replacement) = match (range, behavior) {
+        (Some(range), _) => {
+            let encoding = item.position_encoding;
+            let start = encoding.byte_offset(request.line(), range.start.col);
+            let end = encoding.byte_offset(request.line(), range.end.col);
+
+            // Only the text before the cursor can have been edited since the
+            // item was requested, so the end of the range keeps its distance
+            // from the cursor. It's also extended to cover what's been typed
+            // since then.
+            let end = match end.checked_sub(request.col()) {
+                Some(after_cursor) => ctx.col() + after_cursor,
+                None => end.min(ctx.col()),
+            };
+
+            let end = match behavior {
+                AcceptBehavior::Insert => end.max(ctx.col()),
+                AcceptBehavior::Replace => end.max(ctx.keyword_end()),
+            };
+
+            (start.min(ctx.col()), end.min(ctx.line().len()), text)
+        },
+
+        (None, AcceptBehavior::Insert) if expansion.is_none() => (
+            ctx.prefix_start(),
+            ctx.col(),
+            self::text_to_insert(ctx.prefix(), ctx.after_cursor(), text),
+        ),
+
+        (None, AcceptBehavior::Insert) => {
+            (ctx.prefix_start(), ctx.col(), text)
+        },
+
+        (None, AcceptBehavior::Replace) => {
+            (ctx.prefix_start(), ctx.keyword_end(), text)
+        },
+    };
+
+    let mut buf = ctx.buffer().clone();
+
+    let mut edits = item
+        .additional_edits
+        .iter()
+        .map(|edit| {
+            let range =
+                self::byte_range(&buf, &edit.range, item.position_encoding)?;
+            Ok((range, &*edit.new_text))
+        })
+        .collect::<nvim::Result<Vec<_>>>()?;
+
+    // Applying the edits from the bottom of the buffer up keeps the
+    // positions of the ones above valid.
+    edits.sort_by(|(a, _), (b, _)| b.cmp(a));
+
+    // The continuation lines are indented relative to the current line.
+    let indent = Indent::from_buffer(&buf, ctx.line())?;
+    let lines = indent.apply(replacement);
+
+    let (row, start_col) = client.while_inserting(|| {
+        self::apply_edits(
+            &mut buf,
+            (ctx.row(), start_col, ctx.row(), end_col),
+            lines,
+            edits,
+        )
+    })?;
+
+    match expansion {
+        // The cursor is placed on the first tabstop of the snippet.
+        Some(expansion) => {
+            let text = expansion.text.clone();
+            client.start_snippet(buf, expansion, |offset| {
+                indent::end_position(
+                    &indent.apply(&text[..offset]),
+                    row,
+                    start_col,
+                )
+            })?;
+        },
+
+        // The cursor is placed after the part of the text that overlapped
+        // with the one after the cursor, unless the item says otherwise.
+        None => {
+            let text = item.text();
+            let before_cursor = &text[..item.cursor.unwrap_or(text.len())];
+            let (row, col) = indent::end_position(
+                &indent.apply(before_cursor),
+                row,
+                start_col,
+            );
+            Window::current().set_cursor(row + 1, col)?;
+        },
+    }
+
+    // The item has already been inserted at this point, so a failing
+    // callback is only worth a warning.
+    if let Some(callback) = &item.post_insert_callback {
+        if let Err(err) = callback(item) {
+            let err = Error::SourceError { name: completion.source, err };
+            messages::echowarn!("{err}");
+        }
+    }
+
     Ok(())
 }
+
+/// Replaces the `range` of the completion with `lines` after applying the
+/// additional `edits`, all in a single undo step. Returns the position of
+/// the start of the completion, which moves if the edits add or remove
+/// lines above it.
+fn apply_edits(
+    buf: &mut Buffer,
+    range: ByteRange,
+    lines: Vec<String>,
+    edits: Vec<(ByteRange, &str)>,
+) -> nvim::Result<(usize, usize)> {
+    let namespace = api::create_namespace("compleet_accept");
+
+    let (start_row, start_col, end_row, end_col) = range;
+
+    let opts =
+        SetExtmarkOpts::builder().end_row(end_row).end_col(end_col).build();
+    let mark = buf.set_extmark(namespace, start_row, start_col, &opts)?;
+
+    for ((start_row, start_col, end_row, end_col), text) in edits {
+        // Join the edits with the previous undo block.
+        api::command("silent! undojoin")?;
+        let text = text.split('\n').map(ToOwned::to_owned).collect::<Vec<_>>();
+        buf.set_text(start_row, start_col, end_row, end_col, text)?;
+    }
+
+    let opts = GetExtmarkByIdOpts::builder().details(true).build();
+    let (start_row, start_col, infos) =
+        buf.get_extmark_by_id(namespace, mark, &opts)?;
+    let (end_row, end_col) = infos
+        .and_then(|infos| Some((infos.end_row?, infos.end_col?)))
+        .unwrap_or((start_row, start_col));
+    buf.del_extmark(namespace, mark)?;
+
+    api::command("silent! undojoin")?;
+    buf.set_text(start_row, start_col, end_row, end_col, lines)?;
+
+    Ok((start_row, start_col))
+}
+
+/// Converts `range` from `encoding` to byte offsets, reading the lines it
+/// spans from `buf`. Positions past the end of the buffer are moved to its
+/// end.
+fn byte_range(
+    buf: &Buffer,
+    range: &TextRange,
+    encoding: PositionEncoding,
+) -> nvim::Result<ByteRange> {
+    let line_count = buf.line_count()?;
+
+    let line = |row: usize| -> nvim::Result<String> {
+        Ok(buf
+            .get_lines(row, row + 1, false)?
+            .next()
+            .map(|line| line.to_string_lossy().into_owned())
+            .unwrap_or_default())
+    };
+
+    let to_bytes = |row: usize, col: usize| -> nvim::Result<_> {
+        if row >= line_count {
+            let last = line_count.saturating_sub(1);
+            return Ok((last, line(last)?.len()));
+        }
+        Ok((row, encoding.byte_offset(&line(row)?, col)))
+    };
+
+    let (start_row, start_col) = to_bytes(range.start.row, range.start.col)?;
+    let (end_row, end_col) = to_bytes(range.end.row, range.end.col)?;
+
+    Ok((start_row, start_col, end_row, end_col))
+}
+
+/// Returns the text that should replace the prefix for the completion
+/// `text` to be inserted, taking into account what comes after the cursor.
+/// For example, if we have `f|o` and we're completing `foo` we only need to
+/// replace the `f` with `fo`, since the last `o` is already in the buffer.
+fn text_to_insert<'a>(
+    prefix: &str,
+    line_after_cursor: &str,
+    text: &'a str,
+) -> &'a str {
+    // The part of the text that's already been typed can't overlap with
+    // what comes after the cursor.
+    let typed = if text.starts_with(prefix) { prefix.len() } else { 0 };
+    let rest = &text[typed..];
+
+    // Find the longest suffix of the rest of the text that's also a prefix
+    // of the line after the cursor.
+    let overlap = (1..=rest.len().min(line_after_cursor.len()))
+        .rev()
+        .filter(|&len| line_after_cursor.is_char_boundary(len))
+        .find(|&len| rest.ends_with(&line_after_cursor[..len]))
+        .unwrap_or(0);
+
+    &text[..text.len() - overlap]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::text_to_insert;
+
+    /// Returns the line obtained by completing `text` on `line`, where the
+    /// `|` in `line` indicates the cursor position and everything before it
+    /// is the prefix.
+    fn complete(line: &str, text: &str) -> String {
+        let (prefix, after_cursor) = line.split_once('|').unwrap();
+        format!("{}{after_cursor}", text_to_insert(prefix, after_cursor, text))
+    }
+
+    #[test]
+    fn nothing_after_cursor() {
+        assert_eq!("foobar", complete("foo|", "foobar"));
+    }
+
+    #[test]
+    fn no_overlap() {
+        assert_eq!("foobarbaz", complete("foo|baz", "foobar"));
+    }
+
+    #[test]
+    fn partial_overlap() {
+        assert_eq!("foobar", complete("foo|ar", "foobar"));
+        assert_eq!("foobar", complete("foo|r", "foobar"));
+        assert_eq!("foobar", complete("f|ar", "foobar"));
+        assert_eq!("foobar", complete("f|r", "foobar"));
+    }
+
+    #[test]
+    fn partial_overlap_with_trailing_text() {
+        assert_eq!("foobarbaz", complete("foo|arbaz", "foobar"));
+        assert_eq!("foobarbaz", complete("f|rbaz", "foobar"));
+    }
+
+    #[test]
+    fn full_overlap() {
+        assert_eq!("foobar", complete("foo|bar", "foobar"));
+    }
+
+    #[test]
+    fn multibyte() {
+        assert_eq!("fööbár", complete("föö|ár", "fööbár"));
+        assert_eq!("fööbár", complete("fö|ár", "fööbár"));
+        assert_eq!("fööbáráx", complete("föö|áx", "fööbár"));
+    }
+
+    #[test]
+    fn fuzzy_prefix() {
+        assert_eq!("foo_bar", complete("fb|", "foo_bar"));
+        assert_eq!("foo_bar", complete("fb|ar", "foo_bar"));
+    }
+
+    #[test]
+    fn typed_text_is_not_an_overlap() {
+        assert_eq!("foo", complete("fo|o", "fo"));
+    }
+}
@@ -0,0 +1,9 @@
+use crate::Client;
+
+/// Executed on `<Plug>(compleet-snippet-{next,prev})`.
+pub(super) fn jump_snippet(
+    client: &Client,
+    forward: bool,
+) -> crate::Result<()> {
+    client.jump_snippet(forward)
+}
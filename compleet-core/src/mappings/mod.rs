@@ -1,11 +1,15 @@
 mod accept_completion;
+mod jump_snippet;
 mod scroll_details;
 mod select_completion;
 mod setup;
 mod show_completions;
+mod show_snippet_choices;
 
-use accept_completion::accept_completion;
+use accept_completion::{accept_completion, AcceptBehavior};
+use jump_snippet::jump_snippet;
 use scroll_details::scroll_details;
 use select_completion::select_completion;
 pub(crate) use setup::{setup, IdentifyCompletion};
 use show_completions::show_completions;
+use show_snippet_choices::show_snippet_choices;
@@ -3,9 +3,10 @@ use nvim_oxi as nvim;
 use super::IdentifyCompletion;
 use crate::Client;
 
+/// Executed on `<Plug>(compleet-select-{next,prev})`.
 pub(super) fn select_completion(
     client: &Client,
     which: IdentifyCompletion,
 ) -> nvim::Result<()> {
-    Ok(())
+    client.select_completion(which)
 }
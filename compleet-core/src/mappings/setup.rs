@@ -1,5 +1,6 @@
 use nvim_oxi::{self as nvim, api, opts::SetKeymapOpts, types::Mode};
 
+use super::AcceptBehavior;
 use crate::Client;
 
 pub(crate) enum IdentifyCompletion {
@@ -10,15 +11,32 @@ pub(crate) enum IdentifyCompletion {
     FromSelected(isize),
 }
 
-pub(crate) fn setup(client: &Client) -> nvim::Result<()> {
-    let accept_first = client.create_fn(|client, _| {
-        super::accept_completion(client, IdentifyCompletion::ByIndex(0))
-    });
-
-    let accept_selected = client.create_fn(|client, _| {
-        super::accept_completion(client, IdentifyCompletion::FromSelected(0))
-    });
+impl IdentifyCompletion {
+    /// Returns the index of the identified completion in a list of `len`
+    /// completions where the one at `selected` is currently selected, or
+    /// `None` if it doesn't identify any completion.
+    ///
+    /// Offsets from the selected completion wrap around, going through a
+    /// state where no completion is selected between the last and the first
+    /// completion.
+    pub(crate) fn index(
+        &self,
+        selected: Option<usize>,
+        len: usize,
+    ) -> Option<usize> {
+        match *self {
+            Self::ByIndex(index) => (index < len).then_some(index),
+
+            Self::FromSelected(offset) => {
+                let current = selected.unwrap_or(len) as isize;
+                let new = (current + offset).rem_euclid(len as isize + 1);
+                (new < len as isize).then_some(new as usize)
+            },
+        }
+    }
+}
 
+pub(crate) fn setup(client: &Client) -> nvim::Result<()> {
     let _scroll_details =
         client.create_fn(|client, lines| super::scroll_details(client, lines));
 
@@ -32,22 +50,41 @@ pub(crate) fn setup(client: &Client) -> nvim::Result<()> {
 
     let show = client.create_fn(|client, _| super::show_completions(client));
 
+    let show_snippet_choices =
+        client.create_fn(|client, _| super::show_snippet_choices(client));
+
     let mut opts = SetKeymapOpts::builder();
     opts.silent(true);
 
-    api::set_keymap(
-        Mode::Insert,
-        "<Plug>(compleet-accept-first)",
-        "",
-        Some(&opts.callback(accept_first).build()),
-    )?;
-
-    api::set_keymap(
-        Mode::Insert,
-        "<Plug>(compleet-accept-selected)",
-        "",
-        Some(&opts.callback(accept_selected).build()),
-    )?;
+    // The `-replace` variants also replace the rest of the keyword after
+    // the cursor.
+    for (suffix, behavior) in
+        [("", AcceptBehavior::Insert), ("-replace", AcceptBehavior::Replace)]
+    {
+        let accept_first = client.create_fn(move |client, _| {
+            let which = IdentifyCompletion::ByIndex(0);
+            super::accept_completion(client, which, behavior)
+        });
+
+        let accept_selected = client.create_fn(move |client, _| {
+            let which = IdentifyCompletion::FromSelected(0);
+            super::accept_completion(client, which, behavior)
+        });
+
+        api::set_keymap(
+            Mode::Insert,
+            &format!("<Plug>(compleet-accept-first{suffix})"),
+            "",
+            Some(&opts.callback(accept_first).build()),
+        )?;
+
+        api::set_keymap(
+            Mode::Insert,
+            &format!("<Plug>(compleet-accept-selected{suffix})"),
+            "",
+            Some(&opts.callback(accept_selected).build()),
+        )?;
+    }
 
     api::set_keymap(
         Mode::Insert,
@@ -70,5 +107,65 @@ pub(crate) fn setup(client: &Client) -> nvim::Result<()> {
         Some(&opts.callback(show).build()),
     )?;
 
+    api::set_keymap(
+        Mode::Insert,
+        "<Plug>(compleet-snippet-choices)",
+        "",
+        Some(&opts.callback(show_snippet_choices).build()),
+    )?;
+
+    // Placeholders are selected in select mode when jumping to them.
+    for mode in [Mode::Insert, Mode::Select] {
+        let snippet_next =
+            client.create_fn(|client, _| super::jump_snippet(client, true));
+
+        let snippet_prev =
+            client.create_fn(|client, _| super::jump_snippet(client, false));
+
+        api::set_keymap(
+            mode,
+            "<Plug>(compleet-snippet-next)",
+            "",
+            Some(&opts.callback(snippet_next).build()),
+        )?;
+
+        api::set_keymap(
+            mode,
+            "<Plug>(compleet-snippet-prev)",
+            "",
+            Some(&opts.callback(snippet_prev).build()),
+        )?;
+    }
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::IdentifyCompletion::*;
+
+    #[test]
+    fn by_index() {
+        assert_eq!(Some(0), ByIndex(0).index(None, 3));
+        assert_eq!(Some(2), ByIndex(2).index(Some(0), 3));
+        assert_eq!(None, ByIndex(0).index(None, 0));
+        assert_eq!(None, ByIndex(3).index(None, 3));
+    }
+
+    #[test]
+    fn from_selected() {
+        assert_eq!(None, FromSelected(0).index(None, 3));
+        assert_eq!(Some(1), FromSelected(0).index(Some(1), 3));
+        assert_eq!(Some(2), FromSelected(1).index(Some(1), 3));
+        assert_eq!(Some(0), FromSelected(-1).index(Some(1), 3));
+    }
+
+    #[test]
+    fn from_selected_wraps_around() {
+        assert_eq!(Some(0), FromSelected(1).index(None, 3));
+        assert_eq!(Some(2), FromSelected(-1).index(None, 3));
+        assert_eq!(None, FromSelected(1).index(Some(2), 3));
+        assert_eq!(None, FromSelected(-1).index(Some(0), 3));
+        assert_eq!(None, FromSelected(1).index(None, 0));
+    }
+}
@@ -0,0 +1,8 @@
+use crate::Client;
+
+/// Executed on `<Plug>(compleet-snippet-choices)`, which is only used
+/// internally to show the options of a choice tabstop after jumping to it
+/// from select mode.
+pub(super) fn show_snippet_choices(client: &Client) -> crate::Result<()> {
+    client.show_snippet_choices()
+}
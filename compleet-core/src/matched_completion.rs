@@ -0,0 +1,50 @@
+use std::sync::Arc;
+
+use crate::fuzzy::{self, FuzzyMatch};
+use crate::{CompletionContext, CompletionItem};
+
+/// A completion item that matched the prefix of the current completion
+/// context.
+#[derive(Debug, Clone)]
+pub(crate) struct MatchedCompletion {
+    /// The item, shared with the cache of the current completion session.
+    pub(crate) item: Arc<CompletionItem>,
+
+    /// The name of the source that returned the item.
+    pub(crate) source: &'static str,
+
+    /// The result of fuzzy matching the prefix against the item's label.
+    pub(crate) matched: FuzzyMatch,
+
+    /// Whether the item's label starts with the prefix, respecting case.
+    pub(crate) is_exact: bool,
+
+    /// How much the item is boosted because it was accepted before. See
+    /// [`Frecency::boost`](crate::frecency::Frecency::boost) for details.
+    pub(crate) frecency: u32,
+
+    /// The context the item was requested in, if known. The ranges of the
+    /// item refer to the line as it was then.
+    pub(crate) request: Option<Arc<CompletionContext>>,
+}
+
+impl MatchedCompletion {
+    /// Fuzzy matches `prefix` against the label of `item`, returning `None`
+    /// if they don't match.
+    pub(crate) fn new(
+        item: Arc<CompletionItem>,
+        source: &'static str,
+        prefix: &str,
+    ) -> Option<Self> {
+        let matched = fuzzy::fuzzy_match(prefix, item.label())?;
+        let is_exact = !prefix.is_empty() && item.label().starts_with(prefix);
+        Some(Self {
+            item,
+            source,
+            matched,
+            is_exact,
+            frecency: 0,
+            request: None,
+        })
+    }
+}
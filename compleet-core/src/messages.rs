@@ -1,5 +1,10 @@
+//! The macros used to echo messages to the user. They're exported so that
+//! the completion sources can use them too.
+
 use nvim_oxi::api;
 
+pub use crate::hlgroups::{ERROR_MSG_TAG, INFO_MSG_TAG, WARNING_MSG_TAG};
+
 /// The tag used as a prefix in all the messages.
 const MSG_TAG: &str = "[nvim-compleet]";
 
@@ -8,7 +13,7 @@ macro_rules! echoerr {
     ($($arg:tt)*) => {{
         $crate::messages::echo(
             ::std::fmt::format(format_args!($($arg)*)),
-            $crate::hlgroups::ERROR_MSG_TAG
+            $crate::messages::ERROR_MSG_TAG
         );
     }}
 }
@@ -18,7 +23,7 @@ macro_rules! echoinfo {
     ($($arg:tt)*) => {{
         $crate::messages::echo(
             ::std::fmt::format(format_args!($($arg)*)),
-            $crate::hlgroups::INFO_MSG_TAG
+            $crate::messages::INFO_MSG_TAG
         );
     }}
 }
@@ -28,7 +33,7 @@ macro_rules! echowarn {
     ($($arg:tt)*) => {{
         $crate::messages::echo(
             ::std::fmt::format(format_args!($($arg)*)),
-            $crate::hlgroups::WARNING_MSG_TAG
+            $crate::messages::WARNING_MSG_TAG
         );
     }}
 }
@@ -37,7 +42,7 @@ pub(crate) use echoerr;
 pub(crate) use echoinfo;
 pub(crate) use echowarn;
 
-pub(crate) fn echo(msg: String, tag_hlgroup: &'static str) {
+pub fn echo(msg: String, tag_hlgroup: &'static str) {
     let chunks = [(MSG_TAG, Some(tag_hlgroup)), (" ", None), (&msg, None)];
     let _ = api::echo(chunks, true);
 }
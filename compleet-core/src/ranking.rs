@@ -0,0 +1,284 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::config::Comparator;
+use crate::matched_completion::MatchedCompletion;
+use crate::{CompletionItem, CompletionItemKind};
+
+/// Sorts the completions using the given comparators, then removes the ones
+/// whose text is the same as the one of a higher ranked completion coming
+/// from a different source.
+///
+/// The sort is stable, so completions that compare equal keep the order in
+/// which they were received. This way the menu doesn't shuffle completions
+/// around as the results of new sources stream in.
+pub(crate) fn rank<P>(
+    completions: &mut Vec<MatchedCompletion>,
+    comparators: &[Comparator],
+    priority: P,
+) where
+    P: Fn(&str) -> i32,
+{
+    completions.sort_by(|a, b| {
+        comparators
+            .iter()
+            .map(|comparator| comparator.compare(a, b, &priority))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    });
+
+    dedup(completions);
+}
+
+/// Returns the index of `item` in the ranked `completions`, used to keep it
+/// selected when the completions are updated.
+pub(crate) fn position(
+    completions: &[MatchedCompletion],
+    item: &Arc<CompletionItem>,
+) -> Option<usize> {
+    completions
+        .iter()
+        .position(|completion| Arc::ptr_eq(&completion.item, item))
+}
+
+impl Comparator {
+    /// Compares two completions, where `Ordering::Less` means that `a`
+    /// should be displayed before `b`.
+    fn compare<P>(
+        &self,
+        a: &MatchedCompletion,
+        b: &MatchedCompletion,
+        priority: P,
+    ) -> Ordering
+    where
+        P: Fn(&str) -> i32,
+    {
+        match self {
+            Self::Exact => b.is_exact.cmp(&a.is_exact),
+
+            Self::Frecency => b.frecency.cmp(&a.frecency),
+
+            Self::Score => b.matched.score.total_cmp(&a.matched.score),
+
+            Self::Priority => priority(b.source).cmp(&priority(a.source)),
+
+            Self::SortText => a.item.sort_text().cmp(b.item.sort_text()),
+
+            Self::Kind => {
+                kind_rank(a.item.kind()).cmp(&kind_rank(b.item.kind()))
+            },
+
+            Self::Length => a
+                .item
+                .label()
+                .chars()
+                .count()
+                .cmp(&b.item.label().chars().count()),
+        }
+    }
+}
+
+/// Completions are sorted by the numeric value of their kind, except for
+/// plain text completions which come after all the others. Completions
+/// without a kind come last.
+fn kind_rank(kind: Option<CompletionItemKind>) -> u8 {
+    match kind {
+        Some(CompletionItemKind::Text) => u8::MAX - 1,
+        Some(kind) => kind as u8,
+        None => u8::MAX,
+    }
+}
+
+/// Removes the completions whose text is the same as the one of a higher
+/// ranked completion coming from a different source. Completions with the
+/// same text coming from the same source are kept since they usually differ
+/// in some other way (e.g. overloaded functions).
+fn dedup(completions: &mut Vec<MatchedCompletion>) {
+    let mut seen = HashMap::<String, &'static str>::new();
+
+    completions.retain(|completion| match seen.get(completion.item.text()) {
+        Some(&source) => source == completion.source,
+
+        None => {
+            seen.insert(completion.item.text().to_owned(), completion.source);
+            true
+        },
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matched(
+        label: &str,
+        source: &'static str,
+        prefix: &str,
+    ) -> MatchedCompletion {
+        let item = CompletionItem::builder(label).build();
+        MatchedCompletion::new(item.into(), source, prefix).unwrap()
+    }
+
+    fn labels(completions: &[MatchedCompletion]) -> Vec<&str> {
+        completions.iter().map(|c| c.item.label()).collect()
+    }
+
+    #[test]
+    fn exact_before_fuzzy() {
+        let mut completions =
+            vec![matched("f_o_o", "a", "foo"), matched("foobar", "a", "foo")];
+
+        rank(&mut completions, &[Comparator::Exact], |_| 0);
+
+        assert_eq!(vec!["foobar", "f_o_o"], labels(&completions));
+    }
+
+    #[test]
+    fn frecency() {
+        let mut completions =
+            vec![matched("foo", "a", "f"), matched("fxx", "a", "f")];
+
+        completions[1].frecency = 3;
+
+        rank(
+            &mut completions,
+            &[Comparator::Frecency, Comparator::Score],
+            |_| 0,
+        );
+
+        assert_eq!(vec!["fxx", "foo"], labels(&completions));
+    }
+
+    #[test]
+    fn priority() {
+        let mut completions =
+            vec![matched("foo", "low", ""), matched("bar", "high", "")];
+
+        rank(&mut completions, &[Comparator::Priority], |source| {
+            if source == "high" {
+                10
+            } else {
+                0
+            }
+        });
+
+        assert_eq!(vec!["bar", "foo"], labels(&completions));
+    }
+
+    #[test]
+    fn sort_text() {
+        let mut completions = vec![
+            MatchedCompletion::new(
+                CompletionItem::builder("foo").sort_text("b").build().into(),
+                "a",
+                "",
+            )
+            .unwrap(),
+            matched("bar", "a", ""),
+            matched("a", "a", ""),
+        ];
+
+        rank(&mut completions, &[Comparator::SortText], |_| 0);
+
+        assert_eq!(vec!["a", "foo", "bar"], labels(&completions));
+    }
+
+    #[test]
+    fn kind() {
+        let mut completions = vec![
+            MatchedCompletion::new(
+                CompletionItem::builder("text")
+                    .kind(CompletionItemKind::Text)
+                    .build()
+                    .into(),
+                "a",
+                "",
+            )
+            .unwrap(),
+            matched("none", "a", ""),
+            MatchedCompletion::new(
+                CompletionItem::builder("function")
+                    .kind(CompletionItemKind::Function)
+                    .build()
+                    .into(),
+                "a",
+                "",
+            )
+            .unwrap(),
+        ];
+
+        rank(&mut completions, &[Comparator::Kind], |_| 0);
+
+        assert_eq!(vec!["function", "text", "none"], labels(&completions));
+    }
+
+    #[test]
+    fn ties_are_broken_by_the_next_comparator() {
+        let mut completions = vec![
+            matched("foobar", "a", "foo"),
+            matched("foob", "a", "foo"),
+            matched("fxoxo", "a", "foo"),
+        ];
+
+        rank(
+            &mut completions,
+            &[Comparator::Exact, Comparator::Length],
+            |_| 0,
+        );
+
+        assert_eq!(vec!["foob", "foobar", "fxoxo"], labels(&completions));
+    }
+
+    #[test]
+    fn stable() {
+        let mut completions = vec![
+            matched("foo", "a", ""),
+            matched("bar", "b", ""),
+            matched("baz", "c", ""),
+        ];
+
+        rank(&mut completions, &[Comparator::Score], |_| 0);
+
+        assert_eq!(vec!["foo", "bar", "baz"], labels(&completions));
+    }
+
+    #[test]
+    fn dedup_across_sources() {
+        let mut completions = vec![
+            matched("foo", "a", ""),
+            matched("foo", "a", ""),
+            matched("foo", "b", ""),
+            matched("bar", "b", ""),
+        ];
+
+        rank(&mut completions, &[], |_| 0);
+
+        assert_eq!(vec!["foo", "foo", "bar"], labels(&completions));
+        assert!(completions[..2].iter().all(|c| c.source == "a"));
+    }
+
+    #[test]
+    fn selection_survives_update() {
+        let mut completions =
+            vec![matched("foo", "a", "f"), matched("fxx", "a", "f")];
+        rank(&mut completions, &[Comparator::Score], |_| 0);
+
+        let selected = Arc::clone(&completions[1].item);
+
+        // A slower source returns a better match containing the prefix.
+        let mut completions = vec![
+            completions[0].clone(),
+            completions[1].clone(),
+            matched("f", "b", "f"),
+        ];
+        rank(&mut completions, &[Comparator::Score], |_| 0);
+
+        assert_eq!(vec!["f", "foo", "fxx"], labels(&completions));
+        assert_eq!(Some(2), position(&completions, &selected));
+
+        // An item with the same label isn't the selected one.
+        let completions = vec![matched("fxx", "a", "f")];
+        assert_eq!(None, position(&completions, &selected));
+    }
+}
@@ -0,0 +1,199 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use nvim_oxi::api::Buffer;
+
+use crate::frecency::Frecency;
+use crate::matched_completion::MatchedCompletion;
+use crate::snippet;
+use crate::{CompletionContext, CompletionItem, CompletionList};
+
+/// A completion session starts when the user starts typing a new word and
+/// lasts for as long as they keep editing it. The results returned by the
+/// sources during a session are cached and filtered locally as more
+/// characters are typed.
+pub(crate) struct Session {
+    /// An identifier that's unique among all the sessions.
+    id: u64,
+
+    /// The buffer the word is being typed in.
+    buf: Buffer,
+
+    /// The row of the word.
+    row: usize,
+
+    /// The byte offset of the start of the word in its line.
+    prefix_start: usize,
+
+    /// Whether the session is showing the options of a snippet's choice
+    /// tabstop instead of the results of the sources.
+    is_snippet_choice: bool,
+
+    /// The latest results of every source that has responded during the
+    /// session, in the order in which the sources first responded.
+    results: Vec<(&'static str, SourceResults)>,
+}
+
+struct SourceResults {
+    items: Vec<Arc<CompletionItem>>,
+
+    is_incomplete: bool,
+
+    /// The `changedtick` of the buffer when the results were requested.
+    changedtick: u32,
+
+    /// The context the results were requested in.
+    request: Arc<CompletionContext>,
+}
+
+impl Session {
+    /// Starts a new session for the word being typed in `ctx`.
+    pub(crate) fn new(ctx: &CompletionContext) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            buf: ctx.buffer().clone(),
+            row: ctx.row(),
+            prefix_start: ctx.prefix_start(),
+            is_snippet_choice: false,
+            results: Vec::new(),
+        }
+    }
+
+    /// Starts a new session showing the `choices` of a snippet's tabstop
+    /// starting at the prefix of `ctx`.
+    pub(crate) fn snippet_choices(
+        ctx: &CompletionContext,
+        choices: &[String],
+    ) -> Self {
+        let items = choices
+            .iter()
+            .enumerate()
+            .map(|(idx, choice)| {
+                // Keep the options in the order they're listed in.
+                CompletionItem::builder(choice)
+                    .sort_text(format!("{idx:04}"))
+                    .build()
+            })
+            .collect::<Vec<_>>();
+
+        let mut session = Self { is_snippet_choice: true, ..Self::new(ctx) };
+        let request = Arc::new(ctx.clone());
+        session.insert(snippet::CHOICES_SOURCE, items.into(), 0, request);
+        session
+    }
+
+    /// Whether `ctx` is still editing the word of this session. For the
+    /// options of a choice that's any text typed after the start of the
+    /// tabstop.
+    pub(crate) fn contains(&self, ctx: &CompletionContext) -> bool {
+        &self.buf == ctx.buffer()
+            && self.row == ctx.row()
+            && if self.is_snippet_choice {
+                self.prefix_start <= ctx.col()
+            } else {
+                self.prefix_start == ctx.prefix_start()
+            }
+    }
+
+    /// Whether the source named `source` has sent any results during this
+    /// session.
+    pub(crate) fn has_results(&self, source: &str) -> bool {
+        self.get(source).is_some()
+    }
+
+    #[inline]
+    pub(crate) fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Caches the results of a source, returning `false` if they were
+    /// discarded because newer results of the same source had already been
+    /// received.
+    pub(crate) fn insert(
+        &mut self,
+        source: &'static str,
+        list: CompletionList,
+        changedtick: u32,
+        request: Arc<CompletionContext>,
+    ) -> bool {
+        let results = SourceResults {
+            items: list.items.into_iter().map(Arc::new).collect(),
+            is_incomplete: list.is_incomplete,
+            changedtick,
+            request,
+        };
+
+        match self.results.iter_mut().find(|(name, _)| *name == source) {
+            Some((_, old)) if old.changedtick > changedtick => return false,
+            Some((_, old)) => *old = results,
+            None => self.results.push((source, results)),
+        }
+
+        true
+    }
+
+    #[inline]
+    pub(crate) fn is_snippet_choice(&self) -> bool {
+        self.is_snippet_choice
+    }
+
+    /// Whether the last results of the source named `source` were
+    /// incomplete.
+    pub(crate) fn is_incomplete(&self, source: &str) -> bool {
+        self.get(source).map_or(false, |results| results.is_incomplete)
+    }
+
+    #[inline]
+    pub(crate) fn prefix_start(&self) -> usize {
+        self.prefix_start
+    }
+
+    /// Filters all the cached results against the prefix of `ctx`.
+    pub(crate) fn matches(
+        &self,
+        ctx: &CompletionContext,
+        frecency: &Frecency,
+        now: u64,
+    ) -> Vec<MatchedCompletion> {
+        let prefix = ctx.prefix();
+
+        self.results
+            .iter()
+            .flat_map(|&(source, ref results)| {
+                results.items.iter().filter_map(move |item| {
+                    let mut completion = MatchedCompletion::new(
+                        Arc::clone(item),
+                        source,
+                        prefix,
+                    )?;
+                    completion.frecency =
+                        frecency.boost(ctx.filetype(), item.text(), now);
+                    completion.request = Some(Arc::clone(&results.request));
+                    Some(completion)
+                })
+            })
+            .collect()
+    }
+
+    /// Replaces the cached `old` item with `new`, e.g. once it's been
+    /// resolved. Does nothing if `old` isn't cached in this session.
+    pub(crate) fn replace(
+        &mut self,
+        old: &Arc<CompletionItem>,
+        new: &Arc<CompletionItem>,
+    ) {
+        self.results
+            .iter_mut()
+            .flat_map(|(_, results)| results.items.iter_mut())
+            .filter(|item| Arc::ptr_eq(item, old))
+            .for_each(|item| *item = Arc::clone(new));
+    }
+
+    fn get(&self, source: &str) -> Option<&SourceResults> {
+        self.results
+            .iter()
+            .find_map(|(name, results)| (*name == source).then_some(results))
+    }
+}
@@ -1,4 +1,14 @@
-use nvim_oxi::{object, Object, ObjectKind};
+use std::collections::HashMap;
+
+use nvim_oxi::{
+    api::Buffer,
+    object,
+    Array,
+    Dictionary,
+    FromObject,
+    Object,
+    ObjectKind,
+};
 
 use crate::{autocmds, commands, hlgroups, mappings};
 use crate::{Client, Config, Error, Result};
@@ -12,21 +22,86 @@ pub(crate) fn setup(client: &Client, preferences: Object) -> Result<()> {
     // error messages will be displayed with the right colors.
     hlgroups::setup()?;
 
-    let config = match preferences.kind() {
-        ObjectKind::Nil => Config::default(),
+    let (config, sources) = match preferences.kind() {
+        ObjectKind::Nil => (Config::default(), HashMap::new()),
 
         _ => {
+            let (preferences, sources) = split_sources(preferences)?;
             let deserializer = object::Deserializer::new(preferences);
-            serde_path_to_error::deserialize::<_, Config>(deserializer)?
+            let config =
+                serde_path_to_error::deserialize::<_, Config>(deserializer)?;
+            (config, sources)
         },
     };
 
+    client.set_config(config);
+    client.setup_frecency()?;
+    client.setup_sources(sources)?;
+    client.setup_ui()?;
+
     autocmds::setup(client)?;
     commands::setup(client)?;
     mappings::setup(client)?;
 
-    client.set_config(config);
+    // The `BufNew` autocommand won't fire for the buffer that's already open
+    // when `setup` is called, so we attach to it manually.
+    client.attach_buffer(Buffer::current())?;
+
     client.did_setup();
 
     Ok(())
 }
+
+/// Removes the `sources` table from the preferences, returning the
+/// configuration of every source keyed by the source's name. The
+/// configurations are later deserialized by the sources themselves.
+fn split_sources(
+    preferences: Object,
+) -> Result<(Object, HashMap<String, Object>)> {
+    // If the preferences aren't a table we let the deserializer report the
+    // error.
+    if !matches!(preferences.kind(), ObjectKind::Dictionary) {
+        return Ok((preferences, HashMap::new()));
+    }
+
+    let mut sources = None;
+
+    let preferences = Dictionary::from_object(preferences)?
+        .into_iter()
+        .filter_map(|(key, value)| {
+            if key.to_string_lossy() == "sources" {
+                sources = Some(value);
+                None
+            } else {
+                Some((key, value))
+            }
+        })
+        .collect::<Dictionary>();
+
+    let sources = match sources.map(|sources| (sources.kind(), sources)) {
+        None => HashMap::new(),
+
+        Some((ObjectKind::Dictionary, sources)) => {
+            Dictionary::from_object(sources)?
+                .into_iter()
+                .map(|(name, config)| (name.to_string_lossy().into(), config))
+                .collect()
+        },
+
+        // An empty Lua table is converted to an empty array.
+        Some((ObjectKind::Array, sources))
+            if Array::from_object(sources.clone())?.is_empty() =>
+        {
+            HashMap::new()
+        },
+
+        Some(_) => {
+            return Err(Error::BadPreferences {
+                option: "sources".into(),
+                why: "expected a table".into(),
+            })
+        },
+    };
+
+    Ok((preferences.into(), sources))
+}
@@ -0,0 +1,357 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use super::parser::{Marker, Transform};
+
+/// The text of an expanded snippet together with its tabstops.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Expansion {
+    pub(crate) text: String,
+
+    /// The tabstops in the order they're jumped to. The last one is always
+    /// the final tabstop `$0`, which is placed at the end of the snippet if
+    /// the snippet doesn't have one.
+    pub(crate) tabstops: Vec<Tabstop>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Tabstop {
+    pub(crate) index: u32,
+
+    /// Every place the tabstop appears in, in the order they appear in the
+    /// snippet. The first one is where the cursor jumps to, and all the
+    /// others mirror its text.
+    pub(crate) occurrences: Vec<Occurrence>,
+
+    /// The options of the tabstop if it's a choice, empty otherwise.
+    pub(crate) choices: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Occurrence {
+    /// The byte range of the occurrence in the expanded text.
+    pub(crate) range: Range<usize>,
+
+    /// The transform applied to the text of the tabstop to get the text of
+    /// this occurrence.
+    pub(crate) transform: Option<Transform>,
+}
+
+/// Expands a parsed snippet, using `resolve` to get the values of its
+/// variables. `resolve` should return `None` if the variable is unknown,
+/// and an empty string if it's known but has no value.
+///
+/// Like in VSCode, unknown variables without a default are turned into
+/// placeholders containing their name.
+pub(crate) fn expand<F>(markers: &[Marker], resolve: F) -> Expansion
+where
+    F: FnMut(&str) -> Option<String>,
+{
+    let mut placeholders = HashMap::new();
+    let mut max_index = 0;
+    self::collect_placeholders(markers, &mut placeholders, &mut max_index);
+
+    let mut expander = Expander {
+        placeholders,
+        resolve,
+        text: String::new(),
+        tabstops: Vec::new(),
+        next_unknown: max_index + 1,
+        rendering: Vec::new(),
+    };
+
+    expander.render(markers, true);
+
+    let Expander { text, mut tabstops, .. } = expander;
+
+    if !tabstops.iter().any(|tabstop| tabstop.index == 0) {
+        tabstops.push(Tabstop {
+            index: 0,
+            occurrences: vec![Occurrence {
+                range: text.len()..text.len(),
+                transform: None,
+            }],
+            choices: Vec::new(),
+        });
+    }
+
+    // Tabstops are jumped to in increasing order, leaving `$0` for last.
+    tabstops.sort_by_key(|tabstop| match tabstop.index {
+        0 => u32::MAX,
+        index => index - 1,
+    });
+
+    Expansion { text, tabstops }
+}
+
+/// The text mirrored by the occurrences of a tabstop that don't have their
+/// own placeholder.
+enum Placeholder<'a> {
+    Markers(&'a [Marker]),
+    Choice(&'a str),
+}
+
+/// Records the first placeholder of every tabstop.
+fn collect_placeholders<'a>(
+    markers: &'a [Marker],
+    placeholders: &mut HashMap<u32, Placeholder<'a>>,
+    max_index: &mut u32,
+) {
+    for marker in markers {
+        match marker {
+            Marker::Text(_) => {},
+
+            Marker::Tabstop { index, placeholder, .. } => {
+                *max_index = (*max_index).max(*index);
+                if !placeholder.is_empty() {
+                    placeholders
+                        .entry(*index)
+                        .or_insert(Placeholder::Markers(placeholder));
+                    self::collect_placeholders(
+                        placeholder,
+                        placeholders,
+                        max_index,
+                    );
+                }
+            },
+
+            Marker::Choice { index, choices } => {
+                *max_index = (*max_index).max(*index);
+                if let Some(first) = choices.first() {
+                    placeholders
+                        .entry(*index)
+                        .or_insert(Placeholder::Choice(first));
+                }
+            },
+
+            Marker::Variable { default: Some(default), .. } => {
+                self::collect_placeholders(default, placeholders, max_index)
+            },
+
+            Marker::Variable { .. } => {},
+        }
+    }
+}
+
+struct Expander<'a, F> {
+    placeholders: HashMap<u32, Placeholder<'a>>,
+    resolve: F,
+    text: String,
+    tabstops: Vec<Tabstop>,
+
+    /// The index given to the next placeholder created for an unknown
+    /// variable.
+    next_unknown: u32,
+
+    /// The tabstops whose placeholder is currently being rendered, used to
+    /// avoid infinite recursion on snippets like `${1:${1}}`.
+    rendering: Vec<u32>,
+}
+
+impl<'a, F> Expander<'a, F>
+where
+    F: FnMut(&str) -> Option<String>,
+{
+    /// Renders `markers`, only recording their tabstops if `record` is set.
+    fn render(&mut self, markers: &'a [Marker], record: bool) {
+        for marker in markers {
+            let start = self.text.len();
+
+            match marker {
+                Marker::Text(text) => self.text.push_str(text),
+
+                Marker::Tabstop { index, placeholder, transform } => {
+                    if !placeholder.is_empty() {
+                        self.rendering.push(*index);
+                        self.render(placeholder, record);
+                        self.rendering.pop();
+                    } else if !self.rendering.contains(index) {
+                        match self.placeholders.get(index) {
+                            Some(&Placeholder::Markers(markers)) => {
+                                self.rendering.push(*index);
+                                self.render(markers, false);
+                                self.rendering.pop();
+                            },
+                            Some(&Placeholder::Choice(choice)) => {
+                                self.text.push_str(choice)
+                            },
+                            None => {},
+                        }
+                    }
+
+                    if let Some(transform) = transform {
+                        let transformed = transform.apply(&self.text[start..]);
+                        self.text.truncate(start);
+                        self.text.push_str(&transformed);
+                    }
+
+                    if record {
+                        self.record(*index, start, transform.clone(), &[]);
+                    }
+                },
+
+                Marker::Choice { index, choices } => {
+                    if let Some(first) = choices.first() {
+                        self.text.push_str(first);
+                    }
+
+                    if record {
+                        self.record(*index, start, None, choices);
+                    }
+                },
+
+                Marker::Variable { name, default, transform } => {
+                    match ((self.resolve)(name), default) {
+                        (Some(value), _) if !value.is_empty() => {
+                            self.text.push_str(&match transform {
+                                Some(transform) => transform.apply(&value),
+                                None => value,
+                            })
+                        },
+
+                        (_, Some(default)) => self.render(default, record),
+
+                        (Some(_), None) => {},
+
+                        (None, None) => {
+                            self.text.push_str(name);
+
+                            if record {
+                                let index = self.next_unknown;
+                                self.next_unknown += 1;
+                                self.record(index, start, None, &[]);
+                            }
+                        },
+                    }
+                },
+            }
+        }
+    }
+
+    /// Records an occurrence of the tabstop `index` starting at `start`
+    /// and ending at the end of the current text.
+    fn record(
+        &mut self,
+        index: u32,
+        start: usize,
+        transform: Option<Transform>,
+        choices: &[String],
+    ) {
+        let occurrence =
+            Occurrence { range: start..self.text.len(), transform };
+
+        match self.tabstops.iter_mut().find(|tabstop| tabstop.index == index) {
+            Some(tabstop) => {
+                tabstop.occurrences.push(occurrence);
+                if tabstop.choices.is_empty() {
+                    tabstop.choices = choices.to_vec();
+                }
+            },
+
+            None => self.tabstops.push(Tabstop {
+                index,
+                occurrences: vec![occurrence],
+                choices: choices.to_vec(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parser::parse;
+    use super::*;
+
+    fn expand(snippet: &str) -> Expansion {
+        super::expand(&parse(snippet), |name| match name {
+            "EMPTY" => Some(String::new()),
+            "TM_FILENAME" => Some(String::from("main.rs")),
+            _ => None,
+        })
+    }
+
+    /// Returns the text of every occurrence of every tabstop, in jump order.
+    fn tabstops(expansion: &Expansion) -> Vec<(u32, Vec<&str>)> {
+        expansion
+            .tabstops
+            .iter()
+            .map(|tabstop| {
+                let texts = tabstop
+                    .occurrences
+                    .iter()
+                    .map(|occurrence| {
+                        &expansion.text[occurrence.range.clone()]
+                    })
+                    .collect();
+                (tabstop.index, texts)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn jump_order() {
+        let expansion = expand("${2:b} $0 ${1:a}");
+        assert_eq!("b  a", expansion.text);
+        assert_eq!(
+            vec![(1, vec!["a"]), (2, vec!["b"]), (0, vec![""])],
+            tabstops(&expansion)
+        );
+    }
+
+    #[test]
+    fn implicit_final_tabstop() {
+        let expansion = expand("foo($1)");
+        assert_eq!(vec![(1, vec![""]), (0, vec![""])], tabstops(&expansion));
+        assert_eq!(5..5, expansion.tabstops[1].occurrences[0].range);
+    }
+
+    #[test]
+    fn mirrors() {
+        let expansion = expand("$1 ${1:foo} ${1/o/0/g}");
+        assert_eq!("foo foo f00", expansion.text);
+        assert_eq!(
+            vec![(1, vec!["foo", "foo", "f00"]), (0, vec![""])],
+            tabstops(&expansion)
+        );
+    }
+
+    #[test]
+    fn nested_tabstops() {
+        let expansion = expand("${1:a ${2:b}}");
+        assert_eq!("a b", expansion.text);
+        assert_eq!(
+            vec![(1, vec!["a b"]), (2, vec!["b"]), (0, vec![""])],
+            tabstops(&expansion)
+        );
+    }
+
+    #[test]
+    fn self_referencing_placeholder() {
+        assert_eq!("", expand("${1:${1}}").text);
+    }
+
+    #[test]
+    fn choices() {
+        let expansion = expand("${1|one,two|} $1");
+        assert_eq!("one one", expansion.text);
+        assert_eq!(vec!["one", "two"], expansion.tabstops[0].choices);
+    }
+
+    #[test]
+    fn variables() {
+        assert_eq!("main.rs", expand("$TM_FILENAME").text);
+        assert_eq!("MAIN.rs", expand("${TM_FILENAME/main/MAIN/}").text);
+        assert_eq!("x", expand("${EMPTY:x}").text);
+        assert_eq!("", expand("$EMPTY").text);
+    }
+
+    #[test]
+    fn unknown_variables_become_placeholders() {
+        let expansion = expand("$2 $FOO ${BAR:bar}");
+        assert_eq!(" FOO bar", expansion.text);
+        assert_eq!(
+            vec![(2, vec![""]), (3, vec!["FOO"]), (0, vec![""])],
+            tabstops(&expansion)
+        );
+    }
+}
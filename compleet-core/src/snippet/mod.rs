@@ -0,0 +1,35 @@
+mod expand;
+mod parser;
+mod snippet;
+mod variables;
+
+pub(crate) use expand::Expansion;
+pub(crate) use snippet::{feedkeys, Snippet, CHOICES_SOURCE};
+
+use crate::CompletionContext;
+
+/// Parses and expands the LSP snippet `text` inserted in the context `ctx`.
+pub(crate) fn expand(
+    text: &str,
+    ctx: &CompletionContext,
+) -> nvim_oxi::Result<Expansion> {
+    let mut error = None;
+
+    let expansion = expand::expand(&parser::parse(text), |name| {
+        variables::resolve(name, ctx).unwrap_or_else(|err| {
+            error.get_or_insert(err);
+            None
+        })
+    });
+
+    match error {
+        Some(err) => Err(err),
+        None => Ok(expansion),
+    }
+}
+
+/// Returns the text of the LSP snippet `text` with all its syntax removed,
+/// without resolving any variable.
+pub(crate) fn plain_text(text: &str) -> String {
+    expand::expand(&parser::parse(text), |_| None).text
+}
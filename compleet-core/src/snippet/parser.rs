@@ -0,0 +1,788 @@
+//! A parser for the snippet syntax defined by the LSP specification.
+//!
+//! Like in VSCode, the parts of a snippet that don't follow the grammar are
+//! kept as literal text instead of making the whole parse fail.
+
+use std::collections::HashSet;
+
+use regex::{Regex, RegexBuilder};
+
+/// A piece of a parsed snippet.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Marker {
+    /// Literal text, with all the escapes already resolved.
+    Text(String),
+
+    /// `$1`, `${1}`, `${1:placeholder}` or `${1/regex/format/options}`.
+    Tabstop {
+        index: u32,
+        placeholder: Vec<Marker>,
+        transform: Option<Transform>,
+    },
+
+    /// `${1|one,two,three|}`.
+    Choice { index: u32, choices: Vec<String> },
+
+    /// `$NAME`, `${NAME}`, `${NAME:default}` or `${NAME/regex/format/}`.
+    Variable {
+        name: String,
+        default: Option<Vec<Marker>>,
+        transform: Option<Transform>,
+    },
+}
+
+/// A regex replacement applied to the text of a tabstop or to the value of
+/// a variable.
+#[derive(Debug, Clone)]
+pub(crate) struct Transform {
+    regex: Regex,
+    format: Vec<FormatItem>,
+
+    /// Whether to replace all the matches instead of only the first one.
+    global: bool,
+}
+
+impl PartialEq for Transform {
+    fn eq(&self, other: &Self) -> bool {
+        self.regex.as_str() == other.regex.as_str()
+            && self.format == other.format
+            && self.global == other.global
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum FormatItem {
+    Text(String),
+    Group { index: usize, format: GroupFormat },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum GroupFormat {
+    /// `$1` or `${1}`.
+    Plain,
+
+    /// `${1:/upcase}`, `${1:/downcase}`, etc.
+    Case(Case),
+
+    /// `${1:+if}`, `${1:?if:else}`, `${1:-else}` or `${1:else}`. The `if`
+    /// text is used if the group matched, the `else` text if it didn't.
+    Conditional { if_matched: Option<String>, otherwise: Option<String> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Case {
+    Upcase,
+    Downcase,
+    Capitalize,
+    Camelcase,
+    Pascalcase,
+}
+
+impl Transform {
+    /// Applies the transform to `text`.
+    pub(crate) fn apply(&self, text: &str) -> String {
+        let mut transformed = String::with_capacity(text.len());
+        let mut last = 0;
+
+        for captures in self.regex.captures_iter(text) {
+            let matched = captures.get(0).expect("group 0 is the whole match");
+            transformed.push_str(&text[last..matched.start()]);
+            last = matched.end();
+
+            for item in &self.format {
+                match item {
+                    FormatItem::Text(text) => transformed.push_str(text),
+
+                    FormatItem::Group { index, format } => {
+                        let group = captures
+                            .get(*index)
+                            .map(|group| group.as_str())
+                            .filter(|group| !group.is_empty());
+
+                        match (format, group) {
+                            (GroupFormat::Plain, Some(group)) => {
+                                transformed.push_str(group)
+                            },
+
+                            (GroupFormat::Case(case), Some(group)) => {
+                                transformed.push_str(&case.apply(group))
+                            },
+
+                            (
+                                GroupFormat::Conditional {
+                                    if_matched: Some(text),
+                                    ..
+                                },
+                                Some(_),
+                            ) => transformed.push_str(text),
+
+                            (
+                                GroupFormat::Conditional {
+                                    if_matched: None,
+                                    ..
+                                },
+                                Some(group),
+                            ) => transformed.push_str(group),
+
+                            (
+                                GroupFormat::Conditional {
+                                    otherwise: Some(text),
+                                    ..
+                                },
+                                None,
+                            ) => transformed.push_str(text),
+
+                            _ => {},
+                        }
+                    },
+                }
+            }
+
+            if !self.global {
+                break;
+            }
+        }
+
+        transformed.push_str(&text[last..]);
+        transformed
+    }
+}
+
+impl Case {
+    fn apply(self, text: &str) -> String {
+        match self {
+            Self::Upcase => text.to_uppercase(),
+
+            Self::Downcase => text.to_lowercase(),
+
+            Self::Capitalize => {
+                let mut chars = text.chars();
+                chars
+                    .next()
+                    .map(|first| first.to_uppercase().chain(chars).collect())
+                    .unwrap_or_default()
+            },
+
+            Self::Camelcase | Self::Pascalcase => {
+                let words = text
+                    .split(|char: char| !char.is_alphanumeric())
+                    .filter(|word| !word.is_empty());
+
+                let mut cased = String::with_capacity(text.len());
+
+                for (idx, word) in words.enumerate() {
+                    let mut chars = word.chars();
+                    let first = chars.next().expect("words aren't empty");
+
+                    if idx == 0 && self == Self::Camelcase {
+                        cased.extend(first.to_lowercase());
+                    } else {
+                        cased.extend(first.to_uppercase());
+                    }
+
+                    cased.extend(chars);
+                }
+
+                cased
+            },
+        }
+    }
+}
+
+/// Parses a snippet.
+pub(crate) fn parse(snippet: &str) -> Vec<Marker> {
+    let mut parser = Parser {
+        src: snippet,
+        pos: 0,
+        failed: HashSet::new(),
+        unclosed: false,
+    };
+    let markers = parser.markers(false);
+    debug_assert_eq!(parser.pos, snippet.len());
+    markers
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+
+    /// The positions of the `$`s that couldn't be parsed. How a `$` is
+    /// parsed only depends on its position, so they're kept as text without
+    /// parsing them again when backtracking.
+    failed: HashSet<usize>,
+
+    /// Whether the last `$` that couldn't be parsed started a placeholder
+    /// that was still open at the end of the input.
+    unclosed: bool,
+}
+
+impl Parser<'_> {
+    /// Parses markers until the end of the input, or until an unescaped `}`
+    /// if `in_placeholder` is set. The closing brace is not consumed.
+    fn markers(&mut self, in_placeholder: bool) -> Vec<Marker> {
+        let mut markers = Vec::new();
+        let mut text = String::new();
+
+        while let Some(char) = self.peek() {
+            match char {
+                '}' if in_placeholder => break,
+
+                '$' => match self.dollar_at_pos() {
+                    Some(marker) => {
+                        if !text.is_empty() {
+                            markers
+                                .push(Marker::Text(std::mem::take(&mut text)));
+                        }
+                        markers.push(marker);
+                    },
+
+                    // If a nested placeholder is still open at the end of
+                    // the input this one is too, so there's no point in
+                    // looking for its closing brace.
+                    None if in_placeholder && self.unclosed => break,
+
+                    None => {
+                        self.bump();
+                        text.push('$');
+                    },
+                },
+
+                '\\' => text.push(self.escaped(&['$', '}', '\\'])),
+
+                _ => {
+                    self.bump();
+                    text.push(char);
+                },
+            }
+        }
+
+        if in_placeholder && self.peek().is_none() {
+            self.unclosed = true;
+        }
+
+        if !text.is_empty() {
+            markers.push(Marker::Text(text));
+        }
+
+        markers
+    }
+
+    /// Parses the marker starting at the `$` at the current position,
+    /// restoring the position if it fails.
+    fn dollar_at_pos(&mut self) -> Option<Marker> {
+        let start = self.pos;
+
+        self.unclosed = false;
+
+        if self.failed.contains(&start) {
+            return None;
+        }
+
+        let marker = self.attempt(Self::dollar);
+
+        if marker.is_none() {
+            self.failed.insert(start);
+        }
+
+        marker
+    }
+
+    /// Parses a tabstop, a placeholder, a choice or a variable starting at
+    /// a `$`.
+    fn dollar(&mut self) -> Option<Marker> {
+        self.eat('$')?;
+
+        if let Some(index) = self.int() {
+            return Some(Marker::Tabstop {
+                index,
+                placeholder: Vec::new(),
+                transform: None,
+            });
+        }
+
+        if let Some(name) = self.var_name() {
+            return Some(Marker::Variable {
+                name,
+                default: None,
+                transform: None,
+            });
+        }
+
+        self.eat('{')?;
+
+        if let Some(index) = self.int() {
+            let marker = match self.next()? {
+                '}' => {
+                    return Some(Marker::Tabstop {
+                        index,
+                        placeholder: Vec::new(),
+                        transform: None,
+                    })
+                },
+
+                ':' => Marker::Tabstop {
+                    index,
+                    placeholder: self.markers(true),
+                    transform: None,
+                },
+
+                '|' => Marker::Choice { index, choices: self.choices()? },
+
+                '/' => Marker::Tabstop {
+                    index,
+                    placeholder: Vec::new(),
+                    transform: Some(self.transform()?),
+                },
+
+                _ => return None,
+            };
+
+            self.eat('}')?;
+            return Some(marker);
+        }
+
+        let name = self.var_name()?;
+
+        let marker = match self.next()? {
+            '}' => {
+                return Some(Marker::Variable {
+                    name,
+                    default: None,
+                    transform: None,
+                })
+            },
+
+            ':' => Marker::Variable {
+                name,
+                default: Some(self.markers(true)),
+                transform: None,
+            },
+
+            '/' => Marker::Variable {
+                name,
+                default: None,
+                transform: Some(self.transform()?),
+            },
+
+            _ => return None,
+        };
+
+        self.eat('}')?;
+        Some(marker)
+    }
+
+    /// Parses the options of a choice after the opening `|`, consuming the
+    /// closing `|`.
+    fn choices(&mut self) -> Option<Vec<String>> {
+        let mut choices = Vec::new();
+        let mut choice = String::new();
+
+        loop {
+            match self.peek()? {
+                ',' => {
+                    self.bump();
+                    choices.push(std::mem::take(&mut choice));
+                },
+
+                '|' => {
+                    self.bump();
+                    choices.push(choice);
+                    return Some(choices);
+                },
+
+                '\\' => choice.push(self.escaped(&['$', '}', '\\', ',', '|'])),
+
+                char => {
+                    self.bump();
+                    choice.push(char);
+                },
+            }
+        }
+    }
+
+    /// Parses a transform after the opening `/`, stopping before the
+    /// closing `}`.
+    fn transform(&mut self) -> Option<Transform> {
+        let mut regex = String::new();
+
+        loop {
+            match self.next()? {
+                '/' => break,
+
+                '\\' => match self.next()? {
+                    '/' => regex.push('/'),
+                    char => {
+                        regex.push('\\');
+                        regex.push(char);
+                    },
+                },
+
+                char => regex.push(char),
+            }
+        }
+
+        let mut format = Vec::new();
+        let mut text = String::new();
+
+        loop {
+            match self.peek()? {
+                '/' => {
+                    self.bump();
+                    break;
+                },
+
+                '$' => match self.attempt(Self::format_group) {
+                    Some(group) => {
+                        if !text.is_empty() {
+                            format.push(FormatItem::Text(std::mem::take(
+                                &mut text,
+                            )));
+                        }
+                        format.push(group);
+                    },
+
+                    None => {
+                        self.bump();
+                        text.push('$');
+                    },
+                },
+
+                '\\' => text.push(self.escaped(&['$', '/', '\\'])),
+
+                char => {
+                    self.bump();
+                    text.push(char);
+                },
+            }
+        }
+
+        if !text.is_empty() {
+            format.push(FormatItem::Text(text));
+        }
+
+        let mut builder = RegexBuilder::new(&regex);
+        let mut global = false;
+
+        while let Some(option) = self.peek().filter(char::is_ascii_alphabetic)
+        {
+            self.bump();
+            match option {
+                'g' => global = true,
+                'i' => drop(builder.case_insensitive(true)),
+                'm' => drop(builder.multi_line(true)),
+                _ => {},
+            }
+        }
+
+        Some(Transform { regex: builder.build().ok()?, format, global })
+    }
+
+    /// Parses a group reference in the format string of a transform.
+    fn format_group(&mut self) -> Option<FormatItem> {
+        self.eat('$')?;
+
+        if let Some(index) = self.int() {
+            let format = GroupFormat::Plain;
+            return Some(FormatItem::Group { index: index as usize, format });
+        }
+
+        self.eat('{')?;
+
+        let index = self.int()? as usize;
+
+        let format = match self.next()? {
+            '}' => {
+                return Some(FormatItem::Group {
+                    index,
+                    format: GroupFormat::Plain,
+                })
+            },
+
+            ':' => match self.peek()? {
+                '/' => {
+                    self.bump();
+                    let case = match self.var_name()?.as_str() {
+                        "upcase" => Case::Upcase,
+                        "downcase" => Case::Downcase,
+                        "capitalize" => Case::Capitalize,
+                        "camelcase" => Case::Camelcase,
+                        "pascalcase" => Case::Pascalcase,
+                        _ => return None,
+                    };
+                    GroupFormat::Case(case)
+                },
+
+                '+' => {
+                    self.bump();
+                    GroupFormat::Conditional {
+                        if_matched: Some(self.format_text(&['}'])?),
+                        otherwise: None,
+                    }
+                },
+
+                '?' => {
+                    self.bump();
+                    let if_matched = self.format_text(&[':'])?;
+                    self.eat(':')?;
+                    GroupFormat::Conditional {
+                        if_matched: Some(if_matched),
+                        otherwise: Some(self.format_text(&['}'])?),
+                    }
+                },
+
+                '-' => {
+                    self.bump();
+                    GroupFormat::Conditional {
+                        if_matched: None,
+                        otherwise: Some(self.format_text(&['}'])?),
+                    }
+                },
+
+                _ => GroupFormat::Conditional {
+                    if_matched: None,
+                    otherwise: Some(self.format_text(&['}'])?),
+                },
+            },
+
+            _ => return None,
+        };
+
+        self.eat('}')?;
+        Some(FormatItem::Group { index, format })
+    }
+
+    /// Parses the text of a conditional group until one of the `stop`
+    /// characters, which is not consumed.
+    fn format_text(&mut self, stop: &[char]) -> Option<String> {
+        let mut text = String::new();
+
+        loop {
+            match self.peek()? {
+                char if stop.contains(&char) => return Some(text),
+                '\\' => text.push(self.escaped(&['$', '}', '\\', ':'])),
+                char => {
+                    self.bump();
+                    text.push(char);
+                },
+            }
+        }
+    }
+
+    /// Runs `parse`, restoring the position if it fails.
+    fn attempt<T>(&mut self, parse: fn(&mut Self) -> Option<T>) -> Option<T> {
+        let start = self.pos;
+        let parsed = parse(self);
+        if parsed.is_none() {
+            self.pos = start;
+        }
+        parsed
+    }
+
+    #[inline]
+    fn bump(&mut self) {
+        if let Some(char) = self.peek() {
+            self.pos += char.len_utf8();
+        }
+    }
+
+    /// Consumes `expected` if it's the next character.
+    fn eat(&mut self, expected: char) -> Option<()> {
+        (self.peek()? == expected).then(|| self.bump())
+    }
+
+    /// Consumes a backslash and the character following it, returning the
+    /// latter if it can be escaped or the backslash otherwise.
+    fn escaped(&mut self, escapable: &[char]) -> char {
+        self.bump();
+        match self.peek() {
+            Some(char) if escapable.contains(&char) => {
+                self.bump();
+                char
+            },
+            _ => '\\',
+        }
+    }
+
+    fn int(&mut self) -> Option<u32> {
+        let digits = self.take_while(|char| char.is_ascii_digit());
+        // Tabstop indices too big to fit in a `u32` are kept as text.
+        digits.parse().ok()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let char = self.peek()?;
+        self.bump();
+        Some(char)
+    }
+
+    #[inline]
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> &str {
+        let start = self.pos;
+        while matches!(self.peek(), Some(char) if predicate(char)) {
+            self.bump();
+        }
+        &self.src[start..self.pos]
+    }
+
+    fn var_name(&mut self) -> Option<String> {
+        if !self.peek()?.is_ascii_alphabetic() && self.peek()? != '_' {
+            return None;
+        }
+        let name = self
+            .take_while(|char| char.is_ascii_alphanumeric() || char == '_');
+        Some(name.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(text: &str) -> Marker {
+        Marker::Text(text.to_owned())
+    }
+
+    fn tabstop(index: u32, placeholder: Vec<Marker>) -> Marker {
+        Marker::Tabstop { index, placeholder, transform: None }
+    }
+
+    fn transformed(src: &str, text: &str) -> String {
+        match &parse(src)[..] {
+            [Marker::Tabstop { transform: Some(transform), .. }] => {
+                transform.apply(text)
+            },
+            other => panic!("not a transform: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn plain_text() {
+        assert_eq!(vec![text("foo bar")], parse("foo bar"));
+        assert_eq!(Vec::<Marker>::new(), parse(""));
+    }
+
+    #[test]
+    fn tabstops() {
+        assert_eq!(
+            vec![
+                text("foo("),
+                tabstop(1, vec![]),
+                text(")"),
+                tabstop(0, vec![])
+            ],
+            parse("foo($1)${0}"),
+        );
+    }
+
+    #[test]
+    fn nested_placeholders() {
+        assert_eq!(
+            vec![tabstop(
+                1,
+                vec![text("a "), tabstop(2, vec![text("b")]), text(" c")]
+            )],
+            parse("${1:a ${2:b} c}"),
+        );
+    }
+
+    #[test]
+    fn choices() {
+        assert_eq!(
+            vec![Marker::Choice {
+                index: 1,
+                choices: vec!["a".into(), "b,c".into(), "d|".into()],
+            }],
+            parse(r"${1|a,b\,c,d\||}"),
+        );
+    }
+
+    #[test]
+    fn variables() {
+        assert_eq!(
+            vec![
+                Marker::Variable {
+                    name: "TM_FILENAME".into(),
+                    default: None,
+                    transform: None,
+                },
+                text(" "),
+                Marker::Variable {
+                    name: "FOO".into(),
+                    default: Some(vec![tabstop(1, vec![text("x")])]),
+                    transform: None,
+                },
+            ],
+            parse("$TM_FILENAME ${FOO:${1:x}}"),
+        );
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(vec![text(r"$1 } \ \n")], parse(r"\$1 \} \\ \n"));
+        assert_eq!(vec![tabstop(1, vec![text("}")])], parse(r"${1:\}}"),);
+    }
+
+    #[test]
+    fn invalid_syntax_is_kept_as_text() {
+        assert_eq!(vec![text("${1:foo")], parse("${1:foo"));
+        assert_eq!(vec![text("$ ${} ${1")], parse("$ ${} ${1"));
+        assert_eq!(vec![text("a}b")], parse("a}b"));
+        assert_eq!(
+            vec![text("${1|a,b"), tabstop(2, vec![])],
+            parse("${1|a,b$2"),
+        );
+    }
+
+    #[test]
+    fn deeply_nested_unclosed_placeholders() {
+        let snippet = "${1:".repeat(500);
+        assert_eq!(vec![text(&snippet)], parse(&snippet));
+
+        let snippet = format!("{}${{2:x}}", "${1:".repeat(500));
+        assert_eq!(
+            vec![text(&"${1:".repeat(500)), tabstop(2, vec![text("x")])],
+            parse(&snippet),
+        );
+
+        // Only the placeholders enclosing the unclosed one are unclosed.
+        assert_eq!(
+            vec![text("${1:"), tabstop(2, vec![text("${3|x")])],
+            parse("${1:${2:${3|x}"),
+        );
+    }
+
+    #[test]
+    fn transforms() {
+        assert_eq!(
+            "FOO_bar",
+            transformed("${1/(foo)/${1:/upcase}/}", "foo_bar")
+        );
+        assert_eq!("f--", transformed("${1/o/-/g}", "foo"));
+        assert_eq!("f-o", transformed("${1/o/-/}", "foo"));
+        assert_eq!("xAx", transformed("${1/a/A/}", "xax"));
+        assert_eq!(
+            "XAXA",
+            transformed("${1/(x)|(a)/${1:+X}${2:?A:}/g}", "xaxa")
+        );
+        assert_eq!(
+            "fooBar",
+            transformed("${1/(.*)/${1:/camelcase}/}", "foo_bar")
+        );
+        assert_eq!(
+            "FooBar",
+            transformed("${1/(.*)/${1:/pascalcase}/}", "foo-bar")
+        );
+        assert_eq!("Foo", transformed("${1/(.*)/${1:/capitalize}/}", "foo"));
+        assert_eq!("def", transformed("${1/(a)?.*/${1:-def}/}", "xyz"));
+        assert_eq!("b", transformed("${1/a/b/i}", "A"));
+    }
+
+    #[test]
+    fn invalid_regex_is_kept_as_text() {
+        assert_eq!(vec![text("${1/(/x/}")], parse("${1/(/x/}"));
+    }
+}
@@ -0,0 +1,349 @@
+use nvim_oxi::{
+    self as nvim,
+    api::{self, Buffer, Window},
+    opts::{GetExtmarkByIdOpts, SetExtmarkOpts},
+    types::Mode,
+};
+
+use super::expand::{Expansion, Occurrence};
+use super::parser::Transform;
+use crate::indent;
+
+/// The name of the source the options of a choice tabstop are shown as in
+/// the completion menu.
+pub(crate) const CHOICES_SOURCE: &str = "snippet";
+
+/// A range in a buffer as `(start_row, start_col, end_row, end_col)`, where
+/// columns are byte offsets.
+type Range = (usize, usize, usize, usize);
+
+/// A snippet that's been inserted in a buffer and whose tabstops can be
+/// jumped to. The tabstops are tracked with extmarks, so they keep their
+/// positions when the text around them is edited.
+pub(crate) struct Snippet {
+    buf: Buffer,
+
+    /// The namespace of the extmarks tracking the tabstops.
+    namespace: u32,
+
+    /// The tabstops in the order they're jumped to, the last one being the
+    /// final tabstop `$0`.
+    tabstops: Vec<Tabstop>,
+
+    /// The index of the current tabstop, or `None` if we haven't jumped to
+    /// the first one yet.
+    current: Option<usize>,
+}
+
+struct Tabstop {
+    /// The ids of the extmarks tracking every occurrence of the tabstop,
+    /// together with the transforms applied to them. The first one is where
+    /// the cursor jumps to, and all the others mirror its text.
+    marks: Vec<(u32, Option<Transform>)>,
+
+    /// The options of the tabstop if it's a choice, empty otherwise.
+    choices: Vec<String>,
+}
+
+impl Snippet {
+    /// Starts tracking the tabstops of `expansion` after its text has been
+    /// inserted in `buf`. The `position` closure maps a byte offset in the
+    /// expanded text to its `(row, col)` position in the buffer.
+    pub(crate) fn new<F>(
+        mut buf: Buffer,
+        expansion: Expansion,
+        position: F,
+    ) -> nvim::Result<Self>
+    where
+        F: Fn(usize) -> (usize, usize),
+    {
+        let namespace = api::create_namespace("compleet_snippet");
+
+        let mut opts = SetExtmarkOpts::builder();
+
+        // Text typed at either edge of a tabstop becomes part of it.
+        opts.right_gravity(false).end_right_gravity(true);
+
+        let mut tabstops = Vec::with_capacity(expansion.tabstops.len());
+
+        for tabstop in expansion.tabstops {
+            let mut marks = Vec::with_capacity(tabstop.occurrences.len());
+
+            for Occurrence { range, transform } in tabstop.occurrences {
+                let (start_row, start_col) = position(range.start);
+                let (end_row, end_col) = position(range.end);
+                opts.end_row(end_row).end_col(end_col);
+                let id = buf.set_extmark(
+                    namespace,
+                    start_row,
+                    start_col,
+                    &opts.build(),
+                )?;
+                marks.push((id, transform));
+            }
+
+            tabstops.push(Tabstop { marks, choices: tabstop.choices });
+        }
+
+        Ok(Self { buf, namespace, tabstops, current: None })
+    }
+
+    /// The buffer the snippet was inserted in.
+    #[inline]
+    pub(crate) fn buffer(&self) -> &Buffer {
+        &self.buf
+    }
+
+    /// The options of the current tabstop if it's a choice, an empty slice
+    /// otherwise.
+    pub(crate) fn choices(&self) -> &[String] {
+        self.current.map_or(&[], |idx| &self.tabstops[idx].choices)
+    }
+
+    /// Stops tracking the tabstops of the snippet.
+    pub(crate) fn end(mut self) -> nvim::Result<()> {
+        let line_count = self.buf.line_count()?;
+        self.buf.clear_namespace(self.namespace, 0, line_count)
+    }
+
+    /// Whether the cursor is on the final tabstop, after which there's
+    /// nothing left to jump to.
+    #[inline]
+    pub(crate) fn is_finished(&self) -> bool {
+        self.current == Some(self.tabstops.len() - 1)
+    }
+
+    /// Jumps to the next tabstop if `forward` is set or to the previous one
+    /// otherwise, first updating the mirrors of the current tabstop.
+    ///
+    /// The placeholder of the new tabstop is selected in select mode, so
+    /// that typing replaces it. If the tabstop is empty or is a choice the
+    /// cursor is placed at its start in insert mode.
+    pub(crate) fn jump(&mut self, forward: bool) -> nvim::Result<()> {
+        if !self.jumpable(forward) {
+            return Ok(());
+        }
+
+        let target = match self.current {
+            Some(current) => {
+                self.leave(current)?;
+                if forward {
+                    current + 1
+                } else {
+                    current - 1
+                }
+            },
+
+            None => 0,
+        };
+
+        self.current = Some(target);
+        self.enter(target)
+    }
+
+    /// Whether there's a tabstop to jump to in the given direction.
+    pub(crate) fn jumpable(&self, forward: bool) -> bool {
+        match (self.current, forward) {
+            (None, forward) => forward,
+            (Some(current), true) => current + 1 < self.tabstops.len(),
+            (Some(current), false) => current > 0,
+        }
+    }
+
+    /// Moves the cursor to the tabstop at `idx`.
+    fn enter(&mut self, idx: usize) -> nvim::Result<()> {
+        let (id, _) = self.tabstops[idx].marks[0];
+        let mut range = self.range(id)?;
+
+        // The options of a choice are shown in the completion menu, where
+        // they're filtered against the text typed in the tabstop.
+        if !self.tabstops[idx].choices.is_empty() {
+            self.replace(id, range, "")?;
+            range = (range.0, range.1, range.0, range.1);
+        }
+
+        let (start_row, start_col, end_row, end_col) = range;
+
+        let is_insert = matches!(api::get_mode()?.mode, Mode::Insert);
+
+        if (start_row, start_col) == (end_row, end_col) {
+            return if is_insert {
+                Window::current().set_cursor(start_row + 1, start_col)
+            } else {
+                self::feedkeys(
+                    &format!(
+                        "<Esc>i<Cmd>call cursor({}, {})<CR>",
+                        start_row + 1,
+                        start_col + 1
+                    ),
+                    "n",
+                )
+            };
+        }
+
+        // The positions passed to `cursor()` are 1-indexed, and the end of
+        // the selection is included in it unless `'selection'` is exclusive.
+        let (last_row, last_col) =
+            if api::get_option::<String>("selection")? == "exclusive" {
+                (end_row + 1, end_col + 1)
+            } else {
+                self.last_char_before(end_row, end_col)?
+            };
+
+        self::feedkeys(
+            &format!(
+                "<Esc><Cmd>call cursor({}, {})<CR>v<Cmd>call cursor({}, \
+                 {})<CR><C-g>",
+                start_row + 1,
+                start_col + 1,
+                last_row,
+                last_col,
+            ),
+            "n",
+        )
+    }
+
+    /// Returns the 1-indexed position of the character right before the
+    /// byte at `(row, col)`.
+    fn last_char_before(
+        &self,
+        row: usize,
+        col: usize,
+    ) -> nvim::Result<(usize, usize)> {
+        // The character before the start of a line is the newline at the end
+        // of the previous one.
+        if col == 0 {
+            let len = self.line(row - 1)?.len();
+            return Ok((row, len + 1));
+        }
+
+        let line = self.line(row)?;
+
+        let len = line
+            .get(..col)
+            .and_then(|before| before.chars().next_back())
+            .map_or(1, char::len_utf8);
+
+        Ok((row + 1, col + 1 - len))
+    }
+
+    /// Updates the mirrors of the tabstop at `idx`, filling it with its
+    /// first option if it's a choice that was left empty.
+    fn leave(&mut self, idx: usize) -> nvim::Result<()> {
+        let (id, _) = self.tabstops[idx].marks[0];
+        let range = self.range(id)?;
+        let mut text = self.text(range)?;
+
+        if text.is_empty() {
+            if let Some(first) = self.tabstops[idx].choices.first().cloned() {
+                self.replace(id, range, &first)?;
+                text = first;
+            }
+        }
+
+        let mirrors = self.tabstops[idx].marks[1..].to_vec();
+
+        for (id, transform) in mirrors {
+            let new_text = match transform {
+                Some(transform) => transform.apply(&text),
+                None => text.clone(),
+            };
+
+            let range = self.range(id)?;
+
+            if self.text(range)? != new_text {
+                self.replace(id, range, &new_text)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the `row`-th line of the buffer.
+    fn line(&self, row: usize) -> nvim::Result<String> {
+        Ok(self
+            .buf
+            .get_lines(row, row + 1, false)?
+            .next()
+            .map(|line| line.to_string_lossy().into_owned())
+            .unwrap_or_default())
+    }
+
+    /// Returns the range of the text tracked by the extmark `id`.
+    fn range(&self, id: u32) -> nvim::Result<Range> {
+        let opts = GetExtmarkByIdOpts::builder().details(true).build();
+
+        let (row, col, infos) =
+            self.buf.get_extmark_by_id(self.namespace, id, &opts)?;
+
+        let (end_row, end_col) = infos
+            .and_then(|infos| Some((infos.end_row?, infos.end_col?)))
+            .unwrap_or((row, col));
+
+        Ok((row, col, end_row, end_col))
+    }
+
+    /// Replaces the text tracked by the extmark `id` with `text`.
+    fn replace(
+        &mut self,
+        id: u32,
+        range: Range,
+        text: &str,
+    ) -> nvim::Result<()> {
+        let (start_row, start_col, end_row, end_col) = range;
+
+        let lines =
+            text.split('\n').map(ToOwned::to_owned).collect::<Vec<_>>();
+
+        let (new_end_row, new_end_col) =
+            indent::end_position(&lines, start_row, start_col);
+
+        self.buf.set_text(start_row, start_col, end_row, end_col, lines)?;
+
+        // Make sure the extmark spans exactly the new text.
+        let opts = SetExtmarkOpts::builder()
+            .id(id)
+            .right_gravity(false)
+            .end_right_gravity(true)
+            .end_row(new_end_row)
+            .end_col(new_end_col)
+            .build();
+
+        self.buf.set_extmark(self.namespace, start_row, start_col, &opts)?;
+
+        Ok(())
+    }
+
+    /// Returns the text in `range`.
+    fn text(&self, range: Range) -> nvim::Result<String> {
+        let (start_row, start_col, end_row, end_col) = range;
+
+        let text = self
+            .buf
+            .get_lines(start_row, end_row + 1, false)?
+            .map(|line| line.to_string_lossy().into_owned())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let last_line_start = text.rfind('\n').map_or(0, |idx| idx + 1);
+
+        Ok(text
+            .get(start_col..last_line_start + end_col)
+            .map(ToOwned::to_owned)
+            .unwrap_or_default())
+    }
+}
+
+/// Feeds `keys` to Neovim as if they were typed by the user, replacing the
+/// key codes like `<Esc>` they contain. See `:h feedkeys()` for the `mode`
+/// flags.
+pub(crate) fn feedkeys(keys: &str, mode: &str) -> nvim::Result<()> {
+    let keys = api::call_function::<_, nvim::String>(
+        "nvim_replace_termcodes",
+        (keys, true, false, true),
+    )?;
+
+    api::call_function::<_, i64>("feedkeys", (keys, mode))?;
+
+    Ok(())
+}
@@ -0,0 +1,137 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::path::{Path, PathBuf};
+
+use nvim_oxi::{self as nvim, api};
+
+use crate::CompletionContext;
+
+/// Returns the value of the snippet variable named `name` for a snippet
+/// inserted in the context `ctx`, or `None` if the variable is unknown. See
+/// the VSCode docs on snippets for the list of variables.
+pub(crate) fn resolve(
+    name: &str,
+    ctx: &CompletionContext,
+) -> nvim::Result<Option<String>> {
+    let value = match name {
+        // Snippets are only inserted from insert mode, so nothing is ever
+        // selected.
+        "TM_SELECTED_TEXT" => String::new(),
+        "TM_CURRENT_LINE" => ctx.line().to_owned(),
+        "TM_CURRENT_WORD" => ctx.prefix().to_owned(),
+        "TM_LINE_INDEX" => ctx.row().to_string(),
+        "TM_LINE_NUMBER" => (ctx.row() + 1).to_string(),
+
+        "TM_FILENAME" => self::file_name(&self::path(ctx)?),
+        "TM_FILENAME_BASE" => self::path(ctx)?
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        "TM_DIRECTORY" => self::path(ctx)?
+            .parent()
+            .map(|dir| dir.display().to_string())
+            .unwrap_or_default(),
+        "TM_FILEPATH" => self::path(ctx)?.display().to_string(),
+        "RELATIVE_FILEPATH" => {
+            let path = self::path(ctx)?;
+            path.strip_prefix(self::cwd()?)
+                .unwrap_or(&path)
+                .display()
+                .to_string()
+        },
+        "WORKSPACE_NAME" => self::file_name(&self::cwd()?),
+        "WORKSPACE_FOLDER" => self::cwd()?.display().to_string(),
+
+        "CLIPBOARD" => api::call_function("getreg", ("+",))?,
+
+        "CURRENT_YEAR" => self::strftime("%Y")?,
+        "CURRENT_YEAR_SHORT" => self::strftime("%y")?,
+        "CURRENT_MONTH" => self::strftime("%m")?,
+        "CURRENT_MONTH_NAME" => self::strftime("%B")?,
+        "CURRENT_MONTH_NAME_SHORT" => self::strftime("%b")?,
+        "CURRENT_DATE" => self::strftime("%d")?,
+        "CURRENT_DAY_NAME" => self::strftime("%A")?,
+        "CURRENT_DAY_NAME_SHORT" => self::strftime("%a")?,
+        "CURRENT_HOUR" => self::strftime("%H")?,
+        "CURRENT_MINUTE" => self::strftime("%M")?,
+        "CURRENT_SECOND" => self::strftime("%S")?,
+        "CURRENT_SECONDS_UNIX" => {
+            api::call_function::<_, i64>("localtime", ())?.to_string()
+        },
+
+        "RANDOM" => format!("{:06}", self::random() % 1_000_000),
+        "RANDOM_HEX" => format!("{:06x}", self::random() & 0xff_ffff),
+        "UUID" => self::uuid(),
+
+        "LINE_COMMENT" | "BLOCK_COMMENT_START" | "BLOCK_COMMENT_END" => {
+            let commentstring =
+                ctx.buffer().get_option::<String>("commentstring")?;
+
+            let (start, end) =
+                commentstring.split_once("%s").unwrap_or((&commentstring, ""));
+
+            let (start, end) = (start.trim(), end.trim());
+
+            match name {
+                "LINE_COMMENT" if end.is_empty() => start.to_owned(),
+                "BLOCK_COMMENT_START" if !end.is_empty() => start.to_owned(),
+                "BLOCK_COMMENT_END" => end.to_owned(),
+                _ => String::new(),
+            }
+        },
+
+        _ => return Ok(None),
+    };
+
+    Ok(Some(value))
+}
+
+fn cwd() -> nvim::Result<PathBuf> {
+    api::call_function::<_, String>("getcwd", ()).map(PathBuf::from)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn path(ctx: &CompletionContext) -> nvim::Result<PathBuf> {
+    ctx.buffer().get_name()
+}
+
+/// Returns a random number. It doesn't need to be cryptographically secure,
+/// so we use the random keys of the standard library's hasher instead of
+/// pulling in a dependency.
+fn random() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+fn strftime(format: &str) -> nvim::Result<String> {
+    api::call_function("strftime", (format,))
+}
+
+/// Returns a random version 4 UUID.
+fn uuid() -> String {
+    let bytes = (u128::from(self::random()) << 64
+        | u128::from(self::random()))
+    .to_be_bytes();
+
+    let mut uuid = String::with_capacity(36);
+
+    for (idx, byte) in bytes.into_iter().enumerate() {
+        let byte = match idx {
+            6 => byte & 0x0f | 0x40,
+            8 => byte & 0x3f | 0x80,
+            _ => byte,
+        };
+
+        if matches!(idx, 4 | 6 | 8 | 10) {
+            uuid.push('-');
+        }
+
+        uuid.push_str(&format!("{byte:02x}"));
+    }
+
+    uuid
+}
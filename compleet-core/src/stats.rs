@@ -0,0 +1,359 @@
+//! Statistics about the requests made to the completion sources, collected
+//! per source and per filetype and shown by `:CompleetStats`.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+/// The maximum number of latencies kept for every source and filetype.
+/// When the limit is exceeded the oldest ones are discarded, so that the
+/// percentiles reflect the recent behaviour of the source.
+const MAX_LATENCIES: usize = 1024;
+
+/// The header of the table rendered by [`Stats::render`].
+const HEADER: [&str; 12] = [
+    "source",
+    "filetype",
+    "debounced",
+    "requests",
+    "cancelled",
+    "timed out",
+    "errors",
+    "p50",
+    "p95",
+    "p99",
+    "returned",
+    "accepted",
+];
+
+/// How a request made to a completion source ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Outcome {
+    /// The source returned its completions after `latency`.
+    Completed { items: usize, latency: Duration },
+
+    /// The source returned an error after `latency`.
+    Failed { latency: Duration },
+
+    /// The request was cancelled before the source responded.
+    Cancelled,
+
+    /// The source didn't respond within its timeout.
+    TimedOut,
+
+    /// The request was cancelled during the debounce interval, so it was
+    /// never sent to the source.
+    Debounced,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Stats {
+    /// Maps the name of a source and the filetype of the buffer the requests
+    /// were made in to the statistics of those requests.
+    sources: HashMap<(&'static str, String), SourceStats>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct SourceStats {
+    /// The number of requests cancelled before being sent to the source,
+    /// which aren't counted in `requests`.
+    pub(crate) debounced: u32,
+
+    pub(crate) requests: u32,
+    pub(crate) cancelled: u32,
+    pub(crate) timed_out: u32,
+    pub(crate) errors: u32,
+
+    /// The number of completion items returned.
+    pub(crate) items_returned: u64,
+
+    /// The number of returned completion items that have been accepted.
+    pub(crate) items_accepted: u32,
+
+    /// The latencies of the last [`MAX_LATENCIES`] requests that completed,
+    /// either successfully or with an error.
+    latencies: VecDeque<Duration>,
+}
+
+impl SourceStats {
+    /// Returns the `p`-th percentile of the recorded latencies using the
+    /// nearest-rank method, or `None` if no latencies have been recorded.
+    pub(crate) fn percentile(&self, p: f64) -> Option<Duration> {
+        if self.latencies.is_empty() {
+            return None;
+        }
+
+        let mut latencies = self.latencies.iter().copied().collect::<Vec<_>>();
+        latencies.sort_unstable();
+
+        let rank = (p / 100.0 * latencies.len() as f64).ceil() as usize;
+        Some(latencies[rank.clamp(1, latencies.len()) - 1])
+    }
+
+    fn push_latency(&mut self, latency: Duration) {
+        if self.latencies.len() == MAX_LATENCIES {
+            self.latencies.pop_front();
+        }
+        self.latencies.push_back(latency);
+    }
+}
+
+impl Stats {
+    /// Returns the statistics of the source named `source` for buffers of
+    /// the given filetype, if any request has been made.
+    #[cfg(test)]
+    fn get(
+        &self,
+        source: &'static str,
+        filetype: &str,
+    ) -> Option<&SourceStats> {
+        self.sources.get(&(source, filetype.to_owned()))
+    }
+
+    /// Whether no request has been recorded yet.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+
+    /// Records that one of the completions returned by the source named
+    /// `source` has been accepted in a buffer of the given filetype.
+    pub(crate) fn record_accepted(
+        &mut self,
+        source: &'static str,
+        filetype: &str,
+    ) {
+        self.entry(source, filetype).items_accepted += 1;
+    }
+
+    /// Records the outcome of a request made to the source named `source`
+    /// in a buffer of the given filetype.
+    pub(crate) fn record_request(
+        &mut self,
+        source: &'static str,
+        filetype: &str,
+        outcome: Outcome,
+    ) {
+        let stats = self.entry(source, filetype);
+
+        // Debounced requests never reached the source.
+        if outcome != Outcome::Debounced {
+            stats.requests += 1;
+        }
+
+        match outcome {
+            Outcome::Completed { items, latency } => {
+                stats.items_returned += items as u64;
+                stats.push_latency(latency);
+            },
+
+            Outcome::Failed { latency } => {
+                stats.errors += 1;
+                stats.push_latency(latency);
+            },
+
+            Outcome::Cancelled => stats.cancelled += 1,
+
+            Outcome::TimedOut => stats.timed_out += 1,
+
+            Outcome::Debounced => stats.debounced += 1,
+        }
+    }
+
+    /// Renders the statistics as a table, one row per source and filetype,
+    /// sorted by source name and filetype.
+    pub(crate) fn render(&self) -> Vec<String> {
+        let mut keys = self.sources.keys().collect::<Vec<_>>();
+        keys.sort();
+
+        let rows = keys
+            .into_iter()
+            .map(|key @ (source, filetype)| {
+                let stats = &self.sources[key];
+
+                let latency = |p| {
+                    stats.percentile(p).map_or_else(
+                        || String::from("-"),
+                        |latency| format!("{}ms", latency.as_millis()),
+                    )
+                };
+
+                [
+                    source.to_string(),
+                    if filetype.is_empty() {
+                        String::from("-")
+                    } else {
+                        filetype.clone()
+                    },
+                    stats.debounced.to_string(),
+                    stats.requests.to_string(),
+                    stats.cancelled.to_string(),
+                    stats.timed_out.to_string(),
+                    stats.errors.to_string(),
+                    latency(50.0),
+                    latency(95.0),
+                    latency(99.0),
+                    stats.items_returned.to_string(),
+                    stats.items_accepted.to_string(),
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        let mut widths = HEADER.map(str::len);
+
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.len());
+            }
+        }
+
+        let header = HEADER.map(ToOwned::to_owned);
+
+        std::iter::once(&header)
+            .chain(&rows)
+            .map(|row| {
+                row.iter()
+                    .zip(widths)
+                    .enumerate()
+                    .map(|(i, (cell, width))| match i {
+                        // Source names and filetypes are aligned to the
+                        // left, numbers to the right.
+                        0 | 1 => format!("{cell:<width$}"),
+                        _ => format!("{cell:>width$}"),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("  ")
+                    .trim_end()
+                    .to_owned()
+            })
+            .collect()
+    }
+
+    /// Removes all the recorded statistics.
+    pub(crate) fn reset(&mut self) {
+        self.sources.clear();
+    }
+
+    fn entry(
+        &mut self,
+        source: &'static str,
+        filetype: &str,
+    ) -> &mut SourceStats {
+        self.sources.entry((source, filetype.to_owned())).or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn outcomes() {
+        let mut stats = Stats::default();
+
+        stats.record_request(
+            "lsp",
+            "rust",
+            Outcome::Completed { items: 10, latency: ms(20) },
+        );
+        stats.record_request(
+            "lsp",
+            "rust",
+            Outcome::Failed { latency: ms(5) },
+        );
+        stats.record_request("lsp", "rust", Outcome::Cancelled);
+        stats.record_request("lsp", "rust", Outcome::TimedOut);
+        stats.record_request("lsp", "rust", Outcome::Debounced);
+        stats.record_accepted("lsp", "rust");
+
+        let lsp = stats.get("lsp", "rust").unwrap();
+        assert_eq!(1, lsp.debounced);
+        assert_eq!(4, lsp.requests);
+        assert_eq!(1, lsp.errors);
+        assert_eq!(1, lsp.cancelled);
+        assert_eq!(1, lsp.timed_out);
+        assert_eq!(10, lsp.items_returned);
+        assert_eq!(1, lsp.items_accepted);
+
+        assert!(stats.get("lsp", "lua").is_none());
+    }
+
+    #[test]
+    fn percentiles() {
+        let mut stats = Stats::default();
+
+        for millis in (1..=100).rev() {
+            stats.record_request(
+                "lsp",
+                "",
+                Outcome::Completed { items: 0, latency: ms(millis) },
+            );
+        }
+
+        let lsp = stats.get("lsp", "").unwrap();
+        assert_eq!(Some(ms(50)), lsp.percentile(50.0));
+        assert_eq!(Some(ms(95)), lsp.percentile(95.0));
+        assert_eq!(Some(ms(99)), lsp.percentile(99.0));
+        assert_eq!(Some(ms(1)), lsp.percentile(0.0));
+    }
+
+    #[test]
+    fn no_latencies() {
+        let mut stats = Stats::default();
+        stats.record_request("lsp", "", Outcome::Cancelled);
+        assert_eq!(None, stats.get("lsp", "").unwrap().percentile(50.0));
+    }
+
+    #[test]
+    fn bounded_latencies() {
+        let mut stats = Stats::default();
+
+        for _ in 0..MAX_LATENCIES {
+            stats.record_request(
+                "lsp",
+                "",
+                Outcome::Completed { items: 0, latency: ms(100) },
+            );
+        }
+
+        stats.record_request(
+            "lsp",
+            "",
+            Outcome::Completed { items: 0, latency: ms(1) },
+        );
+
+        let lsp = stats.get("lsp", "").unwrap();
+        assert_eq!(MAX_LATENCIES, lsp.latencies.len());
+        assert_eq!(Some(ms(1)), lsp.percentile(0.0));
+    }
+
+    #[test]
+    fn render() {
+        let mut stats = Stats::default();
+
+        stats.record_request(
+            "lsp",
+            "rust",
+            Outcome::Completed { items: 12, latency: ms(40) },
+        );
+        stats.record_request(
+            "buffer",
+            "",
+            Outcome::Completed { items: 3, latency: ms(2) },
+        );
+
+        assert_eq!(
+            vec![
+                "source  filetype  debounced  requests  cancelled  timed out  \
+                 errors   p50   p95   p99  returned  accepted",
+                "buffer  -                 0         1          0          \
+                 0       0   2ms   2ms   2ms         3         0",
+                "lsp     rust              0         1          0          \
+                 0       0  40ms  40ms  40ms        12         0",
+            ],
+            stats.render()
+        );
+    }
+}
@@ -0,0 +1,102 @@
+/// How the columns of a [`Position`] are measured.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PositionEncoding {
+    /// Columns are byte offsets. This is how Neovim measures them.
+    #[default]
+    Utf8,
+
+    /// Columns are UTF-16 code units. This is how the LSP specification
+    /// measures them unless the client and the server agree otherwise.
+    Utf16,
+
+    /// Columns are UTF-32 code units, i.e. `char`s.
+    Utf32,
+}
+
+impl PositionEncoding {
+    /// Converts `col` from this encoding to a byte offset in `line`,
+    /// clamping it to the length of the line. Columns falling inside a
+    /// character are moved to its start.
+    pub(crate) fn byte_offset(self, line: &str, col: usize) -> usize {
+        let unit_len: fn(char) -> usize = match self {
+            Self::Utf8 => {
+                return (0..=col.min(line.len()))
+                    .rev()
+                    .find(|&idx| line.is_char_boundary(idx))
+                    .unwrap_or_default();
+            },
+            Self::Utf16 => char::len_utf16,
+            Self::Utf32 => |_| 1,
+        };
+
+        let mut units = 0;
+
+        for (idx, char) in line.char_indices() {
+            units += unit_len(char);
+            if units > col {
+                return idx;
+            }
+        }
+
+        line.len()
+    }
+}
+
+/// A position in a buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    /// The row of the position (0-indexed).
+    pub row: usize,
+
+    /// The column of the position, measured in the encoding of the item the
+    /// position belongs to.
+    pub col: usize,
+}
+
+/// A range in a buffer, with the end position being exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextRange {
+    pub start: Position,
+    pub end: Position,
+}
+
+/// Replaces the text in `range` with `new_text`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub range: TextRange,
+    pub new_text: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PositionEncoding::*;
+
+    #[test]
+    fn ascii() {
+        for encoding in [Utf8, Utf16, Utf32] {
+            assert_eq!(0, encoding.byte_offset("foo", 0));
+            assert_eq!(2, encoding.byte_offset("foo", 2));
+            assert_eq!(3, encoding.byte_offset("foo", 3));
+            assert_eq!(3, encoding.byte_offset("foo", 42));
+        }
+    }
+
+    #[test]
+    fn multibyte() {
+        // `é` is 2 bytes and 1 UTF-16 code unit, `𝄞` is 4 bytes and 2 UTF-16
+        // code units.
+        let line = "é𝄞x";
+        assert_eq!(2, Utf16.byte_offset(line, 1));
+        assert_eq!(6, Utf16.byte_offset(line, 3));
+        assert_eq!(7, Utf16.byte_offset(line, 4));
+        assert_eq!(2, Utf32.byte_offset(line, 1));
+        assert_eq!(6, Utf32.byte_offset(line, 2));
+        assert_eq!(2, Utf8.byte_offset(line, 2));
+    }
+
+    #[test]
+    fn inside_a_character() {
+        assert_eq!(2, Utf16.byte_offset("é𝄞x", 2));
+        assert_eq!(0, Utf8.byte_offset("é", 1));
+    }
+}
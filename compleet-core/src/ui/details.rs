@@ -0,0 +1,159 @@
+use std::cmp;
+
+use nvim_oxi::{
+    self as nvim,
+    api::{self, Window},
+};
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::floater::{Floater, RelativeTo};
+use crate::{hlgroups, Config, Details};
+
+pub(crate) struct CompletionDetails {
+    /// Floating window used to show the details of the selected completion.
+    floater: Floater,
+
+    /// The filetype currently set in the floater's buffer.
+    filetype: String,
+}
+
+impl CompletionDetails {
+    pub(crate) fn new(config: &Config) -> nvim::Result<Self> {
+        Ok(Self {
+            floater: Floater::new(
+                config.details_border(),
+                &[
+                    ("FloatBorder", hlgroups::DETAILS_BORDER),
+                    ("Normal", hlgroups::DETAILS),
+                    ("Search", "None"),
+                ],
+            )?,
+            filetype: String::new(),
+        })
+    }
+
+    /// Closes the details window.
+    #[inline]
+    pub(crate) fn close(&mut self) -> nvim::Result<()> {
+        self.floater.close()
+    }
+
+    /// Updates both the contents and the position of the details window,
+    /// showing `details` next to the completion menu displayed in `menu`
+    /// (whose border has the edges in `menu_edges`). The window is closed
+    /// if there's no space to show it.
+    pub(crate) fn update(
+        &mut self,
+        details: &Details,
+        menu: &Window,
+        menu_edges: [bool; 4],
+    ) -> nvim::Result<()> {
+        if details.text.is_empty() {
+            return self.close();
+        }
+
+        let (row, col, height, width) = match self::find_position(
+            &details.text,
+            menu,
+            menu_edges,
+            self.floater.border_edges(),
+        )? {
+            Some(pos) => pos,
+
+            // If it wasn't possible to position the window we close it.
+            None => return self.close(),
+        };
+
+        let buf = self.floater.buffer();
+        let line_count = buf.line_count()?;
+        let lines = details.text.iter().map(String::as_str);
+        buf.set_lines(0, line_count, false, lines)?;
+
+        // Setting the filetype reloads the syntax highlighting, so we only
+        // do it when it changes.
+        if details.ft != self.filetype {
+            buf.set_option("filetype", &*details.ft)?;
+            self.filetype = details.ft.clone();
+        }
+
+        let position = RelativeTo::Floater(menu.clone(), row, col);
+        self.floater.open(position, height, width)?;
+
+        // Always show the details starting from their first line.
+        if let Some(win) = self.floater.window() {
+            win.set_cursor(1, 0)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Tries to find a way to position the details window relative to the
+/// completion menu, first on its right and then on its left. The top edge
+/// of the details window always lines up with the top edge of the menu.
+fn find_position(
+    lines: &[String],
+    menu: &Window,
+    menu_edges: [bool; 4],
+    edges: [bool; 4],
+) -> nvim::Result<Option<(i32, i32, u32, u32)>> {
+    let [menu_top, _, menu_left, menu_right] = menu_edges.map(u32::from);
+    let [top, bottom, left, right] = edges.map(u32::from);
+
+    let columns = api::get_option::<u32>("columns")?;
+    let screen_lines = api::get_option::<u32>("lines")?;
+    let cmdheight = api::get_option::<u32>("cmdheight")?;
+
+    let (menu_row, menu_col) = menu.get_position()?;
+    let (menu_row, menu_col) = (menu_row as u32, menu_col as u32);
+    let menu_width = menu.get_width()?;
+
+    // The number of screen columns before and after the completion menu,
+    // also counting the edges of its border.
+    let cols_before = menu_col.saturating_sub(menu_left);
+    let cols_after =
+        columns.saturating_sub(menu_col + menu_width + menu_right);
+
+    let widths = lines
+        .iter()
+        .map(|line| line.graphemes(true).count() as u32)
+        .collect::<Vec<_>>();
+
+    let longest = widths.iter().copied().max().unwrap_or_default();
+
+    // If the details don't fit on the right of the menu they're placed on
+    // the side with the most space, where long lines are wrapped.
+    let on_the_right =
+        longest + left + right <= cols_after || cols_after >= cols_before;
+
+    let available = if on_the_right { cols_after } else { cols_before };
+
+    let width = cmp::min(longest, available.saturating_sub(left + right));
+
+    if width == 0 {
+        return Ok(None);
+    }
+
+    let col = if on_the_right {
+        (menu_width + menu_right) as i32
+    } else {
+        -((menu_left + right + width + left) as i32)
+    };
+
+    let row = -(menu_top as i32);
+
+    let wrapped_height =
+        widths.iter().map(|&len| cmp::max(1, (len + width - 1) / width)).sum();
+
+    let max_height = screen_lines.saturating_sub(
+        menu_row.saturating_sub(menu_top) + cmdheight + top + bottom,
+    );
+
+    let height = cmp::min(wrapped_height, max_height);
+
+    if height == 0 {
+        return Ok(None);
+    }
+
+    Ok(Some((row, col, height, width)))
+}
@@ -0,0 +1,186 @@
+use nvim_oxi::{
+    self as nvim,
+    api::{self, Buffer, Window},
+    types::{WindowBorder, WindowConfig, WindowRelativeTo, WindowStyle},
+    Array,
+    FromObject,
+    Object,
+    ObjectKind,
+};
+
+/// Abstracts Neovim's floating windows (see `:h api-floatwin` for details).
+pub(crate) struct Floater {
+    /// The style of the border, or `None` if the floater doesn't have one.
+    border: Option<WindowBorder>,
+
+    /// An array of the form `[top_edge, bottom_edge, left_edge, right_edge]`
+    /// where each element is `true` if that edge of the border is present.
+    border_edges: [bool; 4],
+
+    /// The buffer displayed in the floating window. It's created once in
+    /// [`Floater::new`] and never changes.
+    buf: Buffer,
+
+    /// The floating window, or `None` if it's currently closed.
+    win: Option<Window>,
+
+    /// Window-local highlights. See `:h winhl` for details.
+    winhl: String,
+}
+
+/// What the floater is positioned relative to.
+pub(crate) enum RelativeTo {
+    Cursor(i32, i32),
+
+    /// Positioned relative to the top-left corner of another floater's
+    /// window.
+    Floater(Window, i32, i32),
+}
+
+impl Floater {
+    pub(crate) fn new(
+        border: Option<&WindowBorder>,
+        hl_groups: &[(&str, &str)],
+    ) -> nvim::Result<Self> {
+        let border_edges = match border {
+            Some(border) => self::border_edges(border)?,
+            None => [false; 4],
+        };
+
+        let winhl = hl_groups
+            .iter()
+            .map(|(builtin, custom)| format!("{builtin}:{custom}"))
+            .collect::<Vec<_>>()
+            .join(",");
+
+        Ok(Self {
+            border: border.cloned(),
+            border_edges,
+            buf: api::create_buf(false, true)?,
+            win: None,
+            winhl,
+        })
+    }
+
+    /// The buffer displayed in the floating window.
+    #[inline]
+    pub(crate) fn buffer(&mut self) -> &mut Buffer {
+        &mut self.buf
+    }
+
+    /// Closes the floater if it's open, does nothing otherwise.
+    pub(crate) fn close(&mut self) -> nvim::Result<()> {
+        if let Some(win) = self.win.take() {
+            win.hide()?;
+        }
+        Ok(())
+    }
+
+    /// Whether the floater is currently open.
+    #[inline]
+    pub(crate) fn is_open(&self) -> bool {
+        self.win.is_some()
+    }
+
+    /// The floating window, or `None` if the floater is closed.
+    #[inline]
+    pub(crate) fn window(&mut self) -> Option<&mut Window> {
+        self.win.as_mut()
+    }
+
+    /// An array of the form `[top_edge, bottom_edge, left_edge, right_edge]`
+    /// where each element is `true` if that edge of the border is present.
+    #[inline]
+    pub(crate) fn border_edges(&self) -> [bool; 4] {
+        self.border_edges
+    }
+
+    /// Opens the floater at the given position, or moves it there if it's
+    /// already open.
+    pub(crate) fn open(
+        &mut self,
+        position: RelativeTo,
+        height: u32,
+        width: u32,
+    ) -> nvim::Result<()> {
+        let mut config = WindowConfig::builder();
+
+        match position {
+            RelativeTo::Cursor(row, col) => {
+                config.relative(WindowRelativeTo::Cursor).row(row).col(col)
+            },
+
+            RelativeTo::Floater(win, row, col) => config
+                .relative(WindowRelativeTo::Window(win))
+                .row(row)
+                .col(col),
+        };
+
+        config.height(height).width(width);
+
+        match &mut self.win {
+            Some(win) => win.set_config(&config.build())?,
+
+            None => {
+                config.style(WindowStyle::Minimal).focusable(false);
+
+                if let Some(border) = &self.border {
+                    config.border(border.clone());
+                }
+
+                let mut win =
+                    api::open_win(&self.buf, false, &config.build())?;
+                win.set_option("winhl", &*self.winhl)?;
+                win.set_option("scrolloff", 0)?;
+                self.win = Some(win);
+            },
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns an array of the form `[top, bottom, left, right]` where each
+/// element is `true` if that edge of the border is present.
+fn border_edges(border: &WindowBorder) -> nvim::Result<[bool; 4]> {
+    let border = Object::from(border.clone());
+
+    if let ObjectKind::String = border.kind() {
+        let style = nvim::String::from_object(border)?;
+
+        return Ok(match style.to_string_lossy().as_ref() {
+            "none" => [false; 4],
+            "shadow" => [false, true, false, true],
+            _ => [true; 4],
+        });
+    }
+
+    // The border is an array of up to 8 chars, starting from the top-left
+    // corner and going clockwise. Arrays shorter than 8 elements are
+    // repeated. Each element is either a char or a `[char, hl_group]` tuple,
+    // and an empty char means that the edge is missing.
+    let chars = Array::from_object(border)?
+        .into_iter()
+        .map(|item| {
+            let char = match item.kind() {
+                ObjectKind::Array => Array::from_object(item)?
+                    .into_iter()
+                    .next()
+                    .map(nvim::String::from_object)
+                    .transpose()?,
+
+                _ => Some(nvim::String::from_object(item)?),
+            };
+
+            Ok(char.map(|c| !c.as_bytes().is_empty()).unwrap_or(false))
+        })
+        .collect::<nvim::Result<Vec<bool>>>()?;
+
+    if chars.is_empty() {
+        return Ok([false; 4]);
+    }
+
+    let edge = |i: usize| chars[i % chars.len()];
+
+    Ok([edge(1), edge(5), edge(7), edge(3)])
+}
@@ -0,0 +1,459 @@
+use std::borrow::Cow;
+use std::cmp;
+use std::ops::Range;
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::config::{ColumnAlign, ColumnKind, MenuColumn};
+use crate::hlgroups;
+use crate::matched_completion::MatchedCompletion;
+
+/// The character ending the contents of a column that had to be truncated.
+const ELLIPSIS: char = '…';
+
+/// The completions laid out in aligned columns, one line per completion.
+#[derive(Debug, Default)]
+pub(crate) struct MenuLayout<'a> {
+    /// The lines displayed in the completion menu.
+    pub(crate) lines: Vec<String>,
+
+    /// The highlight ranges of every line, in bytes.
+    pub(crate) highlights: Vec<Vec<(Range<usize>, &'a str)>>,
+
+    /// The byte range of the label in every line, or `None` if the label
+    /// isn't shown. If the label was truncated only its visible part is
+    /// included.
+    pub(crate) labels: Vec<Option<Range<usize>>>,
+
+    /// The column where the label starts in every line, in display cells,
+    /// or `None` if the label isn't shown. If the label is aligned
+    /// to the right this is where the padding before it starts.
+    pub(crate) label_col: Option<usize>,
+
+    /// The width of every line, in display cells.
+    pub(crate) width: usize,
+}
+
+/// The contents of a group of joined columns for a single completion.
+#[derive(Default)]
+struct Cell<'a> {
+    text: String,
+
+    /// The number of display cells taken by `text`.
+    width: usize,
+
+    highlights: Vec<(Range<usize>, &'a str)>,
+
+    /// The byte range of the label in `text`, if the cell contains it.
+    label: Option<Range<usize>>,
+}
+
+impl<'a> Cell<'a> {
+    /// Appends the contents of `column` for `completion` to the cell.
+    fn push(
+        &mut self,
+        column: &'a MenuColumn,
+        completion: &'a MatchedCompletion,
+    ) {
+        let item = &*completion.item;
+
+        let (text, default_group): (Option<Cow<'a, str>>, _) = match column
+            .kind
+        {
+            ColumnKind::KindIcon => (
+                item.icon().map(|i| i.to_string().into()),
+                item.icon_hl_group(),
+            ),
+
+            ColumnKind::KindText => {
+                (item.kind().map(|kind| kind.as_str().into()), None)
+            },
+
+            ColumnKind::Label => (Some(item.label().into()), None),
+
+            ColumnKind::LabelDetail => (
+                item.label_detail().map(Into::into),
+                Some(hlgroups::MENU_LABEL_DETAIL),
+            ),
+
+            ColumnKind::Infos => (
+                item.infos().map(Into::into),
+                Some(item.infos_hl_group().unwrap_or(hlgroups::MENU_INFOS)),
+            ),
+
+            ColumnKind::Source => {
+                (Some(completion.source.into()), Some(hlgroups::MENU_INFOS))
+            },
+        };
+
+        let text = match text {
+            Some(text) if !text.is_empty() => text,
+            _ => return,
+        };
+
+        let (visible, width) = self::truncate(&text, column.max_width);
+
+        let offset = self.text.len();
+        self.text.push_str(visible);
+        self.width += width;
+
+        let visible_end = offset + visible.len();
+
+        if visible.len() < text.len() {
+            self.text.push(ELLIPSIS);
+        }
+
+        if let Some(group) = column.hl_group.as_deref().or(default_group) {
+            self.highlights.push((offset..self.text.len(), group));
+        }
+
+        if column.kind != ColumnKind::Label {
+            return;
+        }
+
+        if item.is_deprecated() {
+            let group = hlgroups::MENU_DEPRECATED;
+            self.highlights.push((offset..self.text.len(), group));
+        }
+
+        // The ranges set by the source can't go past the visible part of the
+        // label.
+        self.highlights.extend(item.label_hl_ranges().iter().filter_map(
+            |range| {
+                let start = offset + range.bytes.start;
+                let end = cmp::min(offset + range.bytes.end, visible_end);
+                (start < end).then_some((start..end, range.group))
+            },
+        ));
+
+        self.label = Some(offset..visible_end);
+    }
+}
+
+/// Lays out the `completions` in the given `columns`. Columns are separated
+/// by a single space, and the lines also start and end with one.
+pub(crate) fn layout<'a>(
+    completions: &'a [MatchedCompletion],
+    columns: &'a [MenuColumn],
+) -> MenuLayout<'a> {
+    let groups = self::groups(columns);
+
+    let cells = completions
+        .iter()
+        .map(|completion| {
+            groups
+                .iter()
+                .map(|group| {
+                    let mut cell = Cell::default();
+                    for column in *group {
+                        cell.push(column, completion);
+                    }
+                    cell
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    // The width of every group of columns. Groups that are empty for all
+    // the completions have a width of zero and are hidden.
+    let widths = (0..groups.len())
+        .map(|idx| cells.iter().map(|row| row[idx].width).max().unwrap_or(0))
+        .collect::<Vec<_>>();
+
+    let width =
+        1 + widths.iter().filter(|&&w| w > 0).map(|w| w + 1).sum::<usize>();
+
+    // Every group starts at the same column in all the lines.
+    let label_col = groups
+        .iter()
+        .zip(&widths)
+        .filter(|&(_, &width)| width > 0)
+        .scan(1, |col, (group, width)| {
+            let start = *col;
+            *col += width + 1;
+            Some((group, start))
+        })
+        .find_map(|(group, col)| {
+            group.iter().any(|c| c.kind == ColumnKind::Label).then_some(col)
+        });
+
+    let mut layout = MenuLayout { label_col, width, ..Default::default() };
+
+    for row in cells {
+        let mut line = String::new();
+        let mut highlights = Vec::new();
+        let mut label = None;
+
+        for ((cell, group), &width) in
+            row.into_iter().zip(&groups).zip(&widths)
+        {
+            if width == 0 {
+                continue;
+            }
+
+            line.push(' ');
+
+            let padding = " ".repeat(width - cell.width);
+            let align = group[0].align;
+
+            if align == ColumnAlign::Right {
+                line.push_str(&padding);
+            }
+
+            let offset = line.len();
+            line.push_str(&cell.text);
+
+            highlights.extend(cell.highlights.into_iter().map(
+                |(bytes, group)| {
+                    (offset + bytes.start..offset + bytes.end, group)
+                },
+            ));
+
+            if let Some(bytes) = cell.label {
+                label = Some(offset + bytes.start..offset + bytes.end);
+            }
+
+            if align == ColumnAlign::Left {
+                line.push_str(&padding);
+            }
+        }
+
+        line.push(' ');
+
+        layout.lines.push(line);
+        layout.highlights.push(highlights);
+        layout.labels.push(label);
+    }
+
+    layout
+}
+
+/// Splits the columns in groups whose contents are joined without any
+/// spacing. Only a `label_detail` directly following the `label` is joined
+/// with it.
+fn groups(columns: &[MenuColumn]) -> Vec<&[MenuColumn]> {
+    let mut groups = Vec::<&[MenuColumn]>::new();
+    let mut start = 0;
+
+    for (idx, column) in columns.iter().enumerate().skip(1) {
+        let is_joined = column.kind == ColumnKind::LabelDetail
+            && columns[idx - 1].kind == ColumnKind::Label;
+
+        if !is_joined {
+            groups.push(&columns[start..idx]);
+            start = idx;
+        }
+    }
+
+    if start < columns.len() {
+        groups.push(&columns[start..]);
+    }
+
+    groups
+}
+
+/// Returns the prefix of `text` that fits in `max_width` display cells
+/// together with its width. If the text is too long the prefix leaves room
+/// for an ellipsis, which is included in the returned width.
+fn truncate(text: &str, max_width: Option<u32>) -> (&str, usize) {
+    let width = text.width();
+
+    let max = match max_width.map(|max| max as usize) {
+        Some(max) if width > max => max,
+        _ => return (text, width),
+    };
+
+    let mut end = 0;
+    let mut width = 0;
+
+    // Only whole grapheme clusters are kept, so the prefix can be narrower
+    // than `max - 1` if it would end in the middle of a wide character.
+    for (idx, grapheme) in text.grapheme_indices(true) {
+        let grapheme_width = grapheme.width();
+        if width + grapheme_width > max - 1 {
+            break;
+        }
+        end = idx + grapheme.len();
+        width += grapheme_width;
+    }
+
+    (&text[..end], width + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{CompletionItem, CompletionItemKind};
+
+    fn matched(item: CompletionItem) -> MatchedCompletion {
+        MatchedCompletion::new(Arc::new(item), "lsp", "").unwrap()
+    }
+
+    fn default_columns() -> Vec<MenuColumn> {
+        use ColumnKind::*;
+        [KindIcon, Label, LabelDetail, Infos]
+            .into_iter()
+            .map(Into::into)
+            .collect()
+    }
+
+    #[test]
+    fn without_icon_and_infos() {
+        let completions = [matched(CompletionItem::builder("foo").build())];
+        let columns = default_columns();
+        let layout = layout(&completions, &columns);
+        assert_eq!(vec![" foo "], layout.lines);
+        assert_eq!(Some(1..4), layout.labels[0]);
+        assert_eq!(Some(1), layout.label_col);
+        assert_eq!(5, layout.width);
+    }
+
+    #[test]
+    fn with_icon_and_infos() {
+        let completions = [matched(
+            CompletionItem::builder("foo")
+                .icon('λ')
+                .icon_hl_group("Icon")
+                .infos("bar")
+                .build(),
+        )];
+        let columns = default_columns();
+        let layout = layout(&completions, &columns);
+        assert_eq!(vec![" λ foo bar "], layout.lines);
+        assert_eq!(Some(4..7), layout.labels[0]);
+        assert_eq!(Some(3), layout.label_col);
+        assert_eq!(11, layout.width);
+        assert_eq!(
+            vec![(1..3, "Icon"), (8..11, hlgroups::MENU_INFOS)],
+            layout.highlights[0]
+        );
+    }
+
+    #[test]
+    fn columns_are_aligned() {
+        let completions = [
+            matched(
+                CompletionItem::builder("foo").icon('λ').infos("bar").build(),
+            ),
+            matched(CompletionItem::builder("foobar").build()),
+        ];
+        let columns = default_columns();
+        let layout = layout(&completions, &columns);
+        assert_eq!(vec![" λ foo    bar ", "   foobar     "], layout.lines);
+        assert_eq!(Some(4..7), layout.labels[0]);
+        assert_eq!(Some(3..9), layout.labels[1]);
+        assert_eq!(14, layout.width);
+    }
+
+    #[test]
+    fn label_detail_is_joined_with_label() {
+        let completions = [matched(
+            CompletionItem::builder("foo")
+                .label_detail("(a, b)")
+                .infos("bar")
+                .is_deprecated(true)
+                .build(),
+        )];
+        let columns = default_columns();
+        let layout = layout(&completions, &columns);
+        assert_eq!(vec![" foo(a, b) bar "], layout.lines);
+        assert_eq!(
+            vec![
+                (1..4, hlgroups::MENU_DEPRECATED),
+                (4..10, hlgroups::MENU_LABEL_DETAIL),
+                (11..14, hlgroups::MENU_INFOS),
+            ],
+            layout.highlights[0]
+        );
+    }
+
+    #[test]
+    fn truncated_column() {
+        let completions = [matched(
+            CompletionItem::builder("foobar")
+                .highlight_label(2..6, "Hl")
+                .build(),
+        )];
+        let columns = [MenuColumn {
+            max_width: Some(4),
+            hl_group: Some("Label".into()),
+            ..ColumnKind::Label.into()
+        }];
+        let layout = layout(&completions, &columns);
+        assert_eq!(vec![" foo… "], layout.lines);
+        assert_eq!(Some(1..4), layout.labels[0]);
+        assert_eq!(vec![(1..7, "Label"), (3..4, "Hl")], layout.highlights[0]);
+        assert_eq!(6, layout.width);
+    }
+
+    #[test]
+    fn kind_text_and_source() {
+        let completions = [
+            matched(
+                CompletionItem::builder("foo")
+                    .kind(CompletionItemKind::Function)
+                    .build(),
+            ),
+            matched(CompletionItem::builder("bar").build()),
+        ];
+        let columns = [
+            ColumnKind::Label.into(),
+            ColumnKind::KindText.into(),
+            MenuColumn {
+                align: ColumnAlign::Right,
+                ..ColumnKind::Source.into()
+            },
+        ];
+        let layout = layout(&completions, &columns);
+        assert_eq!(
+            vec![" foo Function lsp ", " bar          lsp "],
+            layout.lines
+        );
+        assert_eq!(Some(1), layout.label_col);
+    }
+
+    #[test]
+    fn width_counts_display_cells() {
+        let completions = [matched(CompletionItem::builder("café").build())];
+        let columns = default_columns();
+        let layout = layout(&completions, &columns);
+        assert_eq!(6, layout.width);
+        assert_eq!(7, layout.lines[0].len());
+    }
+
+    #[test]
+    fn double_width_label() {
+        let completions = [
+            matched(CompletionItem::builder("日本").infos("bar").build()),
+            matched(CompletionItem::builder("foo").infos("baz").build()),
+        ];
+        let columns = default_columns();
+        let layout = layout(&completions, &columns);
+        assert_eq!(vec![" 日本 bar ", " foo  baz "], layout.lines);
+        assert_eq!(10, layout.width);
+    }
+
+    #[test]
+    fn truncated_double_width_label() {
+        let completions = [matched(CompletionItem::builder("日本語").build())];
+        let columns =
+            [MenuColumn { max_width: Some(4), ..ColumnKind::Label.into() }];
+        let layout = layout(&completions, &columns);
+        // The second character doesn't fit before the ellipsis.
+        assert_eq!(vec![" 日… "], layout.lines);
+        assert_eq!(5, layout.width);
+    }
+
+    #[test]
+    fn hidden_label_has_no_column() {
+        let completions =
+            [matched(CompletionItem::builder("foo").icon('λ').build())];
+        let columns = [ColumnKind::KindIcon.into()];
+        let layout = layout(&completions, &columns);
+        assert_eq!(vec![" λ "], layout.lines);
+        assert_eq!(None, layout.label_col);
+    }
+}
@@ -0,0 +1,479 @@
+use std::cmp;
+use std::ops::Range;
+use std::rc::Rc;
+
+use nvim_oxi::{
+    self as nvim,
+    api::{self, Window},
+    opts::SetExtmarkOpts,
+    types::ExtmarkVirtTextPosition,
+    Dictionary,
+    Object,
+};
+
+use super::floater::{Floater, RelativeTo};
+use super::layout::{self, MenuLayout};
+use crate::config::{MenuAnchor, MenuColumn};
+use crate::hlgroups;
+use crate::matched_completion::MatchedCompletion;
+use crate::Config;
+
+/// Sets the chunks `_A.text` of the border's `_A.edge` (either "title" or
+/// "footer") of the window `_A.win`, aligning them to the right.
+const SET_BORDER_TEXT: &str = r#"
+(function(args)
+  vim.api.nvim_win_set_config(args.win, {
+    [args.edge] = { { args.text, args.hl_group } },
+    [args.edge .. "_pos"] = "right",
+  })
+end)(_A)
+"#;
+
+pub(crate) struct CompletionMenu {
+    /// Floating window used to show the completion menu.
+    floater: Floater,
+
+    /// Where the menu is anchored.
+    anchor: MenuAnchor,
+
+    /// The columns shown for every completion.
+    columns: Rc<[MenuColumn]>,
+
+    /// The edge of the border where the counter is shown, either "title" or
+    /// "footer", or `None` if the counter is disabled or there's no edge to
+    /// show it in.
+    counter: Option<&'static str>,
+
+    /// The number of completions currently in the menu.
+    len: usize,
+
+    /// The maximum number of completions shown at once, or `None` if there
+    /// is no limit.
+    max_height: Option<u32>,
+
+    /// The namespace used to highlight the completions.
+    namespace: u32,
+
+    /// The namespace used to draw the scrollbar, or `None` if the scrollbar
+    /// is disabled.
+    scrollbar: Option<u32>,
+
+    /// The index of the currently selected completion, if any.
+    selected_index: Option<usize>,
+}
+
+impl CompletionMenu {
+    pub(crate) fn new(config: &Config) -> nvim::Result<Self> {
+        let floater = Floater::new(
+            config.menu_border(),
+            &[
+                ("CursorLine", hlgroups::MENU_SELECTED),
+                ("FloatBorder", hlgroups::MENU_BORDER),
+                ("Normal", hlgroups::MENU),
+                ("Search", "None"),
+            ],
+        )?;
+
+        let counter = if config.menu_counter() {
+            self::counter_edge(floater.border_edges())?
+        } else {
+            None
+        };
+
+        let scrollbar = config
+            .menu_scrollbar()
+            .then(|| api::create_namespace("compleet_menu_scrollbar"));
+
+        Ok(Self {
+            floater,
+            anchor: config.menu_anchor(),
+            columns: config.menu_columns().into(),
+            counter,
+            len: 0,
+            max_height: config.menu_max_height(),
+            namespace: api::create_namespace("compleet_menu"),
+            scrollbar,
+            selected_index: None,
+        })
+    }
+
+    /// Closes the completion menu.
+    #[inline]
+    pub(crate) fn close(&mut self) -> nvim::Result<()> {
+        self.selected_index = None;
+        self.floater.close()
+    }
+
+    /// An array of the form `[top_edge, bottom_edge, left_edge, right_edge]`
+    /// where each element is `true` if that edge of the menu's border is
+    /// present.
+    #[inline]
+    pub(crate) fn border_edges(&self) -> [bool; 4] {
+        self.floater.border_edges()
+    }
+
+    /// Shows the index of the selected completion and the number of
+    /// completions in the border of the menu, if the counter is enabled.
+    fn draw_counter(&mut self) -> nvim::Result<()> {
+        let edge = match self.counter {
+            Some(edge) => edge,
+            None => return Ok(()),
+        };
+
+        let text = match self.selected_index {
+            Some(index) => format!(" {}/{} ", index + 1, self.len),
+            None => format!(" {} ", self.len),
+        };
+
+        let win = match self.floater.window() {
+            Some(win) => win.clone(),
+            None => return Ok(()),
+        };
+
+        let args = Dictionary::from_iter([
+            ("win", Object::from(win)),
+            ("edge", Object::from(edge)),
+            ("text", Object::from(text)),
+            ("hl_group", Object::from(hlgroups::MENU_COUNTER)),
+        ]);
+
+        api::call_function("luaeval", (SET_BORDER_TEXT, args))
+    }
+
+    /// Draws the scrollbar on the last column of the menu if it's not tall
+    /// enough to show all the completions, or removes it if it is.
+    fn draw_scrollbar(&mut self) -> nvim::Result<()> {
+        let namespace = match self.scrollbar {
+            Some(namespace) => namespace,
+            None => return Ok(()),
+        };
+
+        let win = match self.floater.window() {
+            Some(win) => win.clone(),
+            None => return Ok(()),
+        };
+
+        let buf = self.floater.buffer();
+        let line_count = buf.line_count()?;
+        buf.clear_namespace(namespace, 0, line_count)?;
+
+        let height = win.get_height()? as usize;
+        let width = win.get_width()?;
+
+        if self.len <= height {
+            return Ok(());
+        }
+
+        // The first line shown in the window, which Neovim updates when the
+        // cursor is moved to the selected completion.
+        let topline =
+            api::call_function::<_, usize>("line", ("w0", Object::from(win)))?
+                - 1;
+
+        let thumb = self::scrollbar_thumb(topline, height, self.len);
+
+        let mut opts = SetExtmarkOpts::builder();
+        opts.virt_text_pos(ExtmarkVirtTextPosition::Overlay)
+            .virt_text_win_col(width - 1);
+
+        for row in 0..height {
+            let group = if thumb.contains(&row) {
+                hlgroups::MENU_SCROLLBAR_THUMB
+            } else {
+                hlgroups::MENU_SCROLLBAR_GUTTER
+            };
+            opts.virt_text([(" ", group)]);
+            buf.set_extmark(namespace, topline + row, 0, &opts.build())?;
+        }
+
+        Ok(())
+    }
+
+    /// Fills the menu's buffer with the lines of the `layout`.
+    fn fill(&mut self, layout: &MenuLayout) -> nvim::Result<()> {
+        let lines = layout.lines.iter().map(String::as_str);
+        let buf = self.floater.buffer();
+        let line_count = buf.line_count()?;
+        buf.set_lines(0, line_count, false, lines)
+    }
+
+    /// Highlights the completions, also highlighting the characters of every
+    /// label that matched the current prefix.
+    fn highlight(
+        &mut self,
+        completions: &[MatchedCompletion],
+        layout: &MenuLayout,
+    ) -> nvim::Result<()> {
+        let namespace = self.namespace;
+        let buf = self.floater.buffer();
+
+        let line_count = buf.line_count()?;
+        buf.clear_namespace(namespace, 0, line_count)?;
+
+        let mut opts = SetExtmarkOpts::builder();
+
+        for (row, completion) in completions.iter().enumerate() {
+            // Set the highlight groups of the completion item.
+            opts.priority(100);
+
+            for (bytes, group) in &layout.highlights[row] {
+                opts.end_row(row).end_col(bytes.end).hl_group(group);
+                buf.set_extmark(namespace, row, bytes.start, &opts.build())?;
+            }
+
+            // Highlight the matched characters of every completion item, as
+            // long as they're in the visible part of the label.
+            let visible = match &layout.labels[row] {
+                Some(visible) => visible,
+                None => continue,
+            };
+
+            let label = completion.item.label();
+
+            opts.priority(101).hl_group(hlgroups::MENU_MATCHING);
+
+            for &byte in &completion.matched.matched_bytes {
+                let start = visible.start + byte;
+                if start >= visible.end {
+                    break;
+                }
+                let len =
+                    label[byte..].chars().next().map_or(1, char::len_utf8);
+                opts.end_row(row).end_col(start + len);
+                buf.set_extmark(namespace, row, start, &opts.build())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Whether the completion menu is currently open.
+    #[inline]
+    pub(crate) fn is_open(&self) -> bool {
+        self.floater.is_open()
+    }
+
+    /// Selects the completion at the given index, or removes the selection
+    /// if `index` is `None`.
+    pub(crate) fn select(&mut self, index: Option<usize>) -> nvim::Result<()> {
+        self.selected_index = index;
+
+        let win = match self.floater.window() {
+            Some(win) => win,
+            None => return Ok(()),
+        };
+
+        // The selected completion is highlighted using the `cursorline`.
+        win.set_option("cursorline", index.is_some())?;
+
+        if let Some(index) = index {
+            win.set_cursor(index + 1, 0)?;
+        }
+
+        // Moving the cursor can scroll the window.
+        self.draw_scrollbar()?;
+        self.draw_counter()
+    }
+
+    /// The index of the currently selected completion, if any.
+    #[inline]
+    pub(crate) fn selected_index(&self) -> Option<usize> {
+        self.selected_index
+    }
+
+    /// The menu's floating window, or `None` if the menu is closed.
+    #[inline]
+    pub(crate) fn window(&mut self) -> Option<&mut Window> {
+        self.floater.window()
+    }
+
+    /// Updates the contents and the position of the completion menu, closing
+    /// it if there are no completions to show. The `prefix` is the text
+    /// typed before the cursor that the completions matched, and `selected`
+    /// the index of the completion to select.
+    pub(crate) fn update(
+        &mut self,
+        completions: &[MatchedCompletion],
+        prefix: &str,
+        selected: Option<usize>,
+    ) -> nvim::Result<()> {
+        if completions.is_empty() {
+            return self.close();
+        }
+
+        let columns = Rc::clone(&self.columns);
+        let layout = layout::layout(completions, &columns);
+
+        let (row, col, height, width) = match self::find_position(
+            &layout,
+            &self.floater,
+            self.max_height,
+            self.anchor,
+            prefix,
+        )? {
+            Some(pos) => pos,
+
+            // If it wasn't possible to position the menu we close it.
+            None => return self.close(),
+        };
+
+        self.len = completions.len();
+        self.fill(&layout)?;
+        self.highlight(completions, &layout)?;
+        self.floater.open(RelativeTo::Cursor(row, col), height, width)?;
+
+        self.select(selected)
+    }
+}
+
+/// Returns the edge of a border with the given `edges` where the counter can
+/// be shown. Titles are only supported since Neovim 0.9 and footers since
+/// 0.10.
+fn counter_edge(
+    [top, bottom, ..]: [bool; 4],
+) -> nvim::Result<Option<&'static str>> {
+    let has = |feature: &str| {
+        api::call_function::<_, u32>("has", (feature,)).map(|has| has == 1)
+    };
+
+    Ok(if top && has("nvim-0.9")? {
+        Some("title")
+    } else if bottom && has("nvim-0.10")? {
+        Some("footer")
+    } else {
+        None
+    })
+}
+
+/// Returns the rows of a window `height` rows tall, counted from its top,
+/// covered by the thumb of the scrollbar when the first line shown out of
+/// `total` is `topline`. The thumb is always at least one row tall.
+fn scrollbar_thumb(
+    topline: usize,
+    height: usize,
+    total: usize,
+) -> Range<usize> {
+    let size = cmp::max(1, (height * height + total / 2) / total);
+
+    let max_topline = total - height;
+    let start = (topline * (height - size) + max_topline / 2) / max_topline;
+
+    start..start + size
+}
+
+/// Tries to find a way to position the completion menu relative to the
+/// current cursor position, which is right after `prefix`.
+fn find_position(
+    layout: &MenuLayout,
+    floater: &Floater,
+    max_height: Option<u32>,
+    anchor: MenuAnchor,
+    prefix: &str,
+) -> nvim::Result<Option<(i32, i32, u32, u32)>> {
+    let len = layout.lines.len() as u32;
+
+    let height = match max_height {
+        None => len,
+        Some(height) => cmp::min(height, len),
+    };
+
+    let width = layout.width as u32;
+
+    let [top, bottom, left, right] = floater.border_edges();
+
+    let (rows_above, rows_below) = self::rows_above_below_cursor()?;
+
+    // The total height of the completion menu, also counting the top and
+    // bottom edges of its border.
+    let total_height = height + top as u32 + bottom as u32;
+
+    let row = if total_height <= rows_below {
+        1
+    } else if total_height <= rows_above {
+        -(total_height as i32)
+    } else {
+        return Ok(None);
+    };
+
+    let col = match (anchor, layout.label_col) {
+        (MenuAnchor::Match, Some(label_col)) => {
+            let prefix_width =
+                api::call_function::<_, i32>("strdisplaywidth", (prefix,))?;
+            -(left as i32 + label_col as i32 + prefix_width)
+        },
+
+        // Without a label there's nothing to align with the prefix.
+        _ => -(left as i32),
+    };
+
+    // The 0-indexed screen column of the cursor. This can't use
+    // `screencol()`, which isn't updated until the screen is redrawn and
+    // would be stale when the menu is opened by an async callback.
+    let (_, win_col) = Window::current().get_position()?;
+    let cursor_col =
+        win_col as i32 + api::call_function::<_, i32>("wincol", ())? - 1;
+    let columns = api::get_option::<i32>("columns")?;
+
+    // Shift the menu to keep it on screen, cutting its right side if it's
+    // wider than the screen.
+    let total_width = (width + left as u32 + right as u32) as i32;
+    let col = cmp::max(
+        cmp::min(col, columns - total_width - cursor_col),
+        -cursor_col,
+    );
+
+    Ok(Some((row, col, height, width)))
+}
+
+/// Returns the number of screen rows above and below the current cursor
+/// position.
+fn rows_above_below_cursor() -> nvim::Result<(u32, u32)> {
+    let lines = api::get_option::<u32>("lines")?;
+    let cmdheight = api::get_option::<u32>("cmdheight")?;
+    let laststatus = api::get_option::<u32>("laststatus")?;
+    let showtabline = api::get_option::<u32>("showtabline")?;
+
+    // The 1-indexed screen row of the cursor, computed like the column in
+    // `find_position` since `screenrow()` is just as stale.
+    let (win_row, _) = Window::current().get_position()?;
+    let screenrow =
+        win_row as u32 + api::call_function::<_, u32>("winline", ())?;
+
+    let statusline_offset = match laststatus {
+        0 => 0,
+        1 => (api::call_function::<_, u32>("winnr", ("$",))? > 1) as u32,
+        _ => 1,
+    };
+
+    let tabline_offset = match showtabline {
+        0 => 0,
+        1 => (api::list_tabpages().count() > 1) as u32,
+        _ => 1,
+    };
+
+    let rows_above = screenrow.saturating_sub(tabline_offset + 1);
+
+    let rows_below = lines.saturating_sub(
+        rows_above + tabline_offset + statusline_offset + cmdheight + 1,
+    );
+
+    Ok((rows_above, rows_below))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scrollbar_thumb_at_the_edges() {
+        assert_eq!(0..2, scrollbar_thumb(0, 5, 12));
+        assert_eq!(3..5, scrollbar_thumb(7, 5, 12));
+    }
+
+    #[test]
+    fn scrollbar_thumb_is_at_least_one_row() {
+        assert_eq!(0..1, scrollbar_thumb(0, 10, 1000));
+        assert_eq!(9..10, scrollbar_thumb(990, 10, 1000));
+        assert_eq!(5..6, scrollbar_thumb(495, 10, 1000));
+    }
+}
@@ -0,0 +1,7 @@
+mod details;
+mod floater;
+mod layout;
+mod menu;
+mod ui;
+
+pub(crate) use ui::Ui;
@@ -0,0 +1,80 @@
+use nvim_oxi as nvim;
+
+use super::details::CompletionDetails;
+use super::menu::CompletionMenu;
+use crate::matched_completion::MatchedCompletion;
+use crate::{Config, Details};
+
+/// The client's UI.
+pub(crate) struct Ui {
+    /// The menu used to show all the available completion items.
+    menu: CompletionMenu,
+
+    /// The window showing the details of the selected completion.
+    details: CompletionDetails,
+}
+
+impl Ui {
+    pub(crate) fn new(config: &Config) -> nvim::Result<Self> {
+        Ok(Self {
+            menu: CompletionMenu::new(config)?,
+            details: CompletionDetails::new(config)?,
+        })
+    }
+
+    /// Closes every UI element.
+    pub(crate) fn close(&mut self) -> nvim::Result<()> {
+        self.details.close()?;
+        self.menu.close()
+    }
+
+    /// Whether the completion menu is currently open.
+    #[inline]
+    pub(crate) fn is_menu_open(&self) -> bool {
+        self.menu.is_open()
+    }
+
+    /// Selects the completion at the given index, or removes the selection
+    /// if `index` is `None`.
+    pub(crate) fn select(&mut self, index: Option<usize>) -> nvim::Result<()> {
+        self.menu.select(index)
+    }
+
+    /// The index of the currently selected completion, if any.
+    #[inline]
+    pub(crate) fn selected_index(&self) -> Option<usize> {
+        self.menu.selected_index()
+    }
+
+    /// Shows the `details` of the selected completion next to the
+    /// completion menu, or closes the details window if they're `None`.
+    pub(crate) fn show_details(
+        &mut self,
+        details: Option<&Details>,
+    ) -> nvim::Result<()> {
+        let edges = self.menu.border_edges();
+
+        match (details, self.menu.window()) {
+            (Some(details), Some(menu)) => {
+                self.details.update(details, menu, edges)
+            },
+            _ => self.details.close(),
+        }
+    }
+
+    /// Updates the UI with a new list of completions matching `prefix`,
+    /// selecting the one at index `selected`.
+    pub(crate) fn update(
+        &mut self,
+        completions: &[MatchedCompletion],
+        prefix: &str,
+        selected: Option<usize>,
+    ) -> nvim::Result<()> {
+        self.menu.update(completions, prefix, selected)?;
+
+        // The menu could have moved, so the details window is moved with it.
+        // It's only closed if the selected completion is gone.
+        let selected = selected.map(|idx| &completions[idx]);
+        self.show_details(selected.and_then(|c| c.item.details()))
+    }
+}
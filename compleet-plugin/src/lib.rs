@@ -1,12 +1,20 @@
 use compleet_core as compleet;
 use compleet_lipsum;
-use nvim_oxi::{self as nvim, Dictionary};
+use compleet_lsp;
+use nvim_oxi::{self as nvim, Dictionary, Function, Object};
 
 #[nvim::module]
 fn compleet() -> nvim::Result<Dictionary> {
     let client = compleet::Client::new();
 
-    // client.register_source(compleet_lipsum::Lipsum);
+    client.register_source(compleet_lipsum::Lipsum::default());
+    client.register_source(compleet_lsp::Lsp::default());
 
-    Ok(client.build_api())
+    let lsp_client_capabilities =
+        Function::from_fn(|()| compleet_lsp::client_capabilities());
+
+    Ok(Dictionary::from_iter(client.build_api().into_iter().chain([(
+        nvim::String::from("lsp_client_capabilities"),
+        Object::from(lsp_client_capabilities),
+    )])))
 }
@@ -0,0 +1,9 @@
+use serde::Deserialize;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LipsumConfig {
+    /// Whether the source is enabled. Defaults to `false`.
+    #[serde(default)]
+    pub(crate) enable: bool,
+}
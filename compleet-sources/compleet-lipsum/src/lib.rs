@@ -1,3 +1,10 @@
+mod config;
+mod lipsum;
+mod lorems;
+
+pub use config::LipsumConfig;
+pub use lipsum::Lipsum;
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -0,0 +1,57 @@
+use compleet_core::{
+    async_trait,
+    CancellationToken,
+    CompletionContext,
+    CompletionItem,
+    CompletionList,
+    CompletionSource,
+    ShouldAttach,
+    SourceResult,
+};
+use nvim_oxi::api::Buffer;
+
+use crate::lorems::{LOREMS, LOREM_IPSUM};
+use crate::LipsumConfig;
+
+#[derive(Debug, Default)]
+pub struct Lipsum {
+    config: LipsumConfig,
+}
+
+#[async_trait]
+impl CompletionSource for Lipsum {
+    type Config = LipsumConfig;
+
+    #[inline]
+    fn name(&self) -> &'static str {
+        "lipsum"
+    }
+
+    fn setup(&mut self, config: LipsumConfig) -> SourceResult<()> {
+        self.config = config;
+        Ok(())
+    }
+
+    fn should_attach(&self, _: &Buffer) -> SourceResult<ShouldAttach> {
+        Ok(self.config.enable)
+    }
+
+    async fn complete(
+        &self,
+        _: &CompletionContext,
+        _: &CancellationToken,
+    ) -> SourceResult<CompletionList> {
+        // // Simulate a slow source, this shouldn't block.
+        // tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+
+        Ok(LOREMS
+            .iter()
+            .map(|&lorem| {
+                CompletionItem::builder(lorem)
+                    .details_text(LOREM_IPSUM)
+                    .build()
+            })
+            .collect::<Vec<_>>()
+            .into())
+    }
+}
@@ -0,0 +1,9 @@
+use serde::Deserialize;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LspConfig {
+    /// Whether the source is enabled. Defaults to `false`.
+    #[serde(default)]
+    pub(crate) enable: bool,
+}
@@ -0,0 +1,27 @@
+pub(crate) mod icon {
+    pub(crate) const TEXT: char = '';
+    pub(crate) const METHOD: char = '';
+    pub(crate) const FUNCTION: char = '';
+    pub(crate) const CONSTRUCTOR: char = '';
+    pub(crate) const FIELD: char = 'ﰠ';
+    pub(crate) const VARIABLE: char = '';
+    pub(crate) const CLASS: char = 'ﴯ';
+    pub(crate) const INTERFACE: char = '';
+    pub(crate) const MODULE: char = '';
+    pub(crate) const PROPERTY: char = 'ﰠ';
+    pub(crate) const UNIT: char = '塞';
+    pub(crate) const VALUE: char = '';
+    pub(crate) const ENUM: char = '';
+    pub(crate) const KEYWORD: char = '';
+    pub(crate) const SNIPPET: char = '';
+    pub(crate) const COLOR: char = '';
+    pub(crate) const FILE: char = '';
+    pub(crate) const REFERENCE: char = '';
+    pub(crate) const FOLDER: char = '';
+    pub(crate) const ENUM_MEMBER: char = '';
+    pub(crate) const CONSTANT: char = '';
+    pub(crate) const STRUCT: char = 'פּ';
+    pub(crate) const EVENT: char = '';
+    pub(crate) const OPERATOR: char = '';
+    pub(crate) const TYPE_PARAMETER: char = '';
+}
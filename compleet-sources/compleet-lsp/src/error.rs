@@ -0,0 +1,15 @@
+use nvim_oxi as nvim;
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum Error {
+    #[error(transparent)]
+    NvimError(#[from] nvim::Error),
+
+    #[error(
+        "language server \"{server}\" responded with an error: {message}"
+    )]
+    ServerError { server: String, message: String },
+
+    #[error("couldn't execute command \"{command}\": {message}")]
+    CommandError { command: String, message: String },
+}
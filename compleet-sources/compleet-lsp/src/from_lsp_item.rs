@@ -0,0 +1,424 @@
+use compleet_core::{
+    CompletionItem,
+    CompletionItemKind,
+    Details,
+    Position,
+    PositionEncoding,
+    ResolvedItem,
+    TextEdit,
+    TextRange,
+};
+use nvim_oxi::api::Buffer;
+
+use crate::constants::icon;
+use crate::lsp_client::LspClient;
+use crate::protocol::{
+    CompletionItem as LspCompletionItem,
+    CompletionItemDefaults,
+    CompletionItemDocumentation,
+    CompletionItemKind as LspCompletionItemKind,
+    CompletionItemTag,
+    CompletionItemTextEdit,
+    EditRangeDefault,
+    InsertReplaceEdit,
+    InsertTextFormat,
+    MarkupKind,
+    PositionEncodingKind,
+    Range,
+    TextEdit as LspTextEdit,
+};
+
+/// The data attached to every completion item, used to resolve it.
+pub(crate) struct ResolveData {
+    /// The id of the client whose server returned the item.
+    pub(crate) client_id: u32,
+
+    /// The item as it was sent by the server.
+    pub(crate) raw: serde_json::Value,
+}
+
+impl From<PositionEncodingKind> for PositionEncoding {
+    fn from(kind: PositionEncodingKind) -> Self {
+        match kind {
+            PositionEncodingKind::Utf8 => Self::Utf8,
+            PositionEncodingKind::Utf16 => Self::Utf16,
+            PositionEncodingKind::Utf32 => Self::Utf32,
+        }
+    }
+}
+
+impl From<Range> for TextRange {
+    fn from(range: Range) -> Self {
+        let position = |pos: crate::protocol::Position| Position {
+            row: pos.line as usize,
+            col: pos.character as usize,
+        };
+
+        Self { start: position(range.start), end: position(range.end) }
+    }
+}
+
+impl From<LspTextEdit> for TextEdit {
+    fn from(edit: LspTextEdit) -> Self {
+        Self { range: edit.range.into(), new_text: edit.new_text }
+    }
+}
+
+impl CompletionItemDefaults {
+    /// Fills the fields of `item` that the server left out with the default
+    /// values of its list.
+    pub(crate) fn apply_to(&self, item: &mut LspCompletionItem) {
+        if item.commit_characters.is_none() {
+            item.commit_characters = self.commit_characters.clone();
+        }
+
+        if item.insert_text_format.is_none() {
+            item.insert_text_format = self.insert_text_format;
+        }
+
+        // The default range is only used by the items without a text edit,
+        // whose text is given by their `textEditText`.
+        if let (None, Some(range)) = (&item.text_edit, self.edit_range) {
+            let new_text = item
+                .text_edit_text
+                .take()
+                .or_else(|| item.insert_text.clone())
+                .unwrap_or_else(|| item.label.clone());
+
+            item.text_edit = Some(match range {
+                EditRangeDefault::Range(range) => {
+                    CompletionItemTextEdit::TextEdit(LspTextEdit {
+                        range,
+                        new_text,
+                    })
+                },
+                EditRangeDefault::InsertReplace { insert, replace } => {
+                    CompletionItemTextEdit::InsertReplaceEdit(
+                        InsertReplaceEdit { new_text, insert, replace },
+                    )
+                },
+            });
+        }
+
+        // The data has to be sent back to the server when resolving the
+        // item, so it's added to the raw item.
+        if let (Some(data), Some(raw)) = (&self.data, item.raw.as_object_mut())
+        {
+            raw.entry("data").or_insert_with(|| data.clone());
+        }
+    }
+}
+
+/// Converts a completion item coming from the server of the client with id
+/// `client_id` into a [`CompletionItem`]. The `encoding` is the one used by
+/// the server to measure the columns of the item's edits.
+pub(crate) fn from_lsp_item(
+    lsp_item: LspCompletionItem,
+    filetype: &str,
+    client_id: u32,
+    encoding: PositionEncodingKind,
+) -> CompletionItem {
+    let (text, ranges) = match lsp_item.text_edit {
+        Some(CompletionItemTextEdit::TextEdit(edit)) => {
+            (edit.new_text, Some((edit.range, edit.range)))
+        },
+        Some(CompletionItemTextEdit::InsertReplaceEdit(edit)) => {
+            (edit.new_text, Some((edit.insert, edit.replace)))
+        },
+        None => (
+            lsp_item.insert_text.unwrap_or_else(|| lsp_item.label.clone()),
+            None,
+        ),
+    };
+
+    let mut builder = CompletionItem::builder(text);
+
+    builder
+        .position_encoding(encoding.into())
+        .data(ResolveData { client_id, raw: lsp_item.raw });
+
+    if let Some((insert, replace)) = ranges {
+        builder.insert_range(insert.into()).replace_range(replace.into());
+    }
+
+    for edit in lsp_item.additional_text_edits.into_iter().flatten() {
+        builder.additional_edit(edit.into());
+    }
+
+    if let Some(chars) = lsp_item.commit_characters {
+        builder
+            .commit_characters(chars.iter().filter_map(|c| c.chars().next()));
+    }
+
+    builder.label(lsp_item.label).is_snippet(matches!(
+        lsp_item.insert_text_format,
+        Some(InsertTextFormat::Snippet)
+    ));
+
+    let is_deprecated = lsp_item.deprecated.unwrap_or(false)
+        || lsp_item
+            .tags
+            .iter()
+            .flatten()
+            .any(|&tag| tag == CompletionItemTag::Deprecated);
+
+    builder.is_deprecated(is_deprecated);
+
+    // The detail of the label is shown right after it, and its description
+    // on the right of the menu. Servers that don't send the label details
+    // usually put the type of the item in its `detail`.
+    match lsp_item.label_details {
+        Some(label_details) => {
+            if let Some(detail) = label_details.detail {
+                builder.label_detail(detail);
+            }
+            if let Some(description) = label_details.description {
+                builder.infos(description);
+            }
+        },
+
+        None => {
+            if let Some(detail) =
+                lsp_item.detail.as_deref().filter(|d| !d.contains('\n'))
+            {
+                builder.infos(detail);
+            }
+        },
+    }
+
+    // The command is executed by the client that returned the item.
+    if let Some(command) = lsp_item.command {
+        builder.post_insert_callback(move |_| {
+            let buf = Buffer::current();
+            LspClient::execute_command(client_id, &buf, &command)
+                .map_err(Into::into)
+        });
+    }
+
+    if let Some(kind) = lsp_item.kind {
+        let (kind, icon) = self::kind_and_icon(kind);
+        builder.kind(kind).icon(icon);
+    }
+
+    if let Some(sort_text) = lsp_item.sort_text {
+        builder.sort_text(sort_text);
+    }
+
+    if let Some((details, filetype)) =
+        self::details(lsp_item.documentation, lsp_item.detail, filetype)
+    {
+        builder.details_text(details).details_ft(filetype);
+    }
+
+    builder.build()
+}
+
+/// Converts a completion item resolved by a language server into the
+/// informations added to the original [`CompletionItem`].
+pub(crate) fn resolved_item(
+    lsp_item: LspCompletionItem,
+    filetype: &str,
+) -> ResolvedItem {
+    let details =
+        self::details(lsp_item.documentation, lsp_item.detail, filetype).map(
+            |(text, ft)| Details {
+                text: text.lines().map(ToOwned::to_owned).collect(),
+                ft: ft.to_owned(),
+            },
+        );
+
+    let additional_edits = lsp_item
+        .additional_text_edits
+        .into_iter()
+        .flatten()
+        .map(Into::into)
+        .collect();
+
+    ResolvedItem { details, additional_edits }
+}
+
+/// Returns the text displayed in the details window of an item together with
+/// the filetype used to highlight it. The item's documentation is preferred
+/// over its detail, which is highlighted with the buffer's `filetype`.
+fn details(
+    documentation: Option<CompletionItemDocumentation>,
+    detail: Option<String>,
+    filetype: &str,
+) -> Option<(String, &str)> {
+    match documentation {
+        Some(CompletionItemDocumentation::String(str)) => Some((str, "")),
+
+        Some(CompletionItemDocumentation::MarkupContent(markup)) => Some((
+            markup.value,
+            match markup.kind {
+                MarkupKind::PlainText => "text",
+                MarkupKind::Markdown => "markdown",
+            },
+        )),
+
+        None => detail.map(|detail| (detail, filetype)),
+    }
+}
+
+fn kind_and_icon(kind: LspCompletionItemKind) -> (CompletionItemKind, char) {
+    use LspCompletionItemKind::*;
+
+    match kind {
+        Text => (CompletionItemKind::Text, icon::TEXT),
+        Method => (CompletionItemKind::Method, icon::METHOD),
+        Function => (CompletionItemKind::Function, icon::FUNCTION),
+        Constructor => (CompletionItemKind::Constructor, icon::CONSTRUCTOR),
+        Field => (CompletionItemKind::Field, icon::FIELD),
+        Variable => (CompletionItemKind::Variable, icon::VARIABLE),
+        Class => (CompletionItemKind::Class, icon::CLASS),
+        Interface => (CompletionItemKind::Interface, icon::INTERFACE),
+        Module => (CompletionItemKind::Module, icon::MODULE),
+        Property => (CompletionItemKind::Property, icon::PROPERTY),
+        Unit => (CompletionItemKind::Unit, icon::UNIT),
+        Value => (CompletionItemKind::Value, icon::VALUE),
+        Enum => (CompletionItemKind::Enum, icon::ENUM),
+        Keyword => (CompletionItemKind::Keyword, icon::KEYWORD),
+        Snippet => (CompletionItemKind::Snippet, icon::SNIPPET),
+        Color => (CompletionItemKind::Color, icon::COLOR),
+        File => (CompletionItemKind::File, icon::FILE),
+        Reference => (CompletionItemKind::Reference, icon::REFERENCE),
+        Folder => (CompletionItemKind::Folder, icon::FOLDER),
+        EnumMember => (CompletionItemKind::EnumMember, icon::ENUM_MEMBER),
+        Constant => (CompletionItemKind::Constant, icon::CONSTANT),
+        Struct => (CompletionItemKind::Struct, icon::STRUCT),
+        Event => (CompletionItemKind::Event, icon::EVENT),
+        Operator => (CompletionItemKind::Operator, icon::OPERATOR),
+        TypeParameter => {
+            (CompletionItemKind::TypeParameter, icon::TYPE_PARAMETER)
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::protocol::CompletionList;
+
+    /// Deserializes a completion list and applies its defaults to its items.
+    fn apply_defaults(list: Value) -> Vec<LspCompletionItem> {
+        let mut list = serde_json::from_value::<CompletionList>(list).unwrap();
+        let defaults = list.item_defaults.take().unwrap();
+        for item in &mut list.items {
+            defaults.apply_to(item);
+        }
+        list.items
+    }
+
+    fn range(start: u32, end: u32) -> Value {
+        json!({
+            "start": { "line": 0, "character": start },
+            "end": { "line": 0, "character": end },
+        })
+    }
+
+    fn cols(range: &Range) -> (u32, u32) {
+        (range.start.character, range.end.character)
+    }
+
+    /// Returns the new text and the range of a plain text edit.
+    fn text_edit(item: &LspCompletionItem) -> (&str, (u32, u32)) {
+        match &item.text_edit {
+            Some(CompletionItemTextEdit::TextEdit(edit)) => {
+                (&edit.new_text, cols(&edit.range))
+            },
+            other => panic!("not a text edit: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn edit_range() {
+        let items = apply_defaults(json!({
+            "isIncomplete": false,
+            "itemDefaults": { "editRange": range(2, 5) },
+            "items": [{ "label": "foo" }],
+        }));
+        assert_eq!(("foo", (2, 5)), text_edit(&items[0]));
+    }
+
+    #[test]
+    fn insert_replace_edit_range() {
+        let items = apply_defaults(json!({
+            "isIncomplete": false,
+            "itemDefaults": {
+                "editRange": { "insert": range(2, 4), "replace": range(2, 6) },
+            },
+            "items": [{ "label": "foo" }],
+        }));
+        match &items[0].text_edit {
+            Some(CompletionItemTextEdit::InsertReplaceEdit(edit)) => {
+                assert_eq!("foo", edit.new_text);
+                assert_eq!((2, 4), cols(&edit.insert));
+                assert_eq!((2, 6), cols(&edit.replace));
+            },
+            other => panic!("not an insert/replace edit: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn new_text_precedence() {
+        let items = apply_defaults(json!({
+            "isIncomplete": false,
+            "itemDefaults": { "editRange": range(0, 1) },
+            "items": [
+                { "label": "a", "insertText": "b", "textEditText": "c" },
+                { "label": "a", "insertText": "b" },
+                { "label": "a" },
+            ],
+        }));
+        let new_texts =
+            items.iter().map(|item| text_edit(item).0).collect::<Vec<_>>();
+        assert_eq!(vec!["c", "b", "a"], new_texts);
+    }
+
+    #[test]
+    fn text_edit_is_kept() {
+        let items = apply_defaults(json!({
+            "isIncomplete": false,
+            "itemDefaults": { "editRange": range(0, 1) },
+            "items": [{
+                "label": "foo",
+                "textEdit": { "range": range(3, 4), "newText": "bar" },
+                "textEditText": "baz",
+            }],
+        }));
+        assert_eq!(("bar", (3, 4)), text_edit(&items[0]));
+    }
+
+    #[test]
+    fn data_is_merged_into_raw() {
+        let items = apply_defaults(json!({
+            "isIncomplete": false,
+            "itemDefaults": { "data": { "id": 1 } },
+            "items": [
+                { "label": "a" },
+                { "label": "b", "data": { "id": 2 } },
+            ],
+        }));
+        assert_eq!(json!({ "id": 1 }), items[0].raw["data"]);
+        assert_eq!(json!({ "id": 2 }), items[1].raw["data"]);
+        assert!(items[0].text_edit.is_none());
+    }
+
+    #[test]
+    fn unknown_tags_are_ignored() {
+        let item = serde_json::from_value::<LspCompletionItem>(json!({
+            "label": "foo",
+            "tags": [1, 42],
+        }))
+        .unwrap();
+        assert_eq!(
+            Some(vec![
+                CompletionItemTag::Deprecated,
+                CompletionItemTag::Unknown
+            ]),
+            item.tags
+        );
+    }
+}
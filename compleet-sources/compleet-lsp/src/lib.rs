@@ -0,0 +1,12 @@
+mod config;
+mod constants;
+mod error;
+mod from_lsp_item;
+mod lsp;
+mod lsp_client;
+mod main_thread;
+mod protocol;
+
+pub use config::LspConfig;
+pub use lsp::Lsp;
+pub use lsp_client::client_capabilities;
@@ -0,0 +1,396 @@
+use compleet_core::{
+    async_trait,
+    CancellationToken,
+    CompletionContext,
+    CompletionItem,
+    CompletionList,
+    CompletionSource,
+    ResolvedItem,
+    ShouldAttach,
+    SourceResult,
+    TriggerKind,
+};
+use nvim_oxi::{self as nvim, api::Buffer, object, Function, Object};
+use serde::{de::DeserializeOwned, Deserialize};
+use tokio::sync::mpsc::{self, UnboundedSender};
+
+use crate::error::Error;
+use crate::from_lsp_item::{from_lsp_item, resolved_item, ResolveData};
+use crate::lsp_client::{self, LspClient};
+use crate::main_thread::MainThread;
+use crate::protocol::{
+    CompletionContext as LspCompletionContext,
+    CompletionItem as LspCompletionItem,
+    CompletionParams,
+    CompletionResponse,
+    CompletionTriggerKind,
+    Position,
+    PositionEncodingKind,
+    ResponseError,
+    TextDocumentIdentifier,
+    TextDocumentPositionParams,
+};
+use crate::LspConfig;
+
+/// The response of a server, sent from the main thread together with the id
+/// of the client that received it and the encoding it uses for positions.
+type Response =
+    (u32, PositionEncodingKind, Result<Option<CompletionResponse>, Error>);
+
+/// The response of a server to a `completionItem/resolve` request.
+type ResolveResponse = Result<Option<LspCompletionItem>, Error>;
+
+/// A completion request that's been sent to a server and is waiting for its
+/// response.
+struct PendingRequest {
+    client_id: u32,
+    request_id: u32,
+}
+
+#[derive(Default)]
+pub struct Lsp {
+    config: LspConfig,
+
+    /// Used to talk to the language servers, which can only be done from
+    /// Neovim's main thread. Only set if the source is enabled.
+    main_thread: Option<MainThread>,
+}
+
+#[async_trait]
+impl CompletionSource for Lsp {
+    type Config = LspConfig;
+
+    #[inline]
+    fn name(&self) -> &'static str {
+        "lsp"
+    }
+
+    fn setup(&mut self, config: LspConfig) -> SourceResult<()> {
+        if config.enable {
+            self.main_thread = Some(MainThread::new()?);
+        }
+        self.config = config;
+        Ok(())
+    }
+
+    fn should_attach(&self, _: &Buffer) -> SourceResult<ShouldAttach> {
+        // Language servers are usually attached after the buffer has been
+        // entered, so we look for the attached clients when completing.
+        Ok(self.config.enable)
+    }
+
+    fn trigger_characters(&self, buf: &Buffer) -> SourceResult<Vec<String>> {
+        if !self.config.enable {
+            return Ok(Vec::new());
+        }
+
+        let mut triggers = LspClient::attached_to(buf)?
+            .into_iter()
+            .flat_map(|client| client.trigger_characters)
+            .collect::<Vec<_>>();
+
+        triggers.sort();
+        triggers.dedup();
+
+        Ok(triggers)
+    }
+
+    async fn complete(
+        &self,
+        ctx: &CompletionContext,
+        cancel: &CancellationToken,
+    ) -> SourceResult<CompletionList> {
+        let main_thread =
+            self.main_thread.as_ref().expect("the source is enabled");
+
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+
+        let mut pending = main_thread
+            .run({
+                let ctx = ctx.clone();
+                move || self::send_requests(&ctx, sender)
+            })
+            .await?;
+
+        let filetype = ctx.filetype();
+        let mut list = CompletionList::default();
+        let mut error = None;
+
+        while !pending.is_empty() {
+            tokio::select! {
+                biased;
+
+                _ = cancel.cancelled() => {
+                    // Let the servers know they can stop computing the
+                    // completions.
+                    main_thread.spawn(move || {
+                        for PendingRequest { client_id, request_id } in pending
+                        {
+                            let _ = LspClient::cancel_request(
+                                client_id, request_id,
+                            );
+                        }
+                    });
+
+                    return Ok(CompletionList::default());
+                },
+
+                response = receiver.recv() => {
+                    let (client_id, encoding, response) = match response {
+                        Some(response) => response,
+
+                        // The handlers of the remaining requests have been
+                        // dropped without ever being called.
+                        None => break,
+                    };
+
+                    pending.retain(|req| req.client_id != client_id);
+
+                    let convert = |item| {
+                        from_lsp_item(item, filetype, client_id, encoding)
+                    };
+
+                    match response {
+                        Ok(Some(CompletionResponse::List(mut response))) => {
+                            list.is_incomplete |= response.is_incomplete;
+
+                            if let Some(defaults) = &response.item_defaults {
+                                for item in &mut response.items {
+                                    defaults.apply_to(item);
+                                }
+                            }
+
+                            list.items.extend(
+                                response.items.into_iter().map(convert),
+                            );
+                        },
+
+                        Ok(Some(CompletionResponse::Array(items))) => {
+                            list.items.extend(items.into_iter().map(convert));
+                        },
+
+                        Ok(None) => {},
+
+                        Err(err) => error = Some(err),
+                    }
+                },
+            }
+        }
+
+        // A failing server shouldn't hide the completions of the other ones.
+        match error {
+            Some(err) if list.items.is_empty() => Err(err.into()),
+            _ => Ok(list),
+        }
+    }
+
+    async fn resolve(
+        &self,
+        item: &CompletionItem,
+        cancel: &CancellationToken,
+    ) -> SourceResult<Option<ResolvedItem>> {
+        let data = match item.data::<ResolveData>() {
+            Some(data) => data,
+            None => return Ok(None),
+        };
+
+        let main_thread =
+            self.main_thread.as_ref().expect("the source is enabled");
+
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+
+        let client_id = data.client_id;
+
+        let (request_id, filetype) = match main_thread
+            .run({
+                let raw = data.raw.clone();
+                move || self::send_resolve_request(client_id, &raw, sender)
+            })
+            .await?
+        {
+            Some(request) => request,
+            None => return Ok(None),
+        };
+
+        tokio::select! {
+            biased;
+
+            _ = cancel.cancelled() => {
+                main_thread.spawn(move || {
+                    let _ = LspClient::cancel_request(client_id, request_id);
+                });
+
+                Ok(None)
+            },
+
+            response = receiver.recv() => match response {
+                Some(Ok(Some(lsp_item))) => {
+                    Ok(Some(resolved_item(lsp_item, &filetype)))
+                },
+
+                Some(Err(err)) => Err(err.into()),
+
+                // The server had nothing to add, or the handler has been
+                // dropped without ever being called.
+                Some(Ok(None)) | None => Ok(None),
+            },
+        }
+    }
+}
+
+/// Sends a completion request to every client attached to the buffer of
+/// `ctx`. The servers' responses are sent through `sender`.
+fn send_requests(
+    ctx: &CompletionContext,
+    sender: UnboundedSender<Response>,
+) -> nvim::Result<Vec<PendingRequest>> {
+    let clients = LspClient::attached_to(ctx.buffer())?;
+
+    if clients.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let uri = lsp_client::uri(ctx.buffer())?;
+
+    let mut pending = Vec::with_capacity(clients.len());
+
+    for client in clients {
+        let params = self::make_completion_params(ctx, uri.clone(), &client);
+
+        let handler = {
+            let client_id = client.id;
+            let encoding = client.offset_encoding;
+            let server = client.name.clone();
+            let sender = sender.clone();
+
+            Function::from_fn(move |(err, result): (Object, Object)| {
+                let response = self::parse_response(&server, err, result);
+                // The receiver is dropped if the request has been cancelled
+                // in the meantime.
+                let _ = sender.send((client_id, encoding, response));
+                Ok::<_, nvim::Error>(())
+            })
+        };
+
+        if let Some(request_id) =
+            client.request_completions(ctx.buffer(), &params, handler)?
+        {
+            pending.push(PendingRequest { client_id: client.id, request_id });
+        }
+    }
+
+    Ok(pending)
+}
+
+/// Sends a `completionItem/resolve` request for the `raw` item to the server
+/// of the client with id `client_id`, if it's still attached to the current
+/// buffer and it can resolve items. The server's response is sent through
+/// `sender`.
+///
+/// Returns the id of the request together with the filetype of the buffer,
+/// or `None` if the request wasn't sent.
+fn send_resolve_request(
+    client_id: u32,
+    raw: &serde_json::Value,
+    sender: UnboundedSender<ResolveResponse>,
+) -> nvim::Result<Option<(u32, String)>> {
+    let buf = Buffer::current();
+
+    let client = match LspClient::attached_to(&buf)?
+        .into_iter()
+        .find(|client| client.id == client_id)
+    {
+        Some(client) if client.resolve_provider => client,
+        _ => return Ok(None),
+    };
+
+    let handler = {
+        let server = client.name.clone();
+
+        Function::from_fn(move |(err, result): (Object, Object)| {
+            let _ = sender.send(self::parse_response(&server, err, result));
+            Ok::<_, nvim::Error>(())
+        })
+    };
+
+    let request_id = match client.resolve_completion(&buf, raw, handler)? {
+        Some(request_id) => request_id,
+        None => return Ok(None),
+    };
+
+    let filetype = buf.get_option::<String>("filetype")?;
+
+    Ok(Some((request_id, filetype)))
+}
+
+fn make_completion_params(
+    ctx: &CompletionContext,
+    uri: String,
+    client: &LspClient,
+) -> CompletionParams {
+    let character = match client.offset_encoding {
+        PositionEncodingKind::Utf8 => ctx.col(),
+        PositionEncodingKind::Utf16 => ctx.col_utf16(),
+        PositionEncodingKind::Utf32 => ctx.col_utf32(),
+    } as u32;
+
+    let context = match ctx.trigger_kind() {
+        // The completion could have been triggered by a character that's
+        // only a trigger for another server.
+        TriggerKind::TriggerCharacter(char)
+            if client
+                .trigger_characters
+                .iter()
+                .any(|trigger| trigger.ends_with(char)) =>
+        {
+            LspCompletionContext {
+                trigger_kind: CompletionTriggerKind::TriggerCharacter,
+                trigger_character: Some(char),
+            }
+        },
+
+        _ => LspCompletionContext {
+            trigger_kind: CompletionTriggerKind::Invoked,
+            trigger_character: None,
+        },
+    };
+
+    CompletionParams {
+        text_document_position_params: TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier { uri },
+            position: Position { line: ctx.row() as u32, character },
+        },
+        context: Some(context),
+    }
+}
+
+/// Parses the `err` and `result` fields of a server's response to a
+/// completion or resolve request.
+fn parse_response<T: DeserializeOwned>(
+    server: &str,
+    err: Object,
+    result: Object,
+) -> Result<Option<T>, Error> {
+    let err =
+        Option::<ResponseError>::deserialize(object::Deserializer::new(err))?;
+
+    match err {
+        // These are expected if the user keeps typing while the server is
+        // computing the completions.
+        Some(err)
+            if err.code == ResponseError::REQUEST_CANCELLED
+                || err.code == ResponseError::CONTENT_MODIFIED =>
+        {
+            Ok(None)
+        },
+
+        Some(err) => Err(Error::ServerError {
+            server: server.to_owned(),
+            message: err.message,
+        }),
+
+        None => {
+            Ok(Option::<T>::deserialize(object::Deserializer::new(result))?)
+        },
+    }
+}
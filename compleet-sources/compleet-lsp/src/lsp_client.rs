@@ -0,0 +1,283 @@
+use nvim_oxi::{
+    self as nvim,
+    api::{self, Buffer},
+    object,
+    Dictionary,
+    Function,
+    Object,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::protocol::{
+    Command,
+    CompletionParams,
+    PositionEncodingKind,
+    ResponseError,
+};
+
+/// Returns the clients attached to the buffer `_A` whose server can provide
+/// completions.
+const ATTACHED_CLIENTS: &str = r#"
+(function(bufnr)
+  local clients = {}
+  for _, client in pairs(vim.lsp.get_active_clients({ bufnr = bufnr })) do
+    local provider = client.server_capabilities.completionProvider
+    if provider then
+      local triggers = provider.triggerCharacters or {}
+      table.insert(clients, {
+        id = client.id,
+        name = client.name,
+        offset_encoding = client.offset_encoding,
+        resolve_provider = provider.resolveProvider or false,
+        trigger_characters = #triggers > 0 and triggers or nil,
+      })
+    end
+  end
+  return clients
+end)(_A)
+"#;
+
+/// Sends a request to a client, returning its id or `nil` if the request
+/// couldn't be sent. The handler is wrapped in a Lua function since it's
+/// only interested in the first two arguments.
+const REQUEST: &str = r#"
+(function(args)
+  local client = vim.lsp.get_client_by_id(args.client_id)
+  if not client then
+    return nil
+  end
+  local ok, request_id = client.request(
+    args.method,
+    args.params,
+    function(err, result) args.handler(err, result) end,
+    args.bufnr
+  )
+  return ok and request_id or nil
+end)(_A)
+"#;
+
+/// Sends a `$/cancelRequest` notification to the server of a client.
+const CANCEL_REQUEST: &str = r#"
+(function(args)
+  local client = vim.lsp.get_client_by_id(args.client_id)
+  if client then
+    client.cancel_request(args.request_id)
+  end
+end)(_A)
+"#;
+
+/// Executes a command with the function registered for it by the client or
+/// by Neovim, falling back to sending a `workspace/executeCommand` request
+/// to the server if it supports the command. Returns an error message if
+/// the command couldn't be executed.
+const EXECUTE_COMMAND: &str = r#"
+(function(args)
+  local client = vim.lsp.get_client_by_id(args.client_id)
+  if not client then
+    return "the language server is not running anymore"
+  end
+  local command = args.command
+  local fn = client.commands[command.command]
+    or vim.lsp.commands[command.command]
+  if fn then
+    local ctx = { bufnr = args.bufnr, client_id = client.id }
+    local ok, err = pcall(fn, command, ctx)
+    return not ok and tostring(err) or nil
+  end
+  local provider = client.server_capabilities.executeCommandProvider
+  local supported = provider and provider.commands or {}
+  if not vim.tbl_contains(supported, command.command) then
+    return "the command is not supported by " .. client.name
+  end
+  local ok = client.request(
+    "workspace/executeCommand",
+    command,
+    function(err, result) args.handler(err, result) end,
+    args.bufnr
+  )
+  return not ok and "the request couldn't be sent" or nil
+end)(_A)
+"#;
+
+/// Returns the capabilities of Neovim's LSP client extended with the
+/// completion features supported by the source.
+const CLIENT_CAPABILITIES: &str = r#"
+(function()
+  local capabilities = vim.lsp.protocol.make_client_capabilities()
+  local completion = capabilities.textDocument.completion
+  completion.contextSupport = true
+  completion.completionItem.snippetSupport = true
+  completion.completionItem.commitCharactersSupport = true
+  completion.completionItem.insertReplaceSupport = true
+  completion.completionItem.labelDetailsSupport = true
+  completion.completionItem.deprecatedSupport = true
+  completion.completionItem.tagSupport = { valueSet = { 1 } }
+  completion.completionItem.resolveSupport = {
+    properties = { "documentation", "detail", "additionalTextEdits" },
+  }
+  completion.completionList = {
+    itemDefaults = {
+      "commitCharacters", "editRange", "insertTextFormat", "data",
+    },
+  }
+  return capabilities
+end)()
+"#;
+
+/// The handler called with the `err` and `result` fields of a server's
+/// response.
+pub(crate) type Handler = Function<(Object, Object), ()>;
+
+/// A language server client managed by Neovim's builtin LSP client (see `:h
+/// vim.lsp.client`).
+///
+/// Neovim's LSP clients live in Lua, so all the methods have to be called on
+/// the main thread.
+#[derive(Debug, Deserialize)]
+pub(crate) struct LspClient {
+    pub(crate) id: u32,
+    pub(crate) name: String,
+    pub(crate) offset_encoding: PositionEncodingKind,
+
+    /// Whether the server can resolve additional informations about its
+    /// completion items, taken from its `completionProvider.resolveProvider`
+    /// capability.
+    #[serde(default)]
+    pub(crate) resolve_provider: bool,
+
+    /// The characters that trigger a completion request, taken from the
+    /// server's `completionProvider.triggerCharacters` capability.
+    #[serde(default)]
+    pub(crate) trigger_characters: Vec<String>,
+}
+
+impl LspClient {
+    /// Returns the clients attached to `buf` that can provide completions.
+    pub(crate) fn attached_to(buf: &Buffer) -> nvim::Result<Vec<Self>> {
+        self::luaeval(ATTACHED_CLIENTS, buf.clone())
+    }
+
+    /// Asks the server to cancel the request with id `request_id`.
+    pub(crate) fn cancel_request(
+        client_id: u32,
+        request_id: u32,
+    ) -> nvim::Result<()> {
+        let args = Dictionary::from_iter([
+            ("client_id", Object::from(client_id)),
+            ("request_id", Object::from(request_id)),
+        ]);
+
+        self::luaeval(CANCEL_REQUEST, args)
+    }
+
+    /// Executes `command` through the client with id `client_id`. Errors
+    /// returned by the server are echoed as warnings once it responds.
+    pub(crate) fn execute_command(
+        client_id: u32,
+        buf: &Buffer,
+        command: &Command,
+    ) -> Result<(), Error> {
+        let handler = {
+            let command = command.command.clone();
+
+            Function::from_fn(move |(err, _): (Object, Object)| {
+                let err = Option::<ResponseError>::deserialize(
+                    object::Deserializer::new(err),
+                )?;
+
+                if let Some(err) = err {
+                    let err = Error::CommandError {
+                        command: command.clone(),
+                        message: err.message,
+                    };
+                    compleet_core::echowarn!("{err}");
+                }
+
+                Ok::<_, nvim::Error>(())
+            })
+        };
+
+        let args = Dictionary::from_iter([
+            ("client_id", Object::from(client_id)),
+            ("command", command.serialize(object::Serializer::new())?),
+            ("handler", Object::from(handler)),
+            ("bufnr", Object::from(buf.clone())),
+        ]);
+
+        match self::luaeval::<_, Option<String>>(EXECUTE_COMMAND, args)? {
+            Some(message) => Err(Error::CommandError {
+                command: command.command.clone(),
+                message,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Sends a `textDocument/completion` request to the server, returning
+    /// the id of the request or `None` if it couldn't be sent. The `handler`
+    /// is called once the server responds.
+    pub(crate) fn request_completions(
+        &self,
+        buf: &Buffer,
+        params: &CompletionParams,
+        handler: Handler,
+    ) -> nvim::Result<Option<u32>> {
+        self.request(buf, "textDocument/completion", params, handler)
+    }
+
+    /// Sends a `completionItem/resolve` request for the raw completion `item`
+    /// to the server, returning the id of the request or `None` if it
+    /// couldn't be sent. The `handler` is called once the server responds.
+    pub(crate) fn resolve_completion(
+        &self,
+        buf: &Buffer,
+        item: &serde_json::Value,
+        handler: Handler,
+    ) -> nvim::Result<Option<u32>> {
+        self.request(buf, "completionItem/resolve", item, handler)
+    }
+
+    fn request<P: Serialize>(
+        &self,
+        buf: &Buffer,
+        method: &str,
+        params: &P,
+        handler: Handler,
+    ) -> nvim::Result<Option<u32>> {
+        let args = Dictionary::from_iter([
+            ("client_id", Object::from(self.id)),
+            ("method", Object::from(method)),
+            ("params", params.serialize(object::Serializer::new())?),
+            ("handler", Object::from(handler)),
+            ("bufnr", Object::from(buf.clone())),
+        ]);
+
+        self::luaeval(REQUEST, args)
+    }
+}
+
+/// Returns the capabilities of Neovim's LSP client extended with the
+/// completion features supported by the `lsp` source. They should be passed
+/// to the configurations of the language servers so that the servers know
+/// they can send e.g. snippets and commit characters.
+pub fn client_capabilities() -> nvim::Result<Object> {
+    api::call_function("luaeval", (CLIENT_CAPABILITIES,))
+}
+
+/// Returns the URI of the file loaded in `buf`.
+pub(crate) fn uri(buf: &Buffer) -> nvim::Result<String> {
+    self::luaeval("vim.uri_from_bufnr(_A)", buf.clone())
+}
+
+/// Evaluates the Lua expression `expr` with `_A` set to `arg`, deserializing
+/// its result.
+fn luaeval<A, R>(expr: &str, arg: A) -> nvim::Result<R>
+where
+    A: Into<Object>,
+    R: DeserializeOwned,
+{
+    let result =
+        api::call_function::<_, Object>("luaeval", (expr, arg.into()))?;
+    R::deserialize(object::Deserializer::new(result))
+}
@@ -0,0 +1,62 @@
+use nvim_oxi::{self as nvim, libuv::AsyncHandle};
+use tokio::sync::{
+    mpsc::{self, UnboundedSender},
+    oneshot,
+};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// A handle used to run closures on Neovim's main thread from the
+/// threadpool where the completions are computed.
+pub(crate) struct MainThread {
+    jobs: UnboundedSender<Job>,
+
+    /// Used to wake up Neovim's event loop when a new job has been sent.
+    signal: AsyncHandle,
+}
+
+impl MainThread {
+    /// Creates a new [`MainThread`]. Has to be called on the main thread.
+    pub(crate) fn new() -> nvim::Result<Self> {
+        let (jobs, mut receiver) = mpsc::unbounded_channel::<Job>();
+
+        let signal = AsyncHandle::new(move || {
+            while let Ok(job) = receiver.try_recv() {
+                nvim::schedule(move |_| {
+                    job();
+                    Ok(())
+                });
+            }
+            Ok::<_, nvim::Error>(())
+        })?;
+
+        Ok(Self { jobs, signal })
+    }
+
+    /// Runs `fun` on the main thread and waits for its result.
+    pub(crate) async fn run<F, R>(&self, fun: F) -> R
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (sender, receiver) = oneshot::channel();
+
+        self.spawn(move || {
+            let _ = sender.send(fun());
+        });
+
+        receiver.await.expect("scheduled jobs are always executed")
+    }
+
+    /// Schedules `fun` to be executed on the main thread without waiting for
+    /// it to complete.
+    pub(crate) fn spawn<F>(&self, fun: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        // The receiving end lives as long as `self.signal`, so this can't
+        // fail.
+        let _ = self.jobs.send(Box::new(fun));
+        let _ = self.signal.send();
+    }
+}
@@ -0,0 +1,276 @@
+//! The subset of the types defined by the LSP specification used by the
+//! source. See
+//! https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/
+//! for details.
+
+use serde::{de, Deserialize, Deserializer, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+
+pub(crate) type DocumentUri = String;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CompletionParams {
+    #[serde(flatten)]
+    pub(crate) text_document_position_params: TextDocumentPositionParams,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) context: Option<CompletionContext>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TextDocumentPositionParams {
+    pub(crate) text_document: TextDocumentIdentifier,
+    pub(crate) position: Position,
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct TextDocumentIdentifier {
+    pub(crate) uri: DocumentUri,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub(crate) struct Position {
+    pub(crate) line: u32,
+    pub(crate) character: u32,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub(crate) struct Range {
+    pub(crate) start: Position,
+    pub(crate) end: Position,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CompletionContext {
+    pub(crate) trigger_kind: CompletionTriggerKind,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) trigger_character: Option<char>,
+}
+
+#[derive(Debug, Serialize_repr)]
+#[repr(u8)]
+pub(crate) enum CompletionTriggerKind {
+    Invoked = 1,
+    TriggerCharacter = 2,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ResponseError {
+    pub(crate) code: i32,
+    pub(crate) message: String,
+}
+
+impl ResponseError {
+    /// The error code returned by servers for requests cancelled by the
+    /// client.
+    pub(crate) const REQUEST_CANCELLED: i32 = -32800;
+
+    /// The error code returned by servers for requests invalidated by a
+    /// change in the document.
+    pub(crate) const CONTENT_MODIFIED: i32 = -32801;
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CompletionItem {
+    pub(crate) label: String,
+    pub(crate) label_details: Option<CompletionItemLabelDetails>,
+    pub(crate) kind: Option<CompletionItemKind>,
+    pub(crate) tags: Option<Vec<CompletionItemTag>>,
+    pub(crate) detail: Option<String>,
+    pub(crate) documentation: Option<CompletionItemDocumentation>,
+    pub(crate) deprecated: Option<bool>,
+    pub(crate) sort_text: Option<String>,
+    pub(crate) insert_text: Option<String>,
+    pub(crate) insert_text_format: Option<InsertTextFormat>,
+    pub(crate) text_edit: Option<CompletionItemTextEdit>,
+    pub(crate) text_edit_text: Option<String>,
+    pub(crate) additional_text_edits: Option<Vec<TextEdit>>,
+    pub(crate) commit_characters: Option<Vec<String>>,
+    pub(crate) command: Option<Command>,
+
+    /// The item exactly as it was sent by the server, which is what has to
+    /// be sent back when resolving it. Only set for the items of a
+    /// `CompletionResponse`, and **not** part of the official Lsp protocol.
+    #[serde(skip)]
+    pub(crate) raw: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Command {
+    pub(crate) title: String,
+    pub(crate) command: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) arguments: Option<Vec<serde_json::Value>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct CompletionItemLabelDetails {
+    pub(crate) detail: Option<String>,
+    pub(crate) description: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize_repr)]
+#[repr(u8)]
+pub(crate) enum CompletionItemTag {
+    Deprecated = 1,
+
+    /// Any tag added by a later version of the protocol, which shouldn't make
+    /// the whole response fail to deserialize.
+    #[serde(other)]
+    Unknown = u8::MAX,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize_repr)]
+#[repr(u8)]
+pub(crate) enum CompletionItemKind {
+    Text = 1,
+    Method = 2,
+    Function = 3,
+    Constructor = 4,
+    Field = 5,
+    Variable = 6,
+    Class = 7,
+    Interface = 8,
+    Module = 9,
+    Property = 10,
+    Unit = 11,
+    Value = 12,
+    Enum = 13,
+    Keyword = 14,
+    Snippet = 15,
+    Color = 16,
+    File = 17,
+    Reference = 18,
+    Folder = 19,
+    EnumMember = 20,
+    Constant = 21,
+    Struct = 22,
+    Event = 23,
+    Operator = 24,
+    TypeParameter = 25,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize_repr)]
+#[repr(u8)]
+pub(crate) enum InsertTextFormat {
+    PlainText = 1,
+    Snippet = 2,
+}
+
+// This enum is **not** part of the official Lsp protocol.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum CompletionItemDocumentation {
+    String(String),
+    MarkupContent(MarkupContent),
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct MarkupContent {
+    pub(crate) kind: MarkupKind,
+    pub(crate) value: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum MarkupKind {
+    PlainText,
+    Markdown,
+}
+
+// This enum is **not** part of the official Lsp protocol.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum CompletionItemTextEdit {
+    TextEdit(TextEdit),
+    InsertReplaceEdit(InsertReplaceEdit),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TextEdit {
+    pub(crate) range: Range,
+    pub(crate) new_text: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct InsertReplaceEdit {
+    pub(crate) new_text: String,
+    pub(crate) insert: Range,
+    pub(crate) replace: Range,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CompletionList {
+    pub(crate) is_incomplete: bool,
+    pub(crate) item_defaults: Option<CompletionItemDefaults>,
+
+    #[serde(deserialize_with = "deser_items")]
+    pub(crate) items: Vec<CompletionItem>,
+}
+
+/// The values used for the fields that are missing from the items of a
+/// [`CompletionList`].
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CompletionItemDefaults {
+    pub(crate) commit_characters: Option<Vec<String>>,
+    pub(crate) edit_range: Option<EditRangeDefault>,
+    pub(crate) insert_text_format: Option<InsertTextFormat>,
+    pub(crate) data: Option<serde_json::Value>,
+}
+
+// This enum is **not** part of the official Lsp protocol.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum EditRangeDefault {
+    Range(Range),
+    InsertReplace { insert: Range, replace: Range },
+}
+
+// This enum is **not** part of the official Lsp protocol.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum CompletionResponse {
+    List(CompletionList),
+    Array(#[serde(deserialize_with = "deser_items")] Vec<CompletionItem>),
+}
+
+#[derive(Copy, Clone, Debug, Deserialize, Serialize)]
+pub(crate) enum PositionEncodingKind {
+    #[serde(rename = "utf-8")]
+    Utf8,
+
+    #[serde(rename = "utf-16")]
+    Utf16,
+
+    #[serde(rename = "utf-32")]
+    Utf32,
+}
+
+/// Deserializes the items of a completion response, keeping a copy of each
+/// item in its `raw` field.
+fn deser_items<'de, D>(
+    deserializer: D,
+) -> Result<Vec<CompletionItem>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Vec::<serde_json::Value>::deserialize(deserializer)?
+        .into_iter()
+        .map(|raw| {
+            let mut item = CompletionItem::deserialize(&raw)
+                .map_err(de::Error::custom)?;
+            item.raw = raw;
+            Ok(item)
+        })
+        .collect()
+}
@@ -20,14 +20,14 @@ cargo_build() {
   fi
   profile=$([ $PROFILE == debug ] && echo "" || echo --release)
   # Nightly is needed to compile (rustup toolchain install nightly) until https://github.com/rust-lang/rust/issues/79524 is merged.
-  cargo +nightly build $profile &>/dev/null
+  cargo +nightly build --package compleet-plugin $profile &>/dev/null
   return 0
 }
 
 copy_stuff() {
   # TODO: extension is `.so` on linux, `.dylib` on macOS and `.dll` on Windows
   library_extension=$(\
-    [ -f $PRJ_ROOT/target/$PROFILE/libcompleet_client.so ] \
+    [ -f $PRJ_ROOT/target/$PROFILE/libcompleet_plugin.so ] \
       && echo so \
       || echo dylib \
   )
@@ -35,7 +35,7 @@ copy_stuff() {
   # Place the compiled library where Neovim can find it.
   mkdir -p $PRJ_ROOT/lua
   cp \
-    "$PRJ_ROOT/target/$PROFILE/libcompleet_client.$library_extension" \
+    "$PRJ_ROOT/target/$PROFILE/libcompleet_plugin.$library_extension" \
     $PRJ_ROOT/lua/compleet.so
 
   # I'm not sure if copying all of the compiled library's dependencies is
@@ -0,0 +1,45 @@
+-- Called by `:checkhealth compleet`. The report is generated by the compiled
+-- library, this module only renders it.
+
+local health = vim.health
+
+-- The `report_` prefix was dropped in Neovim 0.9.
+local start = health.start or health.report_start
+local report = {
+  ok = health.ok or health.report_ok,
+  info = health.info or health.report_info,
+  warn = health.warn or health.report_warn,
+  error = health.error or health.report_error,
+}
+
+local M = {}
+
+M.check = function()
+  start("Library")
+
+  local ok, compleet = pcall(require, "compleet")
+
+  if not ok then
+    report.error("couldn't load the compiled library", {
+      "build it by running `./install.sh release` in the plugin's directory",
+    })
+    return
+  end
+
+  report.ok("the compiled library has been loaded")
+
+  for _, section in ipairs(compleet.check_health()) do
+    start(section.name)
+    for _, entry in ipairs(section.entries) do
+      -- `advice` is `vim.NIL` if missing.
+      local advice = entry.advice ~= vim.NIL and entry.advice or nil
+      if entry.level == "ok" or entry.level == "info" then
+        report[entry.level](entry.msg)
+      else
+        report[entry.level](entry.msg, advice and { advice })
+      end
+    end
+  end
+end
+
+return M